@@ -21,7 +21,7 @@
 //! A wrapper around `tracing` macros, to provide semi automatic
 //! `traceID` annotation without codebase turnover.
 
-pub use tracing::{enabled, event, Level};
+pub use tracing::{enabled, event, span, Instrument, Level};
 
 #[doc(hidden)]
 pub use jaeger::hash_to_trace_identifier;
@@ -20,15 +20,19 @@
 //! multiple subsystems and intercept or replace incoming and outgoing
 //! messages on the overseer level.
 
+use crate::shared::spawn_name;
+use gum::Instrument;
 use polkadot_node_subsystem::*;
 pub use polkadot_node_subsystem::{messages, messages::*, overseer, FromOrchestra};
-use std::{future::Future, pin::Pin};
+use std::{collections::HashMap, future::Future, pin::Pin};
 
 /// Filter incoming and outgoing messages.
+#[async_trait::async_trait]
 pub trait MessageInterceptor<Sender>: Send + Sync + Clone + 'static
 where
 	Sender: overseer::SubsystemSender<<Self::Message as overseer::AssociateOutgoing>::OutgoingMessages>
 		+ Clone
+		+ Send
 		+ 'static,
 {
 	/// The message type the original subsystem handles incoming.
@@ -39,7 +43,9 @@ where
 	///
 	/// For non-trivial cases, the `sender` can be used to send
 	/// multiple messages after doing some additional processing.
-	fn intercept_incoming(
+	/// The subsystem does not receive any further messages until
+	/// the returned future completes.
+	async fn intercept_incoming(
 		&self,
 		_sender: &mut Sender,
 		msg: FromOrchestra<Self::Message>,
@@ -47,6 +53,12 @@ where
 		Some(msg)
 	}
 
+	/// Observe signals before they are passed on to `intercept_incoming`.
+	///
+	/// Allows reacting to e.g. `ActiveLeaves` or `BlockFinalized` without
+	/// having to handle forwarding of the signal.
+	fn observe_signal(&self, _signal: &OverseerSignal) {}
+
 	/// Modify outgoing messages.
 	fn intercept_outgoing(
 		&self,
@@ -56,6 +68,58 @@ where
 	}
 }
 
+/// Two interceptors applied to the same subsystem, composing their behavior.
+///
+/// Incoming messages pass `first` and then `second`, outgoing messages pass them
+/// in reverse order. A message dropped by either interceptor is not passed on
+/// to the other one.
+#[derive(Clone)]
+pub struct InterceptorChain<A, B> {
+	first: A,
+	second: B,
+}
+
+impl<A, B> InterceptorChain<A, B> {
+	pub fn new(first: A, second: B) -> Self {
+		Self { first, second }
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender, A, B> MessageInterceptor<Sender> for InterceptorChain<A, B>
+where
+	Sender: overseer::SubsystemSender<<A::Message as overseer::AssociateOutgoing>::OutgoingMessages>
+		+ Clone
+		+ Send
+		+ 'static,
+	A: MessageInterceptor<Sender>,
+	B: MessageInterceptor<Sender, Message = A::Message>,
+{
+	type Message = A::Message;
+
+	async fn intercept_incoming(
+		&self,
+		sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		let msg = self.first.intercept_incoming(sender, msg).await?;
+		self.second.intercept_incoming(sender, msg).await
+	}
+
+	fn observe_signal(&self, signal: &OverseerSignal) {
+		self.first.observe_signal(signal);
+		self.second.observe_signal(signal);
+	}
+
+	fn intercept_outgoing(
+		&self,
+		msg: <Self::Message as overseer::AssociateOutgoing>::OutgoingMessages,
+	) -> Option<<Self::Message as overseer::AssociateOutgoing>::OutgoingMessages> {
+		let msg = self.second.intercept_outgoing(msg)?;
+		self.first.intercept_outgoing(msg)
+	}
+}
+
 /// A sender with the outgoing messages filtered.
 #[derive(Clone)]
 pub struct InterceptedSender<Sender, Fil> {
@@ -126,6 +190,10 @@ where
 	inner: Context,
 	message_filter: Fil,
 	sender: InterceptedSender<<Context as overseer::SubsystemContext>::Sender, Fil>,
+	/// The prefix of the names of spawned tasks, identifying the variant.
+	spawn_prefix: String,
+	/// The prefixed names of tasks spawned so far, such that each is only leaked once.
+	spawn_names: HashMap<&'static str, &'static str>,
 }
 
 impl<Context, Fil> InterceptedContext<Context, Fil>
@@ -140,12 +208,29 @@ where
 		<<Context as overseer::SubsystemContext>::Message as overseer::AssociateOutgoing>::OutgoingMessages
 	>
 {
-	pub fn new(mut inner: Context, message_filter: Fil) -> Self {
+	pub fn new(
+		name: &'static str,
+		spawn_prefix: &str,
+		mut inner: Context,
+		message_filter: Fil,
+	) -> Self {
 		let sender = InterceptedSender::<<Context as overseer::SubsystemContext>::Sender, Fil> {
 			inner: inner.sender().clone(),
 			message_filter: message_filter.clone(),
 		};
-		Self { inner, message_filter, sender }
+		Self {
+			inner,
+			message_filter,
+			sender,
+			spawn_prefix: format!("{}-{}", spawn_prefix, name),
+			spawn_names: HashMap::new(),
+		}
+	}
+
+	/// The name of the spawned task `task`, prefixed with the variant name.
+	fn spawn_name(&mut self, task: &'static str) -> &'static str {
+		let prefix = &self.spawn_prefix;
+		*self.spawn_names.entry(task).or_insert_with(|| spawn_name(prefix, task))
 	}
 }
 
@@ -178,12 +263,16 @@ where
 		loop {
 			match self.inner.try_recv().await? {
 				None => return Ok(None),
-				Some(msg) =>
+				Some(msg) => {
+					if let FromOrchestra::Signal(signal) = &msg {
+						self.message_filter.observe_signal(signal);
+					}
 					if let Some(msg) =
-						self.message_filter.intercept_incoming(self.inner.sender(), msg)
+						self.message_filter.intercept_incoming(self.inner.sender(), msg).await
 					{
 						return Ok(Some(msg))
-					},
+					}
+				},
 			}
 		}
 	}
@@ -191,7 +280,12 @@ where
 	async fn recv(&mut self) -> SubsystemResult<FromOrchestra<Self::Message>> {
 		loop {
 			let msg = self.inner.recv().await?;
-			if let Some(msg) = self.message_filter.intercept_incoming(self.inner.sender(), msg) {
+			if let FromOrchestra::Signal(signal) = &msg {
+				self.message_filter.observe_signal(signal);
+			}
+			if let Some(msg) =
+				self.message_filter.intercept_incoming(self.inner.sender(), msg).await
+			{
 				return Ok(msg)
 			}
 		}
@@ -202,6 +296,7 @@ where
 		name: &'static str,
 		s: Pin<Box<dyn Future<Output = ()> + Send>>,
 	) -> SubsystemResult<()> {
+		let name = self.spawn_name(name);
 		self.inner.spawn(name, s)
 	}
 
@@ -210,6 +305,7 @@ where
 		name: &'static str,
 		s: Pin<Box<dyn Future<Output = ()> + Send>>,
 	) -> SubsystemResult<()> {
+		let name = self.spawn_name(name);
 		self.inner.spawn_blocking(name, s)
 	}
 
@@ -219,14 +315,24 @@ where
 }
 
 /// A subsystem to which incoming and outgoing filters are applied.
+///
+/// The `name` identifies the variant in the names of the tasks spawned by the subsystem,
+/// `<spawn_prefix>-<name>-<task>`, and in a span all of its logs are emitted in.
 pub struct InterceptedSubsystem<Sub, Interceptor> {
+	pub name: &'static str,
+	pub spawn_prefix: &'static str,
 	pub subsystem: Sub,
 	pub message_interceptor: Interceptor,
 }
 
 impl<Sub, Interceptor> InterceptedSubsystem<Sub, Interceptor> {
-	pub fn new(subsystem: Sub, message_interceptor: Interceptor) -> Self {
-		Self { subsystem, message_interceptor }
+	pub fn new(
+		name: &'static str,
+		spawn_prefix: &'static str,
+		subsystem: Sub,
+		message_interceptor: Interceptor,
+	) -> Self {
+		Self { name, spawn_prefix, subsystem, message_interceptor }
 	}
 }
 
@@ -252,10 +358,169 @@ where
 		>,
 {
 	fn start(self, ctx: Context) -> SpawnedSubsystem {
-		let ctx = InterceptedContext::new(ctx, self.message_interceptor);
-		overseer::Subsystem::<InterceptedContext<Context, Interceptor>, SubsystemError>::start(
-			self.subsystem,
-			ctx,
-		)
+		let ctx =
+			InterceptedContext::new(self.name, self.spawn_prefix, ctx, self.message_interceptor);
+		let span = gum::span!(gum::Level::INFO, "malus", variant = self.name);
+		let SpawnedSubsystem { name, future } =
+			overseer::Subsystem::<InterceptedContext<Context, Interceptor>, SubsystemError>::start(
+				self.subsystem,
+				ctx,
+			);
+		SpawnedSubsystem { name, future: Box::pin(future.instrument(span)) }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shared::DEFAULT_SPAWN_PREFIX;
+	use futures::{channel::oneshot, executor::block_on, future::BoxFuture};
+	use polkadot_node_subsystem_test_helpers::{
+		make_subsystem_context, sender_receiver, TestSubsystemSender,
+	};
+	use polkadot_primitives::v2::Hash;
+	use std::sync::{Arc, Mutex};
+
+	type Log = Arc<Mutex<Vec<&'static str>>>;
+
+	/// Records the names of the tasks spawned on it, without running them.
+	#[derive(Clone, Default)]
+	struct SpawnRecorder(Log);
+
+	impl sp_core::traits::SpawnNamed for SpawnRecorder {
+		fn spawn_blocking(
+			&self,
+			name: &'static str,
+			_group: Option<&'static str>,
+			_future: BoxFuture<'static, ()>,
+		) {
+			self.0.lock().unwrap().push(name);
+		}
+
+		fn spawn(
+			&self,
+			name: &'static str,
+			_group: Option<&'static str>,
+			_future: BoxFuture<'static, ()>,
+		) {
+			self.0.lock().unwrap().push(name);
+		}
+	}
+
+	/// Records the order it sees messages in, dropping them if configured to.
+	#[derive(Clone)]
+	struct Recorder {
+		name: &'static str,
+		drop: bool,
+		log: Log,
+	}
+
+	#[async_trait::async_trait]
+	impl<Sender> MessageInterceptor<Sender> for Recorder
+	where
+		Sender: overseer::AvailabilityStoreSenderTrait + Clone + Send + 'static,
+	{
+		type Message = AvailabilityStoreMessage;
+
+		async fn intercept_incoming(
+			&self,
+			_sender: &mut Sender,
+			msg: FromOrchestra<Self::Message>,
+		) -> Option<FromOrchestra<Self::Message>> {
+			self.log.lock().unwrap().push(self.name);
+			(!self.drop).then(|| msg)
+		}
+
+		fn intercept_outgoing(
+			&self,
+			msg: overseer::AvailabilityStoreOutgoingMessages,
+		) -> Option<overseer::AvailabilityStoreOutgoingMessages> {
+			self.log.lock().unwrap().push(self.name);
+			(!self.drop).then(|| msg)
+		}
+	}
+
+	fn recorders(drop_a: bool, drop_b: bool) -> (InterceptorChain<Recorder, Recorder>, Log) {
+		let log = Arc::new(Mutex::new(Vec::new()));
+		let a = Recorder { name: "a", drop: drop_a, log: log.clone() };
+		let b = Recorder { name: "b", drop: drop_b, log: log.clone() };
+		(InterceptorChain::new(a, b), log)
+	}
+
+	fn incoming(chain: &InterceptorChain<Recorder, Recorder>) -> bool {
+		let (mut sender, _rx) = sender_receiver();
+		let msg = FromOrchestra::Signal(OverseerSignal::Conclude);
+		block_on(chain.intercept_incoming(&mut sender, msg)).is_some()
+	}
+
+	fn outgoing(chain: &InterceptorChain<Recorder, Recorder>) -> bool {
+		let (tx, _rx) = oneshot::channel();
+		let msg = ChainApiMessage::BlockNumber(Hash::repeat_byte(1), tx);
+		MessageInterceptor::<TestSubsystemSender>::intercept_outgoing(chain, msg.into()).is_some()
+	}
+
+	#[test]
+	fn incoming_runs_first_then_second() {
+		let (chain, log) = recorders(false, false);
+		assert!(incoming(&chain));
+		assert_eq!(*log.lock().unwrap(), vec!["a", "b"]);
+	}
+
+	#[test]
+	fn outgoing_runs_second_then_first() {
+		let (chain, log) = recorders(false, false);
+		assert!(outgoing(&chain));
+		assert_eq!(*log.lock().unwrap(), vec!["b", "a"]);
+	}
+
+	#[test]
+	fn dropped_messages_are_not_passed_on() {
+		let (chain, log) = recorders(true, false);
+		assert!(!incoming(&chain));
+		assert_eq!(*log.lock().unwrap(), vec!["a"]);
+
+		let (chain, log) = recorders(false, true);
+		assert!(!outgoing(&chain));
+		assert_eq!(*log.lock().unwrap(), vec!["b"]);
+
+		// the later interceptor still gets to drop.
+		let (chain, log) = recorders(false, true);
+		assert!(!incoming(&chain));
+		assert_eq!(*log.lock().unwrap(), vec!["a", "b"]);
+	}
+
+	#[test]
+	fn name_is_threaded_into_spawn_names() {
+		let spawner = SpawnRecorder::default();
+		let (ctx, _handle) = make_subsystem_context::<AvailabilityStoreMessage, _>(spawner.clone());
+		let (chain, _log) = recorders(false, false);
+		let mut ctx = InterceptedContext::new("garbage", DEFAULT_SPAWN_PREFIX, ctx, chain);
+
+		overseer::SubsystemContext::spawn(&mut ctx, "get-validation-data", Box::pin(async {}))
+			.unwrap();
+		overseer::SubsystemContext::spawn_blocking(&mut ctx, "pvf", Box::pin(async {})).unwrap();
+		overseer::SubsystemContext::spawn(&mut ctx, "get-validation-data", Box::pin(async {}))
+			.unwrap();
+
+		assert_eq!(
+			*spawner.0.lock().unwrap(),
+			vec![
+				"malus-garbage-get-validation-data",
+				"malus-garbage-pvf",
+				"malus-garbage-get-validation-data",
+			],
+		);
+	}
+
+	#[test]
+	fn spawn_prefix_is_threaded_into_spawn_names() {
+		let spawner = SpawnRecorder::default();
+		let (ctx, _handle) = make_subsystem_context::<AvailabilityStoreMessage, _>(spawner.clone());
+		let (chain, _log) = recorders(false, false);
+		let mut ctx = InterceptedContext::new("garbage", "malus-2", ctx, chain);
+
+		overseer::SubsystemContext::spawn(&mut ctx, "pvf", Box::pin(async {})).unwrap();
+
+		assert_eq!(*spawner.0.lock().unwrap(), vec!["malus-2-garbage-pvf"]);
 	}
 }
@@ -21,6 +21,9 @@ use color_eyre::eyre;
 use polkadot_cli::{Cli, RunCmd};
 
 pub(crate) mod interceptor;
+pub(crate) mod metrics;
+#[cfg(test)]
+pub(crate) mod mock;
 pub(crate) mod shared;
 
 mod variants;
@@ -33,11 +36,63 @@ use variants::*;
 #[clap(rename_all = "kebab-case")]
 enum NemesisVariant {
 	/// Suggest a candidate with an invalid proof of validity.
-	SuggestGarbageCandidate(RunCmd),
+	SuggestGarbageCandidate(SuggestGarbageCandidateOptions),
 	/// Back a candidate with a specifically crafted proof of validity.
 	BackGarbageCandidate(RunCmd),
 	/// Delayed disputing of ancestors that are perfectly fine.
 	DisputeAncestor(DisputeAncestorOptions),
+	/// Refuse to serve availability chunks of a fraction of candidates.
+	WithholdChunks(WithholdChunksOptions),
+	/// Issue conflicting approval votes for a fraction of approved candidates.
+	ApprovalEquivocation(ApprovalEquivocationOptions),
+	/// Hold back gossiped statements for a configurable duration.
+	DelayStatements(DelayStatementsOptions),
+	/// Second candidates whose erasure root does not match their chunks.
+	BadErasureRoot(BadErasureRootOptions),
+	/// Confirm seconded collations to collators in reverse order.
+	ReorderSeconded(ReorderSecondedOptions),
+	/// Dispute a fraction of the candidates checked in approval voting, regardless of validity.
+	FalseDispute(FalseDisputeOptions),
+	/// Validate candidates in backing against stale validation code.
+	StaleCode(StaleCodeOptions),
+	/// Second candidates whose `para_head` does not match their head data.
+	BadParaHead(BadParaHeadOptions),
+	/// Drop a fraction of the messages sent to peers, simulating packet loss.
+	LossyNetwork(LossyNetworkOptions),
+	/// Back candidates honestly, but refuse to serve their PoV to peers.
+	WithholdPov(WithholdPoVOptions),
+	/// Second candidates rebuilt against an ancestor of their relay parent.
+	StaleRelayParent(StaleRelayParentOptions),
+	/// Distribute a fraction of approval votes with a signature that does not verify.
+	BadApprovalSig(BadApprovalSigOptions),
+	/// Second candidates claiming more processed downward messages than were sent.
+	InflatedDmp(InflatedDmpOptions),
+	/// Second candidates whose validation data hash does not match their validation data.
+	BadPvdHash(BadPvdHashOptions),
+	/// Second candidates sending more HRMP messages over a channel than it can hold.
+	HrmpOverflow(HrmpOverflowOptions),
+	/// Second more distinct candidates than the seconding limit permits.
+	SecondedOvershoot(SecondedOvershootOptions),
+	/// Participate in disputes honestly, but confirm dispute statement imports late.
+	SlowDisputeParticipation(SlowDisputeParticipationOptions),
+	/// Re-broadcast every gossiped `Seconded` statement several times.
+	FloodStatements(FloodStatementsOptions),
+	/// Second candidates with a zero-length `PoV`.
+	EmptyPov(EmptyPovOptions),
+	/// Forge `Seconded` statements of validators outside of the backing group.
+	NonGroupStatements(NonGroupStatementsOptions),
+	/// Gossip a conflicting availability bitfield alongside our own.
+	BitfieldEquivocation(BitfieldEquivocationOptions),
+	/// Alternate between honest and garbage-backing phases of a number of leaves.
+	Intermittent(IntermittentOptions),
+	/// Refuse to serve availability chunks to a set of validators.
+	CensorChunks(CensorChunksOptions),
+	/// Second candidates committing to an HRMP watermark beyond their relay parent.
+	InflatedHrmpWatermark(InflatedHrmpWatermarkOptions),
+	/// Suppress our own approval assignments, causing apparent no-shows.
+	FalseNoShows(FalseNoShowsOptions),
+	/// Second candidates upgrading to validation code exceeding the maximum code size.
+	OversizedCode(OversizedCodeOptions),
 
 	#[allow(missing_docs)]
 	#[clap(name = "prepare-worker", hide = true)]
@@ -68,13 +123,146 @@ impl MalusCli {
 		match self.variant {
 			NemesisVariant::BackGarbageCandidate(cmd) =>
 				polkadot_cli::run_node(run_cmd(cmd), BackGarbageCandidate, finality_delay)?,
-			NemesisVariant::SuggestGarbageCandidate(cmd) =>
-				polkadot_cli::run_node(run_cmd(cmd), BackGarbageCandidateWrapper, finality_delay)?,
+			NemesisVariant::SuggestGarbageCandidate(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				BackGarbageCandidateWrapper::new(opts),
+				finality_delay,
+			)?,
 			NemesisVariant::DisputeAncestor(opts) => polkadot_cli::run_node(
 				run_cmd(opts.clone().cmd),
 				DisputeValidCandidates::new(opts),
 				finality_delay,
 			)?,
+			NemesisVariant::WithholdChunks(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				WithholdAvailabilityChunks::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::ApprovalEquivocation(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				ApprovalEquivocation::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::DelayStatements(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				DelayStatementDistribution::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::BadErasureRoot(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				BadErasureRoot::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::ReorderSeconded(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				ReorderSecondedCollations::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::FalseDispute(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				FalseDispute::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::StaleCode(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				StaleCode::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::BadParaHead(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				BadParaHead::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::LossyNetwork(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				LossyNetwork::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::WithholdPov(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				WithholdPoVs::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::StaleRelayParent(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				StaleRelayParent::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::BadApprovalSig(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				BadApprovalSig::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::InflatedDmp(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				InflatedDmp::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::BadPvdHash(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				BadPvdHash::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::HrmpOverflow(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				HrmpOverflow::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::SecondedOvershoot(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				SecondedOvershoot::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::SlowDisputeParticipation(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				SlowDisputeParticipation::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::FloodStatements(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				FloodStatementDistribution::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::EmptyPov(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				EmptyPov::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::NonGroupStatements(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				NonGroupStatements::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::BitfieldEquivocation(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				BitfieldEquivocation::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::Intermittent(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().garbage.cmd),
+				IntermittentGarbageCandidate::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::CensorChunks(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				CensorAvailabilityChunks::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::InflatedHrmpWatermark(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				InflatedHrmpWatermark::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::FalseNoShows(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				FalseNoShows::new(opts),
+				finality_delay,
+			)?,
+			NemesisVariant::OversizedCode(opts) => polkadot_cli::run_node(
+				run_cmd(opts.clone().cmd),
+				OversizedCode::new(opts),
+				finality_delay,
+			)?,
 			NemesisVariant::PvfPrepareWorker(cmd) => {
 				#[cfg(target_os = "android")]
 				{
@@ -114,19 +302,323 @@ fn main() -> eyre::Result<()> {
 mod tests {
 	use super::*;
 
+	/// Parse `malus <args> --bob`.
+	fn parse(args: &[&str]) -> Result<MalusCli, clap::Error> {
+		MalusCli::try_parse_from(["malus"].iter().chain(args).chain(&["--bob"]))
+	}
+
+	#[test]
+	fn subcommands_parse() {
+		let cases: &[(&[&str], fn(&NemesisVariant) -> bool)] = &[
+			(&["dispute-ancestor"], |v| match v {
+				NemesisVariant::DisputeAncestor(run) => run.cmd.base.bob,
+				_ => false,
+			}),
+			(&["withhold-chunks", "--percentage", "30"], |v| match v {
+				NemesisVariant::WithholdChunks(o) => o.percentage == 30 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["suggest-garbage-candidate", "--seed", "7"], |v| match v {
+				NemesisVariant::SuggestGarbageCandidate(o) =>
+					o.percentage == 100 && o.seed == Some(7) && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["approval-equivocation", "--percentage", "50"], |v| match v {
+				NemesisVariant::ApprovalEquivocation(o) => o.percentage == 50 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["bad-erasure-root", "--percentage", "25"], |v| match v {
+				NemesisVariant::BadErasureRoot(o) => o.percentage == 25 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["reorder-seconded", "--window-size", "4"], |v| match v {
+				NemesisVariant::ReorderSeconded(o) => o.window_size == 4 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["false-dispute", "--percentage", "10"], |v| match v {
+				NemesisVariant::FalseDispute(o) => o.percentage == 10 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["stale-code", "--percentage", "80"], |v| match v {
+				NemesisVariant::StaleCode(o) => o.percentage == 80 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["bad-para-head", "--percentage", "30"], |v| match v {
+				NemesisVariant::BadParaHead(o) => o.percentage == 30 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["lossy-network", "--percentage", "5"], |v| match v {
+				NemesisVariant::LossyNetwork(o) => o.percentage == 5 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["withhold-pov", "--percentage", "40"], |v| match v {
+				NemesisVariant::WithholdPov(o) => o.percentage == 40 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["stale-relay-parent", "--ancestor-depth", "3"], |v| match v {
+				NemesisVariant::StaleRelayParent(o) =>
+					o.percentage == 100 && o.ancestor_depth == 3 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["bad-approval-sig", "--percentage", "25"], |v| match v {
+				NemesisVariant::BadApprovalSig(o) => o.percentage == 25 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["inflated-dmp", "--processed-downward-messages", "5"], |v| match v {
+				NemesisVariant::InflatedDmp(o) =>
+					o.percentage == 100 && o.processed_downward_messages == 5 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["bad-pvd-hash", "--percentage", "40"], |v| match v {
+				NemesisVariant::BadPvdHash(o) => o.percentage == 40 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["hrmp-overflow", "--recipient", "2001", "--message-count", "12"], |v| match v {
+				NemesisVariant::HrmpOverflow(o) =>
+					o.percentage == 100 &&
+						o.recipient == 2001 &&
+						o.message_count == 12 &&
+						o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["seconded-overshoot", "--overshoot", "3"], |v| match v {
+				NemesisVariant::SecondedOvershoot(o) =>
+					o.seconding_limit == 2 && o.overshoot == 3 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(
+				&[
+					"slow-dispute-participation",
+					"--participation-delay-ms",
+					"2500",
+					"--jitter-ms",
+					"500",
+				],
+				|v| match v {
+					NemesisVariant::SlowDisputeParticipation(o) =>
+						o.participation_delay_ms == 2500 && o.jitter_ms == 500 && o.cmd.base.bob,
+					_ => false,
+				},
+			),
+			(&["flood-statements", "--flood-factor", "10"], |v| match v {
+				NemesisVariant::FloodStatements(o) => o.flood_factor == 10 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["empty-pov", "--percentage", "40"], |v| match v {
+				NemesisVariant::EmptyPov(o) => o.percentage == 40 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["non-group-statements", "--forge-count", "3"], |v| match v {
+				NemesisVariant::NonGroupStatements(o) => o.forge_count == 3 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["bitfield-equivocation", "--percentage", "30"], |v| match v {
+				NemesisVariant::BitfieldEquivocation(o) => o.percentage == 30 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["intermittent", "--period", "3", "--percentage", "50"], |v| match v {
+				NemesisVariant::Intermittent(o) =>
+					o.period == 3 && o.garbage.percentage == 50 && o.garbage.cmd.base.bob,
+				_ => false,
+			}),
+			(&["censor-chunks", "--censor-indices", "1,4"], |v| match v {
+				NemesisVariant::CensorChunks(o) => o.censor_indices == vec![1, 4] && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["inflated-hrmp-watermark", "--watermark-offset", "5"], |v| match v {
+				NemesisVariant::InflatedHrmpWatermark(o) =>
+					o.watermark_offset == 5 && o.percentage == 100 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["false-no-shows", "--noshow-percentage", "25"], |v| match v {
+				NemesisVariant::FalseNoShows(o) => o.noshow_percentage == 25 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["oversized-code", "--code-size", "4096"], |v| match v {
+				NemesisVariant::OversizedCode(o) =>
+					o.code_size == 4096 && o.percentage == 100 && o.cmd.base.bob,
+				_ => false,
+			}),
+			(&["delay-statements", "--delay-ms", "250"], |v| match v {
+				NemesisVariant::DelayStatements(o) =>
+					o.delay_ms == 250 && o.spawn.spawn_prefix == "malus" && o.cmd.base.bob,
+				_ => false,
+			}),
+		];
+
+		for (args, expected) in cases {
+			let variant = parse(args).unwrap().variant;
+			assert!(expected(&variant), "{:?} parsed as {:?}", args, variant);
+		}
+	}
+
 	#[test]
-	fn subcommand_works() {
+	fn invalid_subcommands_are_rejected() {
+		let cases: &[&[&str]] = &[
+			&["withhold-chunks", "--percentage", "101"],
+			&["intermittent", "--period", "0"],
+			&["censor-chunks"],
+		];
+
+		for args in cases {
+			assert!(parse(args).is_err(), "{:?} parsed", args);
+		}
+	}
+
+	#[test]
+	fn spawn_prefix_is_configurable() {
 		let cli = MalusCli::try_parse_from(IntoIterator::into_iter([
 			"malus",
-			"dispute-ancestor",
+			"false-dispute",
+			"--spawn-prefix",
+			"malus-2",
 			"--bob",
 		]))
 		.unwrap();
 		assert_matches::assert_matches!(cli, MalusCli {
-			variant: NemesisVariant::DisputeAncestor(run),
+			variant: NemesisVariant::FalseDispute(opts),
 			..
 		} => {
-			assert!(run.cmd.base.bob);
+			assert_eq!(opts.spawn.spawn_prefix, "malus-2");
 		});
 	}
+
+	fn suggest_garbage_candidate_opts(extra: &[&str]) -> SuggestGarbageCandidateOptions {
+		let args = ["malus", "suggest-garbage-candidate", "--bob"].iter().chain(extra);
+		match MalusCli::try_parse_from(args).unwrap().variant {
+			NemesisVariant::SuggestGarbageCandidate(opts) => opts,
+			variant => panic!("Unexpected variant: {:?}", variant),
+		}
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_fake_validation_defaults() {
+		let opts = suggest_garbage_candidate_opts(&[]);
+		assert_eq!(opts.fake_validation, FakeCandidateValidation::BackingAndApprovalValid);
+		assert_eq!(opts.fake_validation_error, FakeCandidateValidationError::InvalidOutputs);
+		assert_eq!(opts.fake_validation_percentage, 100);
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_fake_validation_percentage_parses() {
+		let opts = suggest_garbage_candidate_opts(&["--fake-validation-percentage", "50"]);
+		assert_eq!(opts.fake_validation_percentage, 50);
+		assert!(MalusCli::try_parse_from(IntoIterator::into_iter([
+			"malus",
+			"suggest-garbage-candidate",
+			"--fake-validation-percentage",
+			"101",
+			"--bob",
+		]))
+		.is_err());
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_dry_run_is_opt_in() {
+		assert!(!suggest_garbage_candidate_opts(&[]).dry_run);
+		assert!(suggest_garbage_candidate_opts(&["--dry-run"]).dry_run);
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_decision_log_is_opt_in() {
+		assert_eq!(suggest_garbage_candidate_opts(&[]).decision_log, None);
+		assert_eq!(
+			suggest_garbage_candidate_opts(&["--decision-log", "/tmp/decisions.log"]).decision_log,
+			Some(std::path::PathBuf::from("/tmp/decisions.log"))
+		);
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_deterministic_collator_requires_seed() {
+		assert!(!suggest_garbage_candidate_opts(&[]).deterministic_collator);
+		assert!(
+			suggest_garbage_candidate_opts(&["--deterministic-collator", "--seed", "7"])
+				.deterministic_collator
+		);
+		assert!(MalusCli::try_parse_from(IntoIterator::into_iter([
+			"malus",
+			"suggest-garbage-candidate",
+			"--deterministic-collator",
+			"--bob",
+		]))
+		.is_err());
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_max_chunk_validators_parses() {
+		assert_eq!(suggest_garbage_candidate_opts(&[]).max_chunk_validators, None);
+		assert_eq!(
+			suggest_garbage_candidate_opts(&["--max-chunk-validators", "300"]).max_chunk_validators,
+			Some(300)
+		);
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_burst_parses() {
+		assert_eq!(suggest_garbage_candidate_opts(&[]).burst, None);
+		assert_eq!(
+			suggest_garbage_candidate_opts(&["--burst", "3,10"])
+				.burst
+				.map(|b| (b.n, b.cooldown)),
+			Some((3, 10))
+		);
+		assert!(MalusCli::try_parse_from(IntoIterator::into_iter([
+			"malus",
+			"suggest-garbage-candidate",
+			"--burst",
+			"0,10",
+			"--bob",
+		]))
+		.is_err());
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_fake_validation_modes_parse() {
+		for (arg, mode) in [
+			("disabled", FakeCandidateValidation::Disabled),
+			("backing-invalid", FakeCandidateValidation::BackingInvalid),
+			("approval-invalid", FakeCandidateValidation::ApprovalInvalid),
+			("backing-and-approval-invalid", FakeCandidateValidation::BackingAndApprovalInvalid),
+			("backing-valid", FakeCandidateValidation::BackingValid),
+			("approval-valid", FakeCandidateValidation::ApprovalValid),
+			("backing-and-approval-valid", FakeCandidateValidation::BackingAndApprovalValid),
+		] {
+			let opts = suggest_garbage_candidate_opts(&["--fake-validation", arg]);
+			assert_eq!(opts.fake_validation, mode, "{}", arg);
+		}
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_fake_validation_errors_parse() {
+		for (arg, kind) in [
+			("invalid-outputs", FakeCandidateValidationError::InvalidOutputs),
+			("execution-error", FakeCandidateValidationError::ExecutionError),
+			("timeout", FakeCandidateValidationError::Timeout),
+			("params-too-large", FakeCandidateValidationError::ParamsTooLarge),
+			("code-too-large", FakeCandidateValidationError::CodeTooLarge),
+			("code-decompression-failure", FakeCandidateValidationError::CodeDecompressionFailure),
+			("pov-decompression-failure", FakeCandidateValidationError::POVDecompressionFailure),
+			("bad-return", FakeCandidateValidationError::BadReturn),
+			("bad-parent", FakeCandidateValidationError::BadParent),
+			("pov-hash-mismatch", FakeCandidateValidationError::POVHashMismatch),
+			("bad-signature", FakeCandidateValidationError::BadSignature),
+			("para-head-hash-mismatch", FakeCandidateValidationError::ParaHeadHashMismatch),
+			("code-hash-mismatch", FakeCandidateValidationError::CodeHashMismatch),
+		] {
+			let opts = suggest_garbage_candidate_opts(&["--fake-validation-error", arg]);
+			assert_eq!(opts.fake_validation_error, kind, "{}", arg);
+		}
+	}
+
+	#[test]
+	fn suggest_garbage_candidate_rejects_unknown_fake_validation() {
+		assert!(MalusCli::try_parse_from(IntoIterator::into_iter([
+			"malus",
+			"suggest-garbage-candidate",
+			"--fake-validation",
+			"sometimes-valid",
+			"--bob",
+		]))
+		.is_err());
+	}
 }
@@ -0,0 +1,91 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Metrics exposed by malus interceptors, to measure the intensity of an attack.
+
+use polkadot_node_subsystem_util::metrics::{self, prometheus};
+
+#[derive(Clone)]
+struct MetricsInner {
+	candidates_manipulated: prometheus::Counter<prometheus::U64>,
+	candidates_passed: prometheus::Counter<prometheus::U64>,
+}
+
+/// Malus metrics.
+#[derive(Default, Clone)]
+pub(crate) struct Metrics(Option<MetricsInner>);
+
+impl Metrics {
+	/// A candidate was replaced by a malicious one.
+	pub(crate) fn on_candidate_manipulated(&self) {
+		if let Some(metrics) = &self.0 {
+			metrics.candidates_manipulated.inc();
+		}
+	}
+
+	/// A candidate was passed through untouched.
+	pub(crate) fn on_candidate_passed(&self) {
+		if let Some(metrics) = &self.0 {
+			metrics.candidates_passed.inc();
+		}
+	}
+}
+
+impl metrics::Metrics for Metrics {
+	fn try_register(registry: &prometheus::Registry) -> Result<Self, prometheus::PrometheusError> {
+		let metrics = MetricsInner {
+			candidates_manipulated: prometheus::register(
+				prometheus::Counter::new(
+					"malus_candidates_manipulated_total",
+					"Number of candidates replaced by malicious ones.",
+				)?,
+				registry,
+			)?,
+			candidates_passed: prometheus::register(
+				prometheus::Counter::new(
+					"malus_candidates_passed_total",
+					"Number of candidates passed through without manipulation.",
+				)?,
+				registry,
+			)?,
+		};
+		Ok(Metrics(Some(metrics)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use metrics::Metrics as _;
+
+	#[test]
+	fn counters_increment() {
+		let registry = prometheus::Registry::new();
+		let metrics = Metrics::try_register(&registry).unwrap();
+
+		for manipulate in [true, false, false, true, false] {
+			if manipulate {
+				metrics.on_candidate_manipulated();
+			} else {
+				metrics.on_candidate_passed();
+			}
+		}
+
+		let inner = metrics.0.as_ref().unwrap();
+		assert_eq!(inner.candidates_manipulated.get(), 2);
+		assert_eq!(inner.candidates_passed.get(), 3);
+	}
+}
@@ -0,0 +1,127 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A mock runtime answering the requests interceptors send while fetching validation
+//! data, such that they can be unit tested without an overseer, and helpers for tests
+//! that build one or send signals.
+
+use crate::interceptor::*;
+
+use futures::{channel::mpsc, executor::block_on, future::join, Future, StreamExt};
+use polkadot_node_subsystem::{
+	jaeger, ActivatedLeaf, ActiveLeavesUpdate, LeafStatus, OverseerSignal,
+};
+use polkadot_node_subsystem_test_helpers::{sender_receiver, TestSubsystemSender};
+use polkadot_primitives::v2::{
+	BlockNumber, CandidateReceipt, Hash, PersistedValidationData, ValidationCode, MAX_POV_SIZE,
+};
+use polkadot_primitives_test_helpers::{
+	dummy_candidate_receipt, dummy_validation_code, dummy_validator,
+};
+use std::sync::Arc;
+
+/// Considers every head to support parachains, for overseers built in tests.
+pub(crate) struct AlwaysSupportsParachains;
+
+#[async_trait::async_trait]
+impl overseer::HeadSupportsParachains for AlwaysSupportsParachains {
+	async fn head_supports_parachains(&self, _head: &Hash) -> bool {
+		true
+	}
+}
+
+/// The signal of activating a leaf with the given number.
+pub(crate) fn activate_leaf(number: BlockNumber) -> OverseerSignal {
+	OverseerSignal::ActiveLeaves(ActiveLeavesUpdate::start_work(ActivatedLeaf {
+		hash: Hash::repeat_byte(number as u8),
+		number,
+		status: LeafStatus::Fresh,
+		span: Arc::new(jaeger::Span::Disabled),
+	}))
+}
+
+/// The chain state requests for validators and validation data are answered from.
+#[derive(Clone)]
+pub(crate) struct MockRuntime {
+	/// The size of the validator set.
+	pub n_validators: usize,
+	/// The persisted validation data of every para, `None` if it is unavailable.
+	pub validation_data: Option<PersistedValidationData>,
+	/// The validation code of every para.
+	pub validation_code: ValidationCode,
+}
+
+impl MockRuntime {
+	/// A runtime with the given number of validators and validation data fitting any `PoV`.
+	pub fn new(n_validators: usize) -> Self {
+		Self {
+			n_validators,
+			validation_data: Some(PersistedValidationData {
+				max_pov_size: MAX_POV_SIZE,
+				..Default::default()
+			}),
+			validation_code: dummy_validation_code(),
+		}
+	}
+
+	/// A candidate whose descriptor matches the validation data of this runtime.
+	pub fn candidate(&self, relay_parent: Hash) -> CandidateReceipt {
+		let mut candidate = dummy_candidate_receipt(relay_parent);
+		if let Some(validation_data) = &self.validation_data {
+			candidate.descriptor.persisted_validation_data_hash = validation_data.hash();
+		}
+		candidate.descriptor.validation_code_hash = self.validation_code.hash();
+		candidate
+	}
+
+	/// Answer requests until all senders are dropped. Panics on unexpected messages.
+	async fn answer(self, mut rx: mpsc::UnboundedReceiver<AllMessages>) {
+		while let Some(msg) = rx.next().await {
+			match msg {
+				AllMessages::RuntimeApi(RuntimeApiMessage::Request(
+					_,
+					RuntimeApiRequest::Validators(tx),
+				)) => {
+					let _ = tx.send(Ok(vec![dummy_validator(); self.n_validators]));
+				},
+				AllMessages::RuntimeApi(RuntimeApiMessage::Request(
+					_,
+					RuntimeApiRequest::PersistedValidationData(_, _, tx),
+				)) => {
+					let _ = tx.send(Ok(self.validation_data.clone()));
+				},
+				AllMessages::RuntimeApi(RuntimeApiMessage::Request(
+					_,
+					RuntimeApiRequest::ValidationCode(_, _, tx),
+				)) => {
+					let _ = tx.send(Ok(Some(self.validation_code.clone())));
+				},
+				msg => panic!("Unexpected message: {:?}", msg),
+			}
+		}
+	}
+}
+
+/// Run `f` with a sender whose requests are answered by `runtime`, until the future
+/// returned by `f` completes and drops the sender.
+pub(crate) fn with_mock_sender<Fut: Future>(
+	runtime: MockRuntime,
+	f: impl FnOnce(TestSubsystemSender) -> Fut,
+) -> Fut::Output {
+	let (sender, rx) = sender_receiver();
+	let (output, ()) = block_on(join(f(sender), runtime.answer(rx)));
+	output
+}
@@ -14,14 +14,177 @@
 // You should have received a copy of the GNU General Public License
 // along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
 
+use crate::variants::Percentage;
 use futures::prelude::*;
+use polkadot_cli::service::Error;
+use polkadot_node_subsystem::{OverseerSignal, SubsystemError};
+use polkadot_primitives::v2::{BlockNumber, CandidateHash};
+use rand::{rngs::StdRng, SeedableRng};
 use sp_core::traits::SpawnNamed;
+use std::collections::{HashMap, HashSet};
 
+/// Prefix of the log targets of all variants, for tests to check them against.
+#[cfg(test)]
 pub const MALUS: &str = "MALUS";
 
-#[allow(unused)]
+// Log targets of the individual variants. They are prefixed with `MALUS`, so filtering
+// by `MALUS` still covers all of them.
+pub const MALUS_APPROVAL_EQUIVOCATION: &str = "MALUS::approval-equivocation";
+pub const MALUS_BAD_APPROVAL_SIG: &str = "MALUS::bad-approval-sig";
+pub const MALUS_BAD_ERASURE_ROOT: &str = "MALUS::bad-erasure-root";
+pub const MALUS_BAD_PARA_HEAD: &str = "MALUS::bad-para-head";
+pub const MALUS_BAD_PVD_HASH: &str = "MALUS::bad-pvd-hash";
+pub const MALUS_BITFIELD_EQUIVOCATION: &str = "MALUS::bitfield-equivocation";
+pub const MALUS_CENSOR_CHUNKS: &str = "MALUS::censor-chunks";
+pub const MALUS_DELAY_STATEMENTS: &str = "MALUS::delay-statements";
+pub const MALUS_EMPTY_POV: &str = "MALUS::empty-pov";
+pub const MALUS_FAKE_VALIDATION: &str = "MALUS::fake-validation";
+pub const MALUS_FALSE_DISPUTE: &str = "MALUS::false-dispute";
+pub const MALUS_FALSE_NO_SHOWS: &str = "MALUS::false-no-shows";
+pub const MALUS_FLOOD_STATEMENTS: &str = "MALUS::flood-statements";
+pub const MALUS_HRMP_OVERFLOW: &str = "MALUS::hrmp-overflow";
+pub const MALUS_INFLATED_DMP: &str = "MALUS::inflated-dmp";
+pub const MALUS_INFLATED_HRMP_WATERMARK: &str = "MALUS::inflated-hrmp-watermark";
+pub const MALUS_INTERMITTENT: &str = "MALUS::intermittent";
+pub const MALUS_LOSSY_NETWORK: &str = "MALUS::lossy-network";
+pub const MALUS_NON_GROUP_STATEMENTS: &str = "MALUS::non-group-statements";
+pub const MALUS_OVERSIZED_CODE: &str = "MALUS::oversized-code";
+pub const MALUS_REORDER_SECONDED: &str = "MALUS::reorder-seconded";
+pub const MALUS_SECONDED_OVERSHOOT: &str = "MALUS::seconded-overshoot";
+pub const MALUS_SLOW_DISPUTE_PARTICIPATION: &str = "MALUS::slow-dispute-participation";
+pub const MALUS_STALE_CODE: &str = "MALUS::stale-code";
+pub const MALUS_STALE_RELAY_PARENT: &str = "MALUS::stale-relay-parent";
+pub const MALUS_GARBAGE: &str = "MALUS::suggest-garbage-candidate";
+pub const MALUS_WITHHOLD_CHUNKS: &str = "MALUS::withhold-chunks";
+pub const MALUS_WITHHOLD_POV: &str = "MALUS::withhold-pov";
+
+/// The log targets of all variants, for tests to enumerate.
+#[cfg(test)]
+pub const VARIANT_TARGETS: &[&str] = &[
+	MALUS_APPROVAL_EQUIVOCATION,
+	MALUS_BAD_APPROVAL_SIG,
+	MALUS_BAD_ERASURE_ROOT,
+	MALUS_BAD_PARA_HEAD,
+	MALUS_BAD_PVD_HASH,
+	MALUS_BITFIELD_EQUIVOCATION,
+	MALUS_CENSOR_CHUNKS,
+	MALUS_DELAY_STATEMENTS,
+	MALUS_EMPTY_POV,
+	MALUS_FAKE_VALIDATION,
+	MALUS_FALSE_DISPUTE,
+	MALUS_FALSE_NO_SHOWS,
+	MALUS_FLOOD_STATEMENTS,
+	MALUS_HRMP_OVERFLOW,
+	MALUS_INFLATED_DMP,
+	MALUS_INFLATED_HRMP_WATERMARK,
+	MALUS_INTERMITTENT,
+	MALUS_LOSSY_NETWORK,
+	MALUS_NON_GROUP_STATEMENTS,
+	MALUS_OVERSIZED_CODE,
+	MALUS_REORDER_SECONDED,
+	MALUS_SECONDED_OVERSHOOT,
+	MALUS_SLOW_DISPUTE_PARTICIPATION,
+	MALUS_STALE_CODE,
+	MALUS_STALE_RELAY_PARENT,
+	MALUS_GARBAGE,
+	MALUS_WITHHOLD_CHUNKS,
+	MALUS_WITHHOLD_POV,
+];
+
+/// Default prefix of the names of tasks spawned by malus variants.
+pub(crate) const DEFAULT_SPAWN_PREFIX: &str = "malus";
+
+/// The name of a task spawned by a malus variant, `<prefix>-<task>`.
+///
+/// Spawned tasks require `'static` names, so the name is leaked. Only use this
+/// for long-running tasks, spawned once per variant.
+pub(crate) fn spawn_name(prefix: &str, task: &str) -> &'static str {
+	Box::leak(format!("{}-{}", prefix, task).into_boxed_str())
+}
+
+/// Options of the variants spawning tasks of their own.
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct SpawnOptions {
+	/// Prefix of the names of spawned tasks, to tell apart the tasks of several malus nodes
+	/// running in the same process.
+	#[clap(long, default_value = DEFAULT_SPAWN_PREFIX)]
+	pub spawn_prefix: String,
+}
+
+impl SpawnOptions {
+	/// The spawn prefix, leaked as spawned tasks require `'static` group names. Only use
+	/// this once per variant.
+	pub(crate) fn static_spawn_prefix(self) -> &'static str {
+		Box::leak(self.spawn_prefix.into_boxed_str())
+	}
+}
+
 pub(crate) const MALICIOUS_POV: &[u8] = "😈😈pov_looks_valid_to_me😈😈".as_bytes();
 
+/// Whether the given `PoV` block data was crafted by a malus node. Crafted block data
+/// starts with `MALICIOUS_POV` and is optionally padded.
+pub(crate) fn is_malicious_pov(block_data: &[u8]) -> bool {
+	block_data.starts_with(MALICIOUS_POV)
+}
+
+/// Decides whether a variant acts on a candidate, for a percentage of candidates.
+///
+/// The decision is taken once per candidate and cached, so a candidate seen again
+/// yields the same outcome. If seeded, the decisions are reproducible across runs.
+///
+/// Signals have to be passed to `observe_signal`, such that decisions are forgotten
+/// once the highest leaf activated when last asking about them is finalized.
+#[derive(Debug)]
+pub(crate) struct BernoulliGate {
+	percentage: Percentage,
+	rng: Option<StdRng>,
+	/// The decisions taken, with the highest leaf activated when last asking about them.
+	decisions: HashMap<CandidateHash, (bool, BlockNumber)>,
+	/// The highest activated leaf observed so far.
+	best_number: BlockNumber,
+}
+
+impl BernoulliGate {
+	pub(crate) fn new(percentage: Percentage, seed: Option<u64>) -> Self {
+		Self {
+			percentage,
+			rng: seed.map(StdRng::seed_from_u64),
+			decisions: HashMap::new(),
+			best_number: 0,
+		}
+	}
+
+	/// Whether to act on the candidate identified by `key`.
+	pub(crate) fn should_act(&mut self, key: CandidateHash) -> bool {
+		let Self { percentage, rng, decisions, best_number } = self;
+		let (act, asked_at) = decisions.entry(key).or_insert_with(|| {
+			let act = match rng.as_mut() {
+				Some(rng) => percentage.sample(rng),
+				None => percentage.sample(&mut rand::thread_rng()),
+			};
+			(act, *best_number)
+		});
+		*asked_at = *best_number;
+		*act
+	}
+
+	/// Track the activated leaves, and forget the decisions taken before finalized ones.
+	pub(crate) fn observe_signal(&mut self, signal: &OverseerSignal) {
+		match signal {
+			OverseerSignal::ActiveLeaves(update) =>
+				if let Some(leaf) = &update.activated {
+					self.best_number = self.best_number.max(leaf.number);
+				},
+			OverseerSignal::BlockFinalized(_, finalized_number) => {
+				self.decisions.retain(|_, (_, number)| *number > *finalized_number);
+			},
+			_ => {},
+		}
+	}
+}
+
 /// Launch a service task for each item in the provided queue.
 #[allow(unused)]
 pub(crate) fn launch_processing_task<X, F, U, Q, S>(spawner: &S, queue: Q, action: F)
@@ -47,3 +210,191 @@ where
 		}),
 	);
 }
+
+/// The subsystems replaced by malus variants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum ReplacedSubsystem {
+	CandidateBacking,
+	CandidateValidation,
+}
+
+/// Wraps an overseer builder and keeps track of the subsystems replaced on it, such that
+/// replacing the same subsystem twice is an error instead of the last replacement silently
+/// winning.
+pub(crate) struct ReplacementGuard<Builder> {
+	builder: Builder,
+	replaced: HashSet<ReplacedSubsystem>,
+}
+
+impl<Builder> ReplacementGuard<Builder> {
+	pub(crate) fn new(builder: Builder) -> Self {
+		Self { builder, replaced: HashSet::new() }
+	}
+
+	/// Replace `subsystem` using the given `replace_*` call on the builder.
+	pub(crate) fn replace<NewBuilder>(
+		self,
+		subsystem: ReplacedSubsystem,
+		replace: impl FnOnce(Builder) -> NewBuilder,
+	) -> Result<ReplacementGuard<NewBuilder>, Error> {
+		let Self { builder, mut replaced } = self;
+		if !replaced.insert(subsystem) {
+			return Err(SubsystemError::Context(format!(
+				"replace the {:?} subsystem more than once",
+				subsystem
+			))
+			.into())
+		}
+
+		Ok(ReplacementGuard { builder: replace(builder), replaced })
+	}
+
+	/// The builder with all replacements applied.
+	pub(crate) fn into_inner(self) -> Builder {
+		self.builder
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{activate_leaf, AlwaysSupportsParachains};
+	use polkadot_node_subsystem::overseer::dummy::dummy_overseer_builder;
+	use polkadot_primitives::v2::Hash;
+	use sp_core::testing::TaskExecutor;
+
+	fn gate(percentage: f64, seed: Option<u64>) -> BernoulliGate {
+		BernoulliGate::new(Percentage::new(percentage).unwrap(), seed)
+	}
+
+	fn candidates() -> impl Iterator<Item = CandidateHash> {
+		(0..=255).map(|n| CandidateHash(Hash::repeat_byte(n)))
+	}
+
+	#[test]
+	fn variant_targets_are_distinct_and_prefixed() {
+		let distinct: HashSet<_> = VARIANT_TARGETS.iter().collect();
+		assert_eq!(distinct.len(), VARIANT_TARGETS.len());
+
+		for target in VARIANT_TARGETS {
+			assert!(target.starts_with(&format!("{}::", MALUS)), "{}", target);
+		}
+	}
+
+	#[test]
+	fn spawn_prefix_distinguishes_task_names() {
+		assert_eq!(spawn_name(DEFAULT_SPAWN_PREFIX, "delay-statements"), "malus-delay-statements");
+		assert_eq!(
+			spawn_name(
+				SpawnOptions { spawn_prefix: "malus-2".into() }.static_spawn_prefix(),
+				"delay-statements"
+			),
+			"malus-2-delay-statements"
+		);
+	}
+
+	#[test]
+	fn gate_never_acts_at_zero_percent() {
+		let mut gate = gate(0.0, Some(0));
+		assert!(candidates().all(|c| !gate.should_act(c)));
+	}
+
+	#[test]
+	fn gate_always_acts_at_full_percent() {
+		let mut gate = gate(100.0, None);
+		assert!(candidates().all(|c| gate.should_act(c)));
+	}
+
+	#[test]
+	fn gate_caches_decisions_per_candidate() {
+		let mut gate = gate(50.0, None);
+
+		let first: Vec<_> = candidates().map(|c| gate.should_act(c)).collect();
+		for _ in 0..4 {
+			let again: Vec<_> = candidates().map(|c| gate.should_act(c)).collect();
+			assert_eq!(again, first);
+		}
+		// with 256 candidates at 50%, both outcomes occur.
+		assert!(first.contains(&true) && first.contains(&false));
+	}
+
+	#[test]
+	fn decisions_are_forgotten_once_finalized() {
+		let mut gate = gate(50.0, None);
+		gate.observe_signal(&activate_leaf(2));
+
+		let first: Vec<_> = candidates().map(|c| gate.should_act(c)).collect();
+		gate.observe_signal(&OverseerSignal::BlockFinalized(Hash::repeat_byte(1), 1));
+		let kept: Vec<_> = candidates().map(|c| gate.should_act(c)).collect();
+		assert_eq!(kept, first);
+
+		// asking again after a new leaf keeps a decision until that leaf is finalized.
+		gate.observe_signal(&activate_leaf(3));
+		let kept_candidate = CandidateHash(Hash::repeat_byte(0));
+		gate.should_act(kept_candidate);
+		gate.observe_signal(&OverseerSignal::BlockFinalized(Hash::repeat_byte(2), 2));
+		assert_eq!(gate.decisions.keys().collect::<Vec<_>>(), vec![&kept_candidate]);
+
+		gate.observe_signal(&OverseerSignal::BlockFinalized(Hash::repeat_byte(3), 3));
+		assert!(gate.decisions.is_empty());
+		let again: Vec<_> = candidates().map(|c| gate.should_act(c)).collect();
+		// with 256 candidates at 50%, the fresh decisions differ from the forgotten ones.
+		assert_ne!(again, first);
+	}
+
+	#[test]
+	fn seeded_gates_decide_alike() {
+		let mut a = gate(30.0, Some(1));
+		let mut b = gate(30.0, Some(1));
+
+		for c in candidates() {
+			assert_eq!(a.should_act(c), b.should_act(c));
+		}
+	}
+
+	#[test]
+	fn distinct_replacements_are_applied() {
+		let builder = ReplacementGuard::new(Vec::new())
+			.replace(ReplacedSubsystem::CandidateBacking, |mut b| {
+				b.push("backing");
+				b
+			})
+			.unwrap()
+			.replace(ReplacedSubsystem::CandidateValidation, |mut b| {
+				b.push("validation");
+				b
+			})
+			.unwrap()
+			.into_inner();
+
+		assert_eq!(builder, vec!["backing", "validation"]);
+	}
+
+	#[test]
+	fn double_replacement_is_rejected() {
+		let result = ReplacementGuard::new(())
+			.replace(ReplacedSubsystem::CandidateBacking, |b| b)
+			.unwrap()
+			.replace(ReplacedSubsystem::CandidateBacking, |b| b);
+
+		assert_matches::assert_matches!(
+			result.map(ReplacementGuard::into_inner),
+			Err(Error::Overseer(SubsystemError::Context(_)))
+		);
+	}
+
+	#[test]
+	fn double_replacement_on_overseer_builder_is_rejected() {
+		let builder =
+			dummy_overseer_builder(TaskExecutor::new(), AlwaysSupportsParachains, None).unwrap();
+		let result = ReplacementGuard::new(builder)
+			.replace(ReplacedSubsystem::CandidateBacking, |b| b.replace_candidate_backing(|cb| cb))
+			.unwrap()
+			.replace(ReplacedSubsystem::CandidateBacking, |b| b.replace_candidate_backing(|cb| cb));
+
+		assert_matches::assert_matches!(
+			result.err(),
+			Some(Error::Overseer(SubsystemError::Context(_)))
+		);
+	}
+}
@@ -15,6 +15,7 @@
 // along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::*;
+use crate::shared::DEFAULT_SPAWN_PREFIX;
 
 use polkadot_node_subsystem_test_helpers::*;
 
@@ -98,7 +99,8 @@ fn integrity_test_intercept() {
 	launch_harness(|mut overseer| {
 		let sub = DummySubsystem;
 
-		let sub_intercepted = InterceptedSubsystem::new(sub, BlackHoleInterceptor);
+		let sub_intercepted =
+			InterceptedSubsystem::new("test", DEFAULT_SPAWN_PREFIX, sub, BlackHoleInterceptor);
 
 		(
 			async move {
@@ -121,7 +123,8 @@ fn integrity_test_pass() {
 	launch_harness(|mut overseer| {
 		let sub = DummySubsystem;
 
-		let sub_intercepted = InterceptedSubsystem::new(sub, PassInterceptor);
+		let sub_intercepted =
+			InterceptedSubsystem::new("test", DEFAULT_SPAWN_PREFIX, sub, PassInterceptor);
 
 		(
 			async move {
@@ -0,0 +1,422 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that equivocates in approval voting. For a configurable
+//! fraction of its approvals, it gossips a second vote under the same assignment,
+//! signed over a different candidate hash, to all connected peers, in addition to
+//! distributing the honest vote.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_network_protocol::{v1 as protocol_v1, PeerId, Versioned};
+use polkadot_node_primitives::approval::IndirectSignedApprovalVote;
+use polkadot_node_subsystem::{OverseerSignal, SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{
+	ApprovalVote, BlockNumber, CandidateHash, Hash, SessionIndex, ValidatorPair,
+	PARACHAIN_KEY_TYPE_ID,
+};
+use sc_keystore::LocalKeystore;
+use sp_core::{crypto::Pair, traits::SpawnNamed};
+use sp_keystore::SyncCryptoStore;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{BernoulliGate, DEFAULT_SPAWN_PREFIX, MALUS_APPROVAL_EQUIVOCATION},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::{
+	collections::{HashMap, HashSet},
+	sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct ApprovalEquivocationOptions {
+	/// Determines the percentage of approvals for which a conflicting vote is issued.
+	/// Defaults to equivocating on all approvals.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// A block relevant to approval voting.
+struct BlockEntry {
+	number: BlockNumber,
+	session: SessionIndex,
+	/// The candidates included by the block, in order of their candidate index.
+	candidates: Vec<CandidateHash>,
+}
+
+#[derive(Default)]
+struct Inner {
+	/// Unfinalized blocks, as announced by approval voting.
+	blocks: HashMap<Hash, BlockEntry>,
+	/// Peers currently connected on the validation peer set.
+	peers: HashSet<PeerId>,
+}
+
+/// Issue a conflicting approval vote alongside a subset of our approvals.
+#[derive(Clone)]
+struct EquivocateApprovals {
+	inner: Arc<Mutex<Inner>>,
+	keystore: Arc<LocalKeystore>,
+	/// Decides once per approved candidate whether to equivocate on it.
+	gate: Arc<Mutex<BernoulliGate>>,
+}
+
+impl EquivocateApprovals {
+	fn new(keystore: Arc<LocalKeystore>, percentage: Percentage) -> Self {
+		Self {
+			inner: Arc::new(Mutex::new(Inner::default())),
+			keystore,
+			gate: Arc::new(Mutex::new(BernoulliGate::new(percentage, None))),
+		}
+	}
+
+	/// Whether to equivocate on `vote`. Votes on candidates of unknown blocks are never
+	/// equivocated on, as there is no candidate hash to sign a conflicting vote over.
+	fn should_equivocate(&self, vote: &IndirectSignedApprovalVote) -> bool {
+		let candidate_hash = {
+			let inner = self.inner.lock().expect("bad lock");
+			inner
+				.blocks
+				.get(&vote.block_hash)
+				.and_then(|entry| entry.candidates.get(vote.candidate_index as usize).copied())
+		};
+
+		candidate_hash.map_or(false, |candidate_hash| {
+			self.gate.lock().expect("bad lock").should_act(candidate_hash)
+		})
+	}
+
+	/// Create a vote under the same assignment as `vote`, signed by the same key over a
+	/// different candidate hash. Returns `None` if the block is unknown or the key that
+	/// signed `vote` is not in our keystore.
+	fn conflicting_vote(
+		&self,
+		vote: &IndirectSignedApprovalVote,
+	) -> Option<IndirectSignedApprovalVote> {
+		let (session, candidate_hash) = {
+			let inner = self.inner.lock().expect("bad lock");
+			let entry = inner.blocks.get(&vote.block_hash)?;
+			(entry.session, *entry.candidates.get(vote.candidate_index as usize)?)
+		};
+
+		let payload = ApprovalVote(candidate_hash).signing_payload(session);
+		let key = SyncCryptoStore::sr25519_public_keys(&*self.keystore, PARACHAIN_KEY_TYPE_ID)
+			.into_iter()
+			.filter_map(|public| self.keystore.key_pair::<ValidatorPair>(&public.into()).ok()?)
+			.find(|pair| ValidatorPair::verify(&vote.signature, &payload, &pair.public()))?;
+
+		let conflicting_hash = CandidateHash(sp_core::hashing::blake2_256(&payload[..]).into());
+		let signature = key.sign(&ApprovalVote(conflicting_hash).signing_payload(session)[..]);
+
+		Some(IndirectSignedApprovalVote { signature, ..vote.clone() })
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for EquivocateApprovals
+where
+	Sender: overseer::ApprovalDistributionSenderTrait + Clone + Send + 'static,
+{
+	type Message = ApprovalDistributionMessage;
+
+	/// Track blocks and peers, and gossip a conflicting vote for the approvals we
+	/// equivocate on. All messages are passed on to approval distribution unchanged.
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match &msg {
+			FromOrchestra::Communication { msg: ApprovalDistributionMessage::NewBlocks(metas) } => {
+				let mut inner = self.inner.lock().expect("bad lock");
+				for meta in metas {
+					inner.blocks.insert(
+						meta.hash,
+						BlockEntry {
+							number: meta.number,
+							session: meta.session,
+							candidates: meta.candidates.clone(),
+						},
+					);
+				}
+			},
+			FromOrchestra::Communication {
+				msg: ApprovalDistributionMessage::NetworkBridgeUpdate(event),
+			} => match event {
+				NetworkBridgeEvent::PeerConnected(peer, ..) => {
+					self.inner.lock().expect("bad lock").peers.insert(*peer);
+				},
+				NetworkBridgeEvent::PeerDisconnected(peer) => {
+					self.inner.lock().expect("bad lock").peers.remove(peer);
+				},
+				_ => {},
+			},
+			FromOrchestra::Communication {
+				msg: ApprovalDistributionMessage::DistributeApproval(vote),
+			} if self.should_equivocate(vote) => match self.conflicting_vote(vote) {
+				Some(conflicting) => {
+					let peers: Vec<_> =
+						self.inner.lock().expect("bad lock").peers.iter().cloned().collect();
+
+					gum::debug!(
+						target: MALUS_APPROVAL_EQUIVOCATION,
+						block_hash = ?vote.block_hash,
+						candidate_index = vote.candidate_index,
+						validator = ?vote.validator,
+						n_peers = peers.len(),
+						"😈 Equivocating on approval"
+					);
+
+					if !peers.is_empty() {
+						subsystem_sender
+							.send_message(NetworkBridgeTxMessage::SendValidationMessage(
+								peers,
+								Versioned::V1(
+									protocol_v1::ValidationProtocol::ApprovalDistribution(
+										protocol_v1::ApprovalDistributionMessage::Approvals(vec![
+											conflicting,
+										]),
+									),
+								),
+							))
+							.await;
+					}
+				},
+				None => {
+					gum::warn!(
+						target: MALUS_APPROVAL_EQUIVOCATION,
+						block_hash = ?vote.block_hash,
+						candidate_index = vote.candidate_index,
+						"Unable to create a conflicting approval vote"
+					);
+				},
+			},
+			FromOrchestra::Signal(OverseerSignal::BlockFinalized(_, finalized)) => {
+				self.inner
+					.lock()
+					.expect("bad lock")
+					.blocks
+					.retain(|_, entry| entry.number > *finalized);
+			},
+			_ => {},
+		}
+
+		Some(msg)
+	}
+
+	fn observe_signal(&self, signal: &OverseerSignal) {
+		self.gate.lock().expect("bad lock").observe_signal(signal);
+	}
+}
+
+/// Generates an overseer that replaces the approval distribution subsystem with our
+/// malicious variant.
+pub(crate) struct ApprovalEquivocation {
+	/// Percentage of approvals to equivocate on.
+	percentage: u8,
+}
+
+impl ApprovalEquivocation {
+	pub fn new(opts: ApprovalEquivocationOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for ApprovalEquivocation {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_APPROVAL_EQUIVOCATION,
+			"😈 Started Malus node equivocating on {:?} percent of approvals.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the equivocation percentage: {}", e))
+		})?;
+		let equivocate_approvals = EquivocateApprovals::new(args.keystore.clone(), percentage);
+
+		prepared_overseer_builder(args)?
+			.replace_approval_distribution(move |approval_distribution| {
+				InterceptedSubsystem::new(
+					"approval-equivocation",
+					DEFAULT_SPAWN_PREFIX,
+					approval_distribution,
+					equivocate_approvals,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+	use polkadot_node_primitives::approval::BlockApprovalMeta;
+	use polkadot_node_subsystem_test_helpers::sender_receiver;
+	use polkadot_primitives::v2::{ValidatorId, ValidatorIndex};
+
+	const SESSION: SessionIndex = 1;
+
+	fn block_hash() -> Hash {
+		Hash::repeat_byte(1)
+	}
+
+	fn candidate_hash() -> CandidateHash {
+		CandidateHash(Hash::repeat_byte(2))
+	}
+
+	/// An interceptor with a single validator key, aware of a block including a single
+	/// candidate and of a single peer. Returns our honest vote on that candidate alongside.
+	fn setup(percentage: f64) -> (EquivocateApprovals, IndirectSignedApprovalVote) {
+		let keystore = Arc::new(LocalKeystore::in_memory());
+		let public: ValidatorId =
+			SyncCryptoStore::sr25519_generate_new(&*keystore, PARACHAIN_KEY_TYPE_ID, None)
+				.unwrap()
+				.into();
+		let pair = keystore.key_pair::<ValidatorPair>(&public).unwrap().unwrap();
+
+		let interceptor = EquivocateApprovals::new(keystore, Percentage::new(percentage).unwrap());
+		{
+			let mut inner = interceptor.inner.lock().unwrap();
+			inner.blocks.insert(
+				block_hash(),
+				BlockEntry { number: 1, session: SESSION, candidates: vec![candidate_hash()] },
+			);
+			inner.peers.insert(PeerId::random());
+		}
+
+		let vote = IndirectSignedApprovalVote {
+			block_hash: block_hash(),
+			candidate_index: 0,
+			validator: ValidatorIndex(0),
+			signature: pair.sign(&ApprovalVote(candidate_hash()).signing_payload(SESSION)[..]),
+		};
+
+		(interceptor, vote)
+	}
+
+	fn distribute(vote: IndirectSignedApprovalVote) -> FromOrchestra<ApprovalDistributionMessage> {
+		FromOrchestra::Communication { msg: ApprovalDistributionMessage::DistributeApproval(vote) }
+	}
+
+	#[test]
+	fn conflicting_vote_is_gossiped() {
+		let (interceptor, vote) = setup(100.0);
+		let (mut sender, mut rx) = sender_receiver();
+
+		// The honest vote is passed on to approval distribution.
+		assert_matches::assert_matches!(
+			block_on(interceptor.intercept_incoming(&mut sender, distribute(vote.clone()))),
+			Some(FromOrchestra::Communication {
+				msg: ApprovalDistributionMessage::DistributeApproval(v),
+			}) => assert_eq!(v, vote)
+		);
+
+		// While a conflicting vote under the same assignment goes straight to the network.
+		assert_matches::assert_matches!(
+			rx.try_next(),
+			Ok(Some(AllMessages::NetworkBridgeTx(NetworkBridgeTxMessage::SendValidationMessage(
+				peers,
+				Versioned::V1(protocol_v1::ValidationProtocol::ApprovalDistribution(
+					protocol_v1::ApprovalDistributionMessage::Approvals(votes),
+				)),
+			)))) => {
+				assert_eq!(peers.len(), 1);
+				assert_eq!(votes.len(), 1);
+				assert_eq!(votes[0].block_hash, vote.block_hash);
+				assert_eq!(votes[0].candidate_index, vote.candidate_index);
+				assert_eq!(votes[0].validator, vote.validator);
+				assert_ne!(votes[0].signature, vote.signature);
+			}
+		);
+	}
+
+	#[test]
+	fn honest_when_not_sampled() {
+		let (interceptor, vote) = setup(0.0);
+		let (mut sender, mut rx) = sender_receiver();
+
+		assert!(block_on(interceptor.intercept_incoming(&mut sender, distribute(vote))).is_some());
+		assert!(rx.try_next().is_err());
+	}
+
+	#[test]
+	fn unknown_block_is_not_equivocated_on() {
+		let (interceptor, vote) = setup(100.0);
+		let (mut sender, mut rx) = sender_receiver();
+
+		let vote = IndirectSignedApprovalVote { block_hash: Hash::repeat_byte(3), ..vote };
+		assert!(block_on(interceptor.intercept_incoming(&mut sender, distribute(vote))).is_some());
+		assert!(rx.try_next().is_err());
+	}
+
+	#[test]
+	fn finalized_blocks_are_pruned() {
+		let (interceptor, _) = setup(100.0);
+		let (mut sender, _) = sender_receiver();
+
+		let new_block = ApprovalDistributionMessage::NewBlocks(vec![BlockApprovalMeta {
+			hash: Hash::repeat_byte(3),
+			number: 2,
+			parent_hash: block_hash(),
+			candidates: Vec::new(),
+			slot: 1.into(),
+			session: SESSION,
+		}]);
+		block_on(
+			interceptor
+				.intercept_incoming(&mut sender, FromOrchestra::Communication { msg: new_block }),
+		);
+		block_on(interceptor.intercept_incoming(
+			&mut sender,
+			FromOrchestra::Signal(OverseerSignal::BlockFinalized(block_hash(), 1)),
+		));
+
+		let blocks = &interceptor.inner.lock().unwrap().blocks;
+		assert!(!blocks.contains_key(&block_hash()));
+		assert!(blocks.contains_key(&Hash::repeat_byte(3)));
+	}
+}
@@ -31,6 +31,7 @@ use sp_core::traits::SpawnNamed;
 
 use crate::{
 	interceptor::*,
+	shared::DEFAULT_SPAWN_PREFIX,
 	variants::{FakeCandidateValidation, FakeCandidateValidationError, ReplaceValidationResult},
 };
 
@@ -51,16 +52,19 @@ impl OverseerGen for BackGarbageCandidate {
 		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
 		Spawner: 'static + SpawnNamed + Clone + Unpin,
 	{
-		let spawner = args.spawner.clone();
 		let validation_filter = ReplaceValidationResult::new(
 			FakeCandidateValidation::BackingAndApprovalValid,
 			FakeCandidateValidationError::InvalidOutputs,
-			SpawnGlue(spawner),
 		);
 
 		prepared_overseer_builder(args)?
 			.replace_candidate_validation(move |cv_subsystem| {
-				InterceptedSubsystem::new(cv_subsystem, validation_filter)
+				InterceptedSubsystem::new(
+					"back-garbage-candidate",
+					DEFAULT_SPAWN_PREFIX,
+					cv_subsystem,
+					validation_filter,
+				)
 			})
 			.build_with_connector(connector)
 			.map_err(|e| e.into())
@@ -0,0 +1,223 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that distributes a configurable fraction of its approval votes
+//! with a signature that does not verify, which exercises the signature checks and
+//! reputation handling of approval distribution on honest nodes.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_primitives::approval::IndirectSignedApprovalVote;
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::ValidatorPair;
+use sp_core::{crypto::Pair, traits::SpawnNamed};
+
+use rand::RngCore;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_BAD_APPROVAL_SIG},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct BadApprovalSigOptions {
+	/// Determines the percentage of approval votes distributed with a bad signature.
+	/// Defaults to all approval votes.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Replace the signature of the vote with a signature over random bytes by a freshly
+/// generated key, which is well formed but does not verify for any validator.
+fn corrupt_signature(vote: IndirectSignedApprovalVote) -> IndirectSignedApprovalVote {
+	let mut payload = [0u8; 32];
+	rand::thread_rng().fill_bytes(&mut payload);
+	let signature = ValidatorPair::generate().0.sign(&payload[..]);
+
+	IndirectSignedApprovalVote { signature, ..vote }
+}
+
+/// Corrupts the signature of approval votes to be distributed.
+#[derive(Clone)]
+struct CorruptApprovalSignatures {
+	percentage: Percentage,
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for CorruptApprovalSignatures
+where
+	Sender: overseer::ApprovalVotingSenderTrait + Clone + Send + 'static,
+{
+	type Message = ApprovalVotingMessage;
+
+	/// Approval voting has already imported the honest vote, only the distributed copy
+	/// carries the bad signature.
+	fn intercept_outgoing(
+		&self,
+		msg: overseer::ApprovalVotingOutgoingMessages,
+	) -> Option<overseer::ApprovalVotingOutgoingMessages> {
+		match msg {
+			overseer::ApprovalVotingOutgoingMessages::ApprovalDistributionMessage(
+				ApprovalDistributionMessage::DistributeApproval(vote),
+			) if self.percentage.sample(&mut rand::thread_rng()) => {
+				gum::debug!(
+					target: MALUS_BAD_APPROVAL_SIG,
+					block_hash = ?vote.block_hash,
+					candidate_index = vote.candidate_index,
+					validator = ?vote.validator,
+					"😈 Corrupting approval vote signature"
+				);
+				Some(overseer::ApprovalVotingOutgoingMessages::ApprovalDistributionMessage(
+					ApprovalDistributionMessage::DistributeApproval(corrupt_signature(vote)),
+				))
+			},
+			msg => Some(msg),
+		}
+	}
+}
+
+/// Generates an overseer that distributes approval votes with bad signatures.
+pub(crate) struct BadApprovalSig {
+	/// Percentage of approval votes to corrupt.
+	percentage: u8,
+}
+
+impl BadApprovalSig {
+	pub fn new(opts: BadApprovalSigOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for BadApprovalSig {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_BAD_APPROVAL_SIG,
+			"😈 Started Malus node corrupting the signature of {:?} percent of approval votes.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!(
+				"configure the bad approval signature percentage: {}",
+				e
+			))
+		})?;
+		let corrupt_signatures = CorruptApprovalSignatures { percentage };
+
+		prepared_overseer_builder(args)?
+			.replace_approval_voting(move |av| {
+				InterceptedSubsystem::new(
+					"bad-approval-sig",
+					DEFAULT_SPAWN_PREFIX,
+					av,
+					corrupt_signatures,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use polkadot_node_subsystem_test_helpers::TestSubsystemSender;
+	use polkadot_primitives::v2::{ApprovalVote, CandidateHash, Hash, ValidatorIndex};
+
+	/// A vote on a candidate signed by `pair`, along with the signed payload.
+	fn vote(pair: &ValidatorPair) -> (IndirectSignedApprovalVote, Vec<u8>) {
+		let payload = ApprovalVote(CandidateHash(Hash::repeat_byte(2))).signing_payload(1);
+		let vote = IndirectSignedApprovalVote {
+			block_hash: Hash::repeat_byte(1),
+			candidate_index: 0,
+			validator: ValidatorIndex(0),
+			signature: pair.sign(&payload[..]),
+		};
+		(vote, payload)
+	}
+
+	fn distribute(
+		interceptor: &CorruptApprovalSignatures,
+		vote: IndirectSignedApprovalVote,
+	) -> IndirectSignedApprovalVote {
+		let msg = overseer::ApprovalVotingOutgoingMessages::ApprovalDistributionMessage(
+			ApprovalDistributionMessage::DistributeApproval(vote),
+		);
+		match MessageInterceptor::<TestSubsystemSender>::intercept_outgoing(interceptor, msg) {
+			Some(overseer::ApprovalVotingOutgoingMessages::ApprovalDistributionMessage(
+				ApprovalDistributionMessage::DistributeApproval(vote),
+			)) => vote,
+			_ => panic!("approval vote is not distributed"),
+		}
+	}
+
+	#[test]
+	fn corrupted_signature_fails_verification() {
+		let pair = ValidatorPair::generate().0;
+		let (vote, payload) = vote(&pair);
+		assert!(ValidatorPair::verify(&vote.signature, &payload, &pair.public()));
+
+		let corrupted = corrupt_signature(vote.clone());
+		assert!(!ValidatorPair::verify(&corrupted.signature, &payload, &pair.public()));
+		assert_eq!(
+			IndirectSignedApprovalVote { signature: vote.signature.clone(), ..corrupted },
+			vote
+		);
+	}
+
+	#[test]
+	fn corruption_respects_percentage() {
+		let pair = ValidatorPair::generate().0;
+		let (vote, payload) = vote(&pair);
+		let always = CorruptApprovalSignatures { percentage: Percentage::new(100.0).unwrap() };
+		let never = CorruptApprovalSignatures { percentage: Percentage::new(0.0).unwrap() };
+
+		for _ in 0..10 {
+			let corrupted = distribute(&always, vote.clone());
+			assert!(!ValidatorPair::verify(&corrupted.signature, &payload, &pair.public()));
+			assert_eq!(distribute(&never, vote.clone()), vote);
+		}
+	}
+}
@@ -0,0 +1,258 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that seconds candidates with the real PoV, but with an
+//! `erasure_root` in the descriptor that does not match the chunks derived from it.
+//! Unlike `suggest-garbage-candidate`, the chunks themselves stay valid, which
+//! exercises the erasure root mismatch detection of backing and availability
+//! recovery instead of full candidate garbage.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_primitives::AvailableData;
+use polkadot_node_subsystem::{OverseerSignal, SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{CandidateDescriptor, CandidateReceipt, Hash};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{BernoulliGate, DEFAULT_SPAWN_PREFIX, MALUS_BAD_ERASURE_ROOT},
+	variants::{fetch_validation_data, parse_percentage, Percentage},
+};
+
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct BadErasureRootOptions {
+	/// Determines the percentage of seconded candidates whose erasure root gets corrupted.
+	/// Defaults to corrupting all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Derive an erasure root that is guaranteed to differ from `root`.
+fn corrupt_erasure_root(root: Hash) -> Hash {
+	root ^ Hash::repeat_byte(0xff)
+}
+
+/// Replaces the erasure root of candidates to be seconded.
+#[derive(Clone)]
+struct CorruptErasureRoot {
+	gate: Arc<Mutex<BernoulliGate>>,
+}
+
+impl CorruptErasureRoot {
+	fn new(percentage: Percentage) -> Self {
+		Self { gate: Arc::new(Mutex::new(BernoulliGate::new(percentage, None))) }
+	}
+
+	fn should_corrupt(&self, candidate: &CandidateReceipt) -> bool {
+		self.gate.lock().expect("bad lock").should_act(candidate.hash())
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for CorruptErasureRoot
+where
+	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+{
+	type Message = CandidateBackingMessage;
+
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			} => {
+				if !self.should_corrupt(&candidate) {
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				let (validation_data, validation_code, n_validators) = match fetch_validation_data(
+					subsystem_sender,
+					relay_parent,
+					candidate.descriptor(),
+				)
+				.await
+				{
+					Some(data) => data,
+					None => {
+						gum::warn!(
+							target: MALUS_BAD_ERASURE_ROOT,
+							candidate_hash = ?candidate.hash(),
+							?relay_parent,
+							"Unable to fetch validation data, not corrupting erasure root"
+						);
+						return Some(FromOrchestra::Communication {
+							msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+						})
+					},
+				};
+
+				let validation_data_hash = validation_data.hash();
+				let validation_code_hash = validation_code.hash();
+				let pov_hash = pov.hash();
+
+				let available_data = AvailableData { pov: Arc::new(pov.clone()), validation_data };
+				let erasure_root = match erasure::obtain_chunks_v1(n_validators, &available_data) {
+					Ok(chunks) => erasure::branches(chunks.as_ref()).root(),
+					Err(err) => {
+						gum::warn!(
+							target: MALUS_BAD_ERASURE_ROOT,
+							candidate_hash = ?candidate.hash(),
+							?err,
+							"Unable to erasure code candidate, not corrupting erasure root"
+						);
+						return Some(FromOrchestra::Communication {
+							msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+						})
+					},
+				};
+				let corrupted_erasure_root = corrupt_erasure_root(erasure_root);
+
+				let (collator_id, collator_signature) = {
+					use polkadot_primitives::v2::CollatorPair;
+					use sp_core::crypto::Pair;
+
+					let collator_pair = CollatorPair::generate().0;
+					let signature_payload = polkadot_primitives::v2::collator_signature_payload(
+						&relay_parent,
+						&candidate.descriptor().para_id,
+						&validation_data_hash,
+						&pov_hash,
+						&validation_code_hash,
+					);
+
+					(collator_pair.public(), collator_pair.sign(&signature_payload))
+				};
+
+				let malicious_candidate = CandidateReceipt {
+					descriptor: CandidateDescriptor {
+						collator: collator_id,
+						signature: collator_signature,
+						erasure_root: corrupted_erasure_root,
+						..candidate.descriptor.clone()
+					},
+					commitments_hash: candidate.commitments_hash,
+				};
+
+				gum::info!(
+					target: MALUS_BAD_ERASURE_ROOT,
+					candidate_hash = ?candidate.hash(),
+					malicious_candidate_hash = ?malicious_candidate.hash(),
+					?erasure_root,
+					?corrupted_erasure_root,
+					"😈 Corrupted erasure root of candidate"
+				);
+
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(relay_parent, malicious_candidate, pov),
+				})
+			},
+			other => Some(other),
+		}
+	}
+
+	fn observe_signal(&self, signal: &OverseerSignal) {
+		self.gate.lock().expect("bad lock").observe_signal(signal);
+	}
+}
+
+/// Generates an overseer that corrupts the erasure root of seconded candidates.
+pub(crate) struct BadErasureRoot {
+	percentage: u8,
+}
+
+impl BadErasureRoot {
+	pub fn new(opts: BadErasureRootOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for BadErasureRoot {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_BAD_ERASURE_ROOT,
+			"😈 Started Malus node corrupting the erasure root of {:?} percent of seconded candidates.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!(
+				"configure the corrupted erasure root percentage: {}",
+				e
+			))
+		})?;
+		let corrupt_erasure_root = CorruptErasureRoot::new(percentage);
+
+		prepared_overseer_builder(args)?
+			.replace_candidate_backing(move |cb| {
+				InterceptedSubsystem::new(
+					"bad-erasure-root",
+					DEFAULT_SPAWN_PREFIX,
+					cb,
+					corrupt_erasure_root,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn corrupted_root_differs_and_is_deterministic() {
+		for byte in [0x00, 0x0f, 0xff] {
+			let root = Hash::repeat_byte(byte);
+			assert_ne!(corrupt_erasure_root(root), root);
+			assert_eq!(corrupt_erasure_root(root), corrupt_erasure_root(root));
+		}
+	}
+}
@@ -0,0 +1,197 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that seconds the real candidate, but with a `para_head` in the
+//! descriptor that does not match the hash of the head data produced by the PoV.
+//! Everything else, including the collator signature which does not cover the
+//! `para_head`, stays consistent, which exercises the head data check of candidate
+//! validation instead of full candidate garbage.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{CandidateDescriptor, CandidateReceipt, Hash};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_BAD_PARA_HEAD},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct BadParaHeadOptions {
+	/// Determines the percentage of seconded candidates whose `para_head` gets replaced.
+	/// Defaults to replacing all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Replace the `para_head` of `descriptor` with a hash that is guaranteed to differ
+/// from `head_data_hash`, the hash of the head data the candidate commits to.
+fn mismatch_para_head(
+	descriptor: &CandidateDescriptor,
+	head_data_hash: Hash,
+) -> CandidateDescriptor {
+	CandidateDescriptor {
+		para_head: head_data_hash ^ Hash::repeat_byte(0xff),
+		..descriptor.clone()
+	}
+}
+
+/// Replaces the `para_head` of candidates to be seconded.
+#[derive(Clone)]
+struct ReplaceParaHead {
+	percentage: Percentage,
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for ReplaceParaHead
+where
+	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+{
+	type Message = CandidateBackingMessage;
+
+	async fn intercept_incoming(
+		&self,
+		_subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			} => {
+				if !self.percentage.sample(&mut rand::thread_rng()) {
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				// The collator already committed to the real head data in the descriptor.
+				let para_head = candidate.descriptor.para_head;
+				let malicious_candidate = CandidateReceipt {
+					descriptor: mismatch_para_head(&candidate.descriptor, para_head),
+					commitments_hash: candidate.commitments_hash,
+				};
+
+				gum::info!(
+					target: MALUS_BAD_PARA_HEAD,
+					candidate_hash = ?candidate.hash(),
+					malicious_candidate_hash = ?malicious_candidate.hash(),
+					?para_head,
+					malicious_para_head = ?malicious_candidate.descriptor.para_head,
+					"😈 Replaced para head of candidate"
+				);
+
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(relay_parent, malicious_candidate, pov),
+				})
+			},
+			other => Some(other),
+		}
+	}
+}
+
+/// Generates an overseer that replaces the `para_head` of seconded candidates.
+pub(crate) struct BadParaHead {
+	percentage: u8,
+}
+
+impl BadParaHead {
+	pub fn new(opts: BadParaHeadOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for BadParaHead {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_BAD_PARA_HEAD,
+			"😈 Started Malus node replacing the para head of {:?} percent of seconded candidates.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the bad para head percentage: {}", e))
+		})?;
+		let replace_para_head = ReplaceParaHead { percentage };
+
+		prepared_overseer_builder(args)?
+			.replace_candidate_backing(move |cb| {
+				InterceptedSubsystem::new(
+					"bad-para-head",
+					DEFAULT_SPAWN_PREFIX,
+					cb,
+					replace_para_head,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use polkadot_primitives::v2::HeadData;
+	use polkadot_primitives_test_helpers::{dummy_candidate_commitments, dummy_candidate_receipt};
+
+	#[test]
+	fn para_head_differs_from_head_data_hash() {
+		for head_data in [HeadData(vec![]), HeadData(vec![1, 2, 3])] {
+			let commitments = dummy_candidate_commitments(head_data);
+			let mut candidate = dummy_candidate_receipt(Hash::repeat_byte(1));
+			candidate.descriptor.para_head = commitments.head_data.hash();
+
+			let descriptor =
+				mismatch_para_head(&candidate.descriptor, commitments.head_data.hash());
+
+			assert_ne!(descriptor.para_head, commitments.head_data.hash());
+			assert_eq!(
+				CandidateDescriptor { para_head: candidate.descriptor.para_head, ..descriptor },
+				candidate.descriptor,
+			);
+		}
+	}
+}
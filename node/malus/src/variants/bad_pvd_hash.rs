@@ -0,0 +1,255 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that seconds the real candidate and `PoV`, but with a
+//! `persisted_validation_data_hash` in the descriptor that does not match the
+//! validation data of the candidate, which the `PoV` is made available along with.
+//! The descriptor is re-signed, so the collator signature stays valid, which
+//! exercises the validation data check of candidate validation instead of full
+//! candidate garbage.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{CandidateDescriptor, CandidateReceipt, Hash};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_BAD_PVD_HASH},
+	variants::{fetch_validation_data, parse_percentage, sign_with_new_collator, Percentage},
+};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct BadPvdHashOptions {
+	/// Determines the percentage of seconded candidates whose validation data hash gets
+	/// replaced. Defaults to replacing all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Replace the `persisted_validation_data_hash` of `descriptor` with a hash that is
+/// guaranteed to differ from `validation_data_hash`, the hash of the actual validation
+/// data, and re-sign the descriptor.
+fn mismatch_validation_data_hash(
+	descriptor: &CandidateDescriptor,
+	validation_data_hash: Hash,
+) -> CandidateDescriptor {
+	sign_with_new_collator(CandidateDescriptor {
+		persisted_validation_data_hash: validation_data_hash ^ Hash::repeat_byte(0xff),
+		..descriptor.clone()
+	})
+}
+
+/// Replaces the `persisted_validation_data_hash` of candidates to be seconded.
+#[derive(Clone)]
+struct ReplaceValidationDataHash {
+	percentage: Percentage,
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for ReplaceValidationDataHash
+where
+	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+{
+	type Message = CandidateBackingMessage;
+
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			} => {
+				if !self.percentage.sample(&mut rand::thread_rng()) {
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				// The validation data the `PoV` is made available along with.
+				let validation_data_hash = match fetch_validation_data(
+					subsystem_sender,
+					relay_parent,
+					candidate.descriptor(),
+				)
+				.await
+				{
+					Some((validation_data, _, _)) => validation_data.hash(),
+					None => {
+						gum::warn!(
+							target: MALUS_BAD_PVD_HASH,
+							candidate_hash = ?candidate.hash(),
+							?relay_parent,
+							"Unable to fetch validation data, not replacing candidate"
+						);
+						return Some(FromOrchestra::Communication {
+							msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+						})
+					},
+				};
+
+				let malicious_candidate = CandidateReceipt {
+					descriptor: mismatch_validation_data_hash(
+						&candidate.descriptor,
+						validation_data_hash,
+					),
+					commitments_hash: candidate.commitments_hash,
+				};
+
+				gum::info!(
+					target: MALUS_BAD_PVD_HASH,
+					candidate_hash = ?candidate.hash(),
+					malicious_candidate_hash = ?malicious_candidate.hash(),
+					?validation_data_hash,
+					malicious_validation_data_hash =
+						?malicious_candidate.descriptor.persisted_validation_data_hash,
+					"😈 Replaced validation data hash of candidate"
+				);
+
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(relay_parent, malicious_candidate, pov),
+				})
+			},
+			other => Some(other),
+		}
+	}
+}
+
+/// Generates an overseer that replaces the validation data hash of seconded candidates.
+pub(crate) struct BadPvdHash {
+	percentage: u8,
+}
+
+impl BadPvdHash {
+	pub fn new(opts: BadPvdHashOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for BadPvdHash {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_BAD_PVD_HASH,
+			"😈 Started Malus node replacing the validation data hash of {:?} percent of seconded candidates.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the bad PVD hash percentage: {}", e))
+		})?;
+		let replace_pvd_hash = ReplaceValidationDataHash { percentage };
+
+		prepared_overseer_builder(args)?
+			.replace_candidate_backing(move |cb| {
+				InterceptedSubsystem::new(
+					"bad-pvd-hash",
+					DEFAULT_SPAWN_PREFIX,
+					cb,
+					replace_pvd_hash,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{with_mock_sender, MockRuntime};
+	use polkadot_node_primitives::{BlockData, PoV};
+
+	#[test]
+	fn validation_data_hash_differs_from_validation_data() {
+		let runtime = MockRuntime::new(4);
+		let validation_data = runtime.validation_data.clone().unwrap();
+		let candidate = runtime.candidate(Hash::repeat_byte(1));
+
+		let descriptor =
+			mismatch_validation_data_hash(&candidate.descriptor, validation_data.hash());
+
+		assert_ne!(descriptor.persisted_validation_data_hash, validation_data.hash());
+		assert!(descriptor.check_collator_signature().is_ok());
+		assert_eq!(
+			CandidateDescriptor {
+				persisted_validation_data_hash: candidate.descriptor.persisted_validation_data_hash,
+				collator: candidate.descriptor.collator.clone(),
+				signature: candidate.descriptor.signature.clone(),
+				..descriptor
+			},
+			candidate.descriptor,
+		);
+	}
+
+	#[test]
+	fn seconded_candidate_mismatches_validation_data() {
+		let runtime = MockRuntime::new(4);
+		let validation_data = runtime.validation_data.clone().unwrap();
+		let relay_parent = Hash::repeat_byte(1);
+		let candidate = runtime.candidate(relay_parent);
+		let interceptor = ReplaceValidationDataHash { percentage: Percentage::new(100.0).unwrap() };
+
+		let (seconded, pov) = with_mock_sender(runtime, |mut sender| async move {
+			let msg = FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(
+					relay_parent,
+					candidate,
+					PoV { block_data: BlockData(vec![1, 2, 3]) },
+				),
+			};
+			match interceptor.intercept_incoming(&mut sender, msg).await {
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(_, candidate, pov),
+				}) => (candidate, pov),
+				_ => panic!("candidate is not seconded"),
+			}
+		});
+
+		assert_ne!(seconded.descriptor.persisted_validation_data_hash, validation_data.hash());
+		assert_eq!(pov.block_data.0, vec![1, 2, 3]);
+	}
+}
@@ -0,0 +1,340 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that equivocates on availability bitfields. For a configurable
+//! fraction of its bitfields, it gossips a second bitfield for the same relay parent,
+//! with all bits flipped and signed by the same key, to all connected peers, in
+//! addition to distributing the honest bitfield.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use parity_scale_codec::Encode;
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_network_protocol::{v1 as protocol_v1, PeerId, Versioned};
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_node_subsystem_util::request_session_index_for_child;
+use polkadot_primitives::v2::{
+	AvailabilityBitfield, Hash, SignedAvailabilityBitfield, SigningContext,
+	UncheckedSignedAvailabilityBitfield, ValidatorPair, PARACHAIN_KEY_TYPE_ID,
+};
+use sc_keystore::LocalKeystore;
+use sp_core::{crypto::Pair, traits::SpawnNamed};
+use sp_keystore::SyncCryptoStore;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_BITFIELD_EQUIVOCATION},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::{
+	collections::HashSet,
+	sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct BitfieldEquivocationOptions {
+	/// Determines the percentage of bitfields for which a conflicting bitfield is issued.
+	/// Defaults to equivocating on all bitfields.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Create a bitfield for the same relay parent as `bitfield`, with all bits flipped and
+/// signed by the same key. Returns `None` if the bitfield has no bits to flip or the key
+/// that signed `bitfield` is not in our keystore.
+fn conflicting_bitfield(
+	keystore: &LocalKeystore,
+	bitfield: &SignedAvailabilityBitfield,
+	context: &SigningContext,
+) -> Option<UncheckedSignedAvailabilityBitfield> {
+	if bitfield.payload().0.is_empty() {
+		return None
+	}
+
+	let key = SyncCryptoStore::sr25519_public_keys(keystore, PARACHAIN_KEY_TYPE_ID)
+		.into_iter()
+		.filter_map(|public| keystore.key_pair::<ValidatorPair>(&public.into()).ok()?)
+		.find(|pair| bitfield.as_unchecked().check_signature(context, &pair.public()).is_ok())?;
+
+	let conflicting = AvailabilityBitfield(!bitfield.payload().0.clone());
+	// signed payloads are signed together with their context.
+	let signature = key.sign(&(&conflicting, context).encode()[..]);
+
+	Some(UncheckedSignedAvailabilityBitfield::new(
+		conflicting,
+		bitfield.validator_index(),
+		signature,
+	))
+}
+
+/// Gossip a conflicting bitfield alongside a subset of our bitfields.
+#[derive(Clone)]
+struct EquivocateBitfields {
+	/// Peers currently connected on the validation peer set.
+	peers: Arc<Mutex<HashSet<PeerId>>>,
+	keystore: Arc<LocalKeystore>,
+	/// Percentage of bitfields to equivocate on.
+	percentage: Percentage,
+}
+
+impl EquivocateBitfields {
+	fn new(keystore: Arc<LocalKeystore>, percentage: Percentage) -> Self {
+		Self { peers: Arc::new(Mutex::new(HashSet::new())), keystore, percentage }
+	}
+
+	fn should_equivocate(&self) -> bool {
+		self.percentage.sample(&mut rand::thread_rng())
+	}
+
+	/// Gossip a conflicting bitfield for our `bitfield` to all peers.
+	async fn equivocate<Sender>(
+		&self,
+		sender: &mut Sender,
+		relay_parent: Hash,
+		bitfield: &SignedAvailabilityBitfield,
+	) where
+		Sender: overseer::BitfieldDistributionSenderTrait,
+	{
+		let session_index = match request_session_index_for_child(relay_parent, sender).await.await
+		{
+			Ok(Ok(session_index)) => session_index,
+			_ => {
+				gum::warn!(
+					target: MALUS_BITFIELD_EQUIVOCATION,
+					?relay_parent,
+					"Unable to fetch the session index, not equivocating"
+				);
+				return
+			},
+		};
+		let context = SigningContext { session_index, parent_hash: relay_parent };
+
+		let conflicting = match conflicting_bitfield(&self.keystore, bitfield, &context) {
+			Some(conflicting) => conflicting,
+			None => {
+				gum::warn!(
+					target: MALUS_BITFIELD_EQUIVOCATION,
+					?relay_parent,
+					validator_index = ?bitfield.validator_index(),
+					"Unable to create a conflicting bitfield"
+				);
+				return
+			},
+		};
+
+		let peers: Vec<_> = self.peers.lock().expect("bad lock").iter().cloned().collect();
+
+		gum::debug!(
+			target: MALUS_BITFIELD_EQUIVOCATION,
+			?relay_parent,
+			validator_index = ?bitfield.validator_index(),
+			n_peers = peers.len(),
+			"😈 Equivocating on bitfield"
+		);
+
+		if peers.is_empty() {
+			return
+		}
+
+		sender
+			.send_message(NetworkBridgeTxMessage::SendValidationMessage(
+				peers,
+				Versioned::V1(protocol_v1::ValidationProtocol::BitfieldDistribution(
+					protocol_v1::BitfieldDistributionMessage::Bitfield(relay_parent, conflicting),
+				)),
+			))
+			.await;
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for EquivocateBitfields
+where
+	Sender: overseer::BitfieldDistributionSenderTrait + Clone + Send + 'static,
+{
+	type Message = BitfieldDistributionMessage;
+
+	/// Track peers, and gossip a conflicting bitfield for the bitfields we equivocate on.
+	/// All messages are passed on to bitfield distribution unchanged.
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match &msg {
+			FromOrchestra::Communication {
+				msg: BitfieldDistributionMessage::NetworkBridgeUpdate(event),
+			} => match event {
+				NetworkBridgeEvent::PeerConnected(peer, ..) => {
+					self.peers.lock().expect("bad lock").insert(*peer);
+				},
+				NetworkBridgeEvent::PeerDisconnected(peer) => {
+					self.peers.lock().expect("bad lock").remove(peer);
+				},
+				_ => {},
+			},
+			FromOrchestra::Communication {
+				msg: BitfieldDistributionMessage::DistributeBitfield(relay_parent, bitfield),
+			} if self.should_equivocate() => {
+				self.equivocate(subsystem_sender, *relay_parent, bitfield).await;
+			},
+			_ => {},
+		}
+
+		Some(msg)
+	}
+}
+
+/// Generates an overseer that equivocates on availability bitfields.
+pub(crate) struct BitfieldEquivocation {
+	/// Percentage of bitfields to equivocate on.
+	percentage: u8,
+}
+
+impl BitfieldEquivocation {
+	pub fn new(opts: BitfieldEquivocationOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for BitfieldEquivocation {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_BITFIELD_EQUIVOCATION,
+			"😈 Started Malus node equivocating on {:?} percent of bitfields.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!(
+				"configure the bitfield equivocation percentage: {}",
+				e
+			))
+		})?;
+		let equivocate_bitfields = EquivocateBitfields::new(args.keystore.clone(), percentage);
+
+		prepared_overseer_builder(args)?
+			.replace_bitfield_distribution(move |bitfield_distribution| {
+				InterceptedSubsystem::new(
+					"bitfield-equivocation",
+					DEFAULT_SPAWN_PREFIX,
+					bitfield_distribution,
+					equivocate_bitfields,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+	use polkadot_node_subsystem_test_helpers::sender_receiver;
+	use polkadot_primitives::v2::{ValidatorId, ValidatorIndex};
+	use sp_keystore::SyncCryptoStorePtr;
+
+	fn context() -> SigningContext {
+		SigningContext { session_index: 1, parent_hash: Hash::repeat_byte(1) }
+	}
+
+	/// A keystore with a single validator key, along with our bitfield signed by it.
+	fn setup(bits: &[bool]) -> (Arc<LocalKeystore>, SignedAvailabilityBitfield, ValidatorId) {
+		let keystore = Arc::new(LocalKeystore::in_memory());
+		let public: ValidatorId =
+			SyncCryptoStore::sr25519_generate_new(&*keystore, PARACHAIN_KEY_TYPE_ID, None)
+				.unwrap()
+				.into();
+
+		let bitfield = block_on(SignedAvailabilityBitfield::sign(
+			&(keystore.clone() as SyncCryptoStorePtr),
+			AvailabilityBitfield(bits.iter().copied().collect()),
+			&context(),
+			ValidatorIndex(3),
+			&public,
+		))
+		.unwrap()
+		.unwrap();
+
+		(keystore, bitfield, public)
+	}
+
+	#[test]
+	fn conflicting_bitfield_differs_under_same_validator() {
+		let (keystore, bitfield, public) = setup(&[true, false, true]);
+
+		let conflicting = conflicting_bitfield(&keystore, &bitfield, &context()).unwrap();
+		assert_ne!(conflicting.unchecked_payload(), bitfield.payload());
+		assert_eq!(conflicting.unchecked_validator_index(), bitfield.validator_index());
+
+		// both are validly signed for the same relay parent.
+		let conflicting = conflicting.try_into_checked(&context(), &public).unwrap();
+		assert_eq!(conflicting.payload().0.len(), bitfield.payload().0.len());
+		assert!(bitfield.as_unchecked().check_signature(&context(), &public).is_ok());
+	}
+
+	#[test]
+	fn empty_or_foreign_bitfields_are_not_equivocated_on() {
+		let (keystore, bitfield, _) = setup(&[]);
+		assert!(conflicting_bitfield(&keystore, &bitfield, &context()).is_none());
+
+		// the key that signed the bitfield is not ours.
+		let (_, bitfield, _) = setup(&[true]);
+		let keystore = LocalKeystore::in_memory();
+		SyncCryptoStore::sr25519_generate_new(&keystore, PARACHAIN_KEY_TYPE_ID, None).unwrap();
+		assert!(conflicting_bitfield(&keystore, &bitfield, &context()).is_none());
+	}
+
+	#[test]
+	fn honest_when_not_sampled() {
+		let (keystore, bitfield, _) = setup(&[true]);
+		let interceptor = EquivocateBitfields::new(keystore, Percentage::new(0.0).unwrap());
+		let (mut sender, mut rx) = sender_receiver();
+
+		let msg = FromOrchestra::Communication {
+			msg: BitfieldDistributionMessage::DistributeBitfield(Hash::repeat_byte(1), bitfield),
+		};
+		assert!(block_on(interceptor.intercept_incoming(&mut sender, msg)).is_some());
+		assert!(rx.try_next().is_err());
+	}
+}
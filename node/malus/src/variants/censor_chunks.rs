@@ -0,0 +1,205 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that behaves honestly, except that it never serves availability
+//! chunks to a configured set of validators. Unlike `withhold-chunks`, which withholds
+//! the chunks of some candidates from everyone, this partitions the validator set:
+//! the censored validators have to fetch their chunks from other backers, while
+//! everybody else is served as usual.
+//!
+//! Validators fetch the chunk of their own index during availability distribution,
+//! so requests are filtered by the requested chunk index.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::SpawnGlue;
+use polkadot_primitives::v2::ValidatorIndex;
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_CENSOR_CHUNKS},
+};
+
+use std::{collections::HashSet, sync::Arc};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct CensorChunksOptions {
+	/// Comma separated indices of the validators that are never served chunks.
+	#[clap(long, use_value_delimiter = true, required = true)]
+	pub censor_indices: Vec<u32>,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Refuse to serve chunks to a set of validators.
+#[derive(Clone)]
+struct CensorChunks {
+	censored: Arc<HashSet<ValidatorIndex>>,
+}
+
+impl CensorChunks {
+	fn new(censor_indices: &[u32]) -> Self {
+		Self { censored: Arc::new(censor_indices.iter().copied().map(ValidatorIndex).collect()) }
+	}
+
+	fn is_censored(&self, validator_index: ValidatorIndex) -> bool {
+		self.censored.contains(&validator_index)
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for CensorChunks
+where
+	Sender: overseer::AvailabilityStoreSenderTrait + Clone + Send + 'static,
+{
+	type Message = AvailabilityStoreMessage;
+
+	/// Intercept chunk queries and answer them with "not available" for censored validators.
+	async fn intercept_incoming(
+		&self,
+		_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: AvailabilityStoreMessage::QueryChunk(candidate_hash, validator_index, tx),
+			} if self.is_censored(validator_index) => {
+				gum::debug!(
+					target: MALUS_CENSOR_CHUNKS,
+					?candidate_hash,
+					?validator_index,
+					"😈 Censoring chunk"
+				);
+				let _ = tx.send(None);
+				None
+			},
+			msg => Some(msg),
+		}
+	}
+}
+
+/// Generates an overseer that replaces the availability store subsystem with our
+/// malicious variant.
+pub(crate) struct CensorAvailabilityChunks {
+	/// Indices of the validators to censor.
+	censor_indices: Vec<u32>,
+}
+
+impl CensorAvailabilityChunks {
+	pub fn new(opts: CensorChunksOptions) -> Self {
+		Self { censor_indices: opts.censor_indices }
+	}
+}
+
+impl OverseerGen for CensorAvailabilityChunks {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_CENSOR_CHUNKS,
+			"😈 Started Malus node censoring chunks of validators {:?}.",
+			self.censor_indices,
+		);
+
+		let censor_chunks = CensorChunks::new(&self.censor_indices);
+
+		prepared_overseer_builder(args)?
+			.replace_availability_store(move |av_store| {
+				InterceptedSubsystem::new(
+					"censor-chunks",
+					DEFAULT_SPAWN_PREFIX,
+					av_store,
+					censor_chunks,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{channel::oneshot, executor::block_on};
+	use polkadot_node_primitives::ErasureChunk;
+	use polkadot_node_subsystem_test_helpers::sender_receiver;
+	use polkadot_primitives::v2::{CandidateHash, Hash};
+
+	fn query_chunk(
+		validator_index: ValidatorIndex,
+	) -> (FromOrchestra<AvailabilityStoreMessage>, oneshot::Receiver<Option<ErasureChunk>>) {
+		let (tx, rx) = oneshot::channel();
+		let msg = FromOrchestra::Communication {
+			msg: AvailabilityStoreMessage::QueryChunk(
+				CandidateHash(Hash::repeat_byte(1)),
+				validator_index,
+				tx,
+			),
+		};
+		(msg, rx)
+	}
+
+	#[test]
+	fn only_listed_indices_are_censored() {
+		let interceptor = CensorChunks::new(&[1, 3]);
+
+		for index in [1, 3] {
+			assert!(interceptor.is_censored(ValidatorIndex(index)));
+		}
+		for index in [0, 2, 4] {
+			assert!(!interceptor.is_censored(ValidatorIndex(index)));
+		}
+	}
+
+	#[test]
+	fn censored_requests_are_answered_with_no_chunk() {
+		let interceptor = CensorChunks::new(&[1]);
+		let (mut sender, _) = sender_receiver();
+
+		let (msg, mut rx) = query_chunk(ValidatorIndex(1));
+		assert!(block_on(interceptor.intercept_incoming(&mut sender, msg)).is_none());
+		assert_matches::assert_matches!(rx.try_recv(), Ok(Some(None)));
+
+		let (msg, _rx) = query_chunk(ValidatorIndex(0));
+		assert_matches::assert_matches!(
+			block_on(interceptor.intercept_incoming(&mut sender, msg)),
+			Some(FromOrchestra::Communication { msg: AvailabilityStoreMessage::QueryChunk(..) })
+		);
+	}
+}
@@ -18,21 +18,28 @@
 //! interceptor is implemented.
 use crate::{
 	interceptor::*,
-	shared::{MALICIOUS_POV, MALUS},
+	shared::{is_malicious_pov, BernoulliGate, MALICIOUS_POV, MALUS_FAKE_VALIDATION},
 };
 
 use polkadot_node_core_candidate_validation::find_validation_data;
-use polkadot_node_primitives::{InvalidCandidate, ValidationResult};
+use polkadot_node_primitives::{AvailableData, BlockData, InvalidCandidate, PoV, ValidationResult};
 use polkadot_node_subsystem::{
 	messages::{CandidateValidationMessage, ValidationFailed},
-	overseer,
+	overseer, OverseerSignal,
 };
+use polkadot_node_subsystem_util::request_validators;
 
 use polkadot_primitives::v2::{
-	CandidateCommitments, CandidateDescriptor, CandidateReceipt, PersistedValidationData,
+	BlockNumber, CandidateCommitments, CandidateDescriptor, CandidateReceipt, Hash, Id as ParaId,
+	OutboundHrmpMessage, PersistedValidationData, ValidationCode,
 };
 
 use futures::channel::oneshot;
+use rand::{
+	distributions::{Bernoulli, Distribution},
+	Rng,
+};
+use std::sync::{Arc, Mutex};
 
 #[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
 #[clap(rename_all = "kebab-case")]
@@ -106,68 +113,314 @@ impl Into<InvalidCandidate> for FakeCandidateValidationError {
 #[derive(Clone, Debug)]
 /// An interceptor which fakes validation result with a preconfigured result.
 /// Replaces `CandidateValidationSubsystem`.
-pub struct ReplaceValidationResult<Spawner> {
+pub struct ReplaceValidationResult {
 	fake_validation: FakeCandidateValidation,
 	fake_validation_error: FakeCandidateValidationError,
-	spawner: Spawner,
+	/// Decides which candidates get their validation faked, all of them if not set.
+	gate: Option<Arc<Mutex<BernoulliGate>>>,
+	/// Overrides of the commitments faked valid candidates commit to.
+	fake_commitments: FakeCommitments,
+	/// Whether candidates with an empty `PoV` are considered malicious as well.
+	fake_empty_pov: bool,
 }
 
-impl<Spawner> ReplaceValidationResult<Spawner>
-where
-	Spawner: overseer::gen::Spawner,
-{
+impl ReplaceValidationResult {
 	pub fn new(
 		fake_validation: FakeCandidateValidation,
 		fake_validation_error: FakeCandidateValidationError,
-		spawner: Spawner,
 	) -> Self {
-		Self { fake_validation, fake_validation_error, spawner }
+		Self {
+			fake_validation,
+			fake_validation_error,
+			gate: None,
+			fake_commitments: FakeCommitments::default(),
+			fake_empty_pov: false,
+		}
+	}
+
+	/// Only fake the validation of the given percentage of candidates, leaving the rest
+	/// to actual validation. The decision is taken once per candidate, so backing and
+	/// approval checks of a candidate are faked alike, and forgotten once the leaf active
+	/// when last checking the candidate is finalized.
+	pub fn with_percentage(self, percentage: Percentage, seed: Option<u64>) -> Self {
+		let gate = BernoulliGate::new(percentage, seed);
+		Self { gate: Some(Arc::new(Mutex::new(gate))), ..self }
+	}
+
+	/// Commit faked valid candidates to the given overrides, matching candidates crafted
+	/// with the same overrides.
+	pub fn with_fake_commitments(self, fake_commitments: FakeCommitments) -> Self {
+		Self { fake_commitments, ..self }
+	}
+
+	/// Also fake the validation of candidates with an empty `PoV`, as crafted by the
+	/// `empty-pov` variant.
+	pub fn with_empty_pov(self) -> Self {
+		Self { fake_empty_pov: true, ..self }
+	}
+
+	/// Whether the `PoV` is known to be malicious.
+	fn is_malicious(&self, pov: &PoV) -> bool {
+		is_malicious_pov(&pov.block_data.0) || (self.fake_empty_pov && pov.block_data.0.is_empty())
+	}
+
+	/// Whether the validation of the given candidate is faked.
+	fn should_fake(&self, candidate_receipt: &CandidateReceipt) -> bool {
+		self.gate.as_ref().map_or(true, |gate| {
+			gate.lock().expect("bad lock").should_act(candidate_receipt.hash())
+		})
 	}
 
 	/// Creates and sends the validation response for a given candidate. Queries the runtime to obtain the validation data for the
-	/// given candidate.
-	pub fn send_validation_response<Sender>(
+	/// given candidate. If the validation data can't be obtained, the response sender is handed back.
+	pub async fn send_validation_response<Sender>(
 		&self,
 		candidate_descriptor: CandidateDescriptor,
-		subsystem_sender: Sender,
+		subsystem_sender: &mut Sender,
 		response_sender: oneshot::Sender<Result<ValidationResult, ValidationFailed>>,
-	) where
+	) -> Result<(), oneshot::Sender<Result<ValidationResult, ValidationFailed>>>
+	where
 		Sender: overseer::CandidateValidationSenderTrait + Clone + Send + 'static,
 	{
-		let _candidate_descriptor = candidate_descriptor.clone();
-		let mut subsystem_sender = subsystem_sender.clone();
-		let (sender, receiver) = std::sync::mpsc::channel();
-		self.spawner.spawn_blocking(
-			"malus-get-validation-data",
-			Some("malus"),
-			Box::pin(async move {
-				match find_validation_data(&mut subsystem_sender, &_candidate_descriptor).await {
-					Ok(Some((validation_data, validation_code))) => {
-						sender
-							.send((validation_data, validation_code))
-							.expect("channel is still open");
-					},
-					_ => {
-						panic!("Unable to fetch validation data");
-					},
-				}
-			}),
-		);
-		let (validation_data, _) = receiver.recv().unwrap();
-		create_validation_response(validation_data, candidate_descriptor, response_sender);
+		match find_validation_data(subsystem_sender, &candidate_descriptor).await {
+			Ok(Some((validation_data, _))) => {
+				create_validation_response(
+					validation_data,
+					candidate_descriptor,
+					&self.fake_commitments,
+					response_sender,
+				);
+				Ok(())
+			},
+			_ => {
+				gum::warn!(
+					target: MALUS_FAKE_VALIDATION,
+					para_id = ?candidate_descriptor.para_id,
+					"Unable to fetch validation data",
+				);
+				Err(response_sender)
+			},
+		}
+	}
+}
+
+/// Parses a percentage in the range `0..=100`, for use as a `clap` value parser.
+pub fn parse_percentage(s: &str) -> Result<u8, String> {
+	let percentage: u8 = s.parse().map_err(|e| format!("{}", e))?;
+	if percentage > 100 {
+		return Err(format!("{} is not a percentage in the range 0..=100", percentage))
 	}
+	Ok(percentage)
 }
 
+/// A percentage in the range `0..=100`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Percentage(f64);
+
+impl Percentage {
+	/// Create a percentage, failing if `value` is not in the range `0..=100`.
+	pub fn new(value: f64) -> Result<Self, String> {
+		if !(0.0..=100.0).contains(&value) {
+			return Err(format!("{} is not a percentage in the range 0..=100", value))
+		}
+		Ok(Self(value))
+	}
+
+	/// The percentage as a value in the range `0..=100`.
+	pub fn get(self) -> f64 {
+		self.0
+	}
+
+	/// Sample an event that occurs with a chance of this percentage.
+	pub fn sample(self, rng: &mut impl Rng) -> bool {
+		Bernoulli::new(self.0 / 100.0)
+			.expect("Percentage is checked to be in range [0..=100] on construction; qed")
+			.sample(rng)
+	}
+}
+
+impl TryFrom<u8> for Percentage {
+	type Error = String;
+
+	fn try_from(value: u8) -> Result<Self, Self::Error> {
+		Self::new(f64::from(value))
+	}
+}
+
+/// Overrides of the commitments of fake candidates, which take no action otherwise.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FakeCommitments {
+	/// The number of processed downward messages, none if not provided.
+	pub processed_downward_messages: Option<u32>,
+	/// The outbound HRMP messages sent.
+	pub horizontal_messages: Vec<OutboundHrmpMessage<ParaId>>,
+	/// The number of blocks the HRMP watermark is set beyond the relay parent number,
+	/// the watermark is the relay parent number if not provided.
+	pub hrmp_watermark_offset: Option<BlockNumber>,
+	/// The new validation code of the parachain, none if not provided.
+	pub new_validation_code: Option<ValidationCode>,
+}
+
+/// Create commitments of a candidate that took no action, other than the given overrides.
 pub fn create_fake_candidate_commitments(
 	persisted_validation_data: &PersistedValidationData,
+	fake_commitments: &FakeCommitments,
 ) -> CandidateCommitments {
 	CandidateCommitments {
 		upward_messages: Vec::new(),
-		horizontal_messages: Vec::new(),
-		new_validation_code: None,
+		horizontal_messages: fake_commitments.horizontal_messages.clone(),
+		new_validation_code: fake_commitments.new_validation_code.clone(),
 		head_data: persisted_validation_data.parent_head.clone(),
-		processed_downward_messages: 0,
-		hrmp_watermark: persisted_validation_data.relay_parent_number,
+		processed_downward_messages: fake_commitments.processed_downward_messages.unwrap_or(0),
+		hrmp_watermark: persisted_validation_data
+			.relay_parent_number
+			.saturating_add(fake_commitments.hrmp_watermark_offset.unwrap_or(0)),
+	}
+}
+
+/// Craft a candidate of the same para to replace `candidate` with, along with its malicious
+/// `PoV`. The candidate commits to fake commitments with the given overrides, which its
+/// validation has to be faked to by `ReplaceValidationResult`. Returns `None` if the
+/// validation data can't be obtained or erasure coded.
+pub async fn craft_fake_candidate<Sender>(
+	sender: &mut Sender,
+	relay_parent: Hash,
+	candidate: &CandidateReceipt,
+	fake_commitments: &FakeCommitments,
+) -> Option<(CandidateReceipt, PoV)>
+where
+	Sender: overseer::CandidateBackingSenderTrait,
+{
+	craft_fake_candidate_with_pov(
+		sender,
+		relay_parent,
+		candidate,
+		fake_commitments,
+		BlockData(MALICIOUS_POV.to_vec()),
+	)
+	.await
+}
+
+/// Like `craft_fake_candidate`, but with a `PoV` of the given block data.
+pub async fn craft_fake_candidate_with_pov<Sender>(
+	sender: &mut Sender,
+	relay_parent: Hash,
+	candidate: &CandidateReceipt,
+	fake_commitments: &FakeCommitments,
+	block_data: BlockData,
+) -> Option<(CandidateReceipt, PoV)>
+where
+	Sender: overseer::CandidateBackingSenderTrait,
+{
+	let (validation_data, validation_code, n_validators) =
+		fetch_validation_data(sender, relay_parent, candidate.descriptor()).await?;
+
+	let pov = PoV { block_data };
+	let commitments = create_fake_candidate_commitments(&validation_data, fake_commitments);
+	let validation_data_hash = validation_data.hash();
+	let available_data = AvailableData { pov: Arc::new(pov.clone()), validation_data };
+
+	let erasure_root = match erasure::obtain_chunks_v1(n_validators, &available_data) {
+		Ok(chunks) => erasure::branches(chunks.as_ref()).root(),
+		Err(err) => {
+			gum::debug!(target: MALUS_FAKE_VALIDATION, ?n_validators, ?err, "Erasure coding failed");
+			return None
+		},
+	};
+
+	let fake_candidate = CandidateReceipt {
+		descriptor: sign_with_new_collator(CandidateDescriptor {
+			persisted_validation_data_hash: validation_data_hash,
+			pov_hash: pov.hash(),
+			erasure_root,
+			para_head: commitments.head_data.hash(),
+			validation_code_hash: validation_code.hash(),
+			..candidate.descriptor().clone()
+		}),
+		commitments_hash: commitments.hash(),
+	};
+
+	Some((fake_candidate, pov))
+}
+
+/// The payload a collator signs for the given descriptor.
+fn collator_signature_payload(descriptor: &CandidateDescriptor) -> [u8; 132] {
+	polkadot_primitives::v2::collator_signature_payload(
+		&descriptor.relay_parent,
+		&descriptor.para_id,
+		&descriptor.persisted_validation_data_hash,
+		&descriptor.pov_hash,
+		&descriptor.validation_code_hash,
+	)
+}
+
+/// Sign the descriptor with a freshly generated collator key, replacing its collator and
+/// signature, such that the signature matches the other fields of the descriptor.
+pub fn sign_with_new_collator(descriptor: CandidateDescriptor) -> CandidateDescriptor {
+	use polkadot_primitives::v2::CollatorPair;
+	use sp_core::crypto::Pair;
+
+	let collator_pair = CollatorPair::generate().0;
+	let signature_payload = collator_signature_payload(&descriptor);
+
+	CandidateDescriptor {
+		collator: collator_pair.public(),
+		signature: collator_pair.sign(&signature_payload),
+		..descriptor
+	}
+}
+
+/// Like [`sign_with_new_collator`], but with a collator key derived from `seed`.
+///
+/// Regular `sr25519` signatures are randomized, so the signing nonce is derived from the
+/// seed as well. The same seed and descriptor thus always yield the same signature, and
+/// the same candidate hash.
+pub fn sign_with_seeded_collator(
+	descriptor: CandidateDescriptor,
+	seed: u64,
+) -> CandidateDescriptor {
+	use polkadot_primitives::v2::{CollatorPair, CollatorSignature};
+	use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+	use sp_core::{crypto::Pair, sr25519};
+
+	let collator_pair = CollatorPair::from_seed(&sp_core::blake2_256(&seed.to_le_bytes()));
+	let signature_payload = collator_signature_payload(&descriptor);
+
+	let keypair: &sr25519::Pair = collator_pair.as_ref();
+	let keypair: &schnorrkel::Keypair = keypair.as_ref();
+	// The signing context of `sr25519::Pair::sign`.
+	let transcript = schnorrkel::signing_context(b"substrate").bytes(&signature_payload);
+	let signature =
+		keypair.sign(schnorrkel::context::attach_rng(transcript, ChaChaRng::seed_from_u64(seed)));
+
+	CandidateDescriptor {
+		collator: collator_pair.public(),
+		signature: CollatorSignature::from(sr25519::Signature::from_raw(signature.to_bytes())),
+		..descriptor
+	}
+}
+
+/// Fetch the number of validators at the relay parent along with the validation data for
+/// the candidate. Returns `None` if any of it can't be obtained.
+pub async fn fetch_validation_data<Sender>(
+	sender: &mut Sender,
+	relay_parent: Hash,
+	descriptor: &CandidateDescriptor,
+) -> Option<(PersistedValidationData, ValidationCode, usize)>
+where
+	Sender: overseer::CandidateBackingSenderTrait,
+{
+	gum::trace!(target: MALUS_FAKE_VALIDATION, "Requesting validators");
+	let n_validators = match request_validators(relay_parent, sender).await.await {
+		Ok(Ok(validators)) => validators.len(),
+		_ => return None,
+	};
+	gum::trace!(target: MALUS_FAKE_VALIDATION, "Validators {}", n_validators);
+
+	match find_validation_data(sender, descriptor).await {
+		Ok(Some((validation_data, validation_code))) =>
+			Some((validation_data, validation_code, n_validators)),
+		_ => None,
 	}
 }
 
@@ -175,9 +428,11 @@ pub fn create_fake_candidate_commitments(
 fn create_validation_response(
 	persisted_validation_data: PersistedValidationData,
 	descriptor: CandidateDescriptor,
+	fake_commitments: &FakeCommitments,
 	response_sender: oneshot::Sender<Result<ValidationResult, ValidationFailed>>,
 ) {
-	let commitments = create_fake_candidate_commitments(&persisted_validation_data);
+	let commitments =
+		create_fake_candidate_commitments(&persisted_validation_data, fake_commitments);
 
 	// Craft the new malicious candidate.
 	let candidate_receipt = CandidateReceipt { descriptor, commitments_hash: commitments.hash() };
@@ -185,7 +440,7 @@ fn create_validation_response(
 	let result = Ok(ValidationResult::Valid(commitments, persisted_validation_data));
 
 	gum::debug!(
-		target: MALUS,
+		target: MALUS_FAKE_VALIDATION,
 		para_id = ?candidate_receipt.descriptor.para_id,
 		candidate_hash = ?candidate_receipt.hash(),
 		"ValidationResult: {:?}",
@@ -195,15 +450,15 @@ fn create_validation_response(
 	response_sender.send(result).unwrap();
 }
 
-impl<Sender, Spawner> MessageInterceptor<Sender> for ReplaceValidationResult<Spawner>
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for ReplaceValidationResult
 where
 	Sender: overseer::CandidateValidationSenderTrait + Clone + Send + 'static,
-	Spawner: overseer::gen::Spawner + Clone + 'static,
 {
 	type Message = CandidateValidationMessage;
 
 	// Capture all candidate validation requests and depending on configuration fail them.
-	fn intercept_incoming(
+	async fn intercept_incoming(
 		&self,
 		subsystem_sender: &mut Sender,
 		msg: FromOrchestra<Self::Message>,
@@ -224,7 +479,7 @@ where
 					FakeCandidateValidation::ApprovalValid |
 					FakeCandidateValidation::BackingAndApprovalValid => {
 						// Behave normally if the `PoV` is not known to be malicious.
-						if pov.block_data.0.as_slice() != MALICIOUS_POV {
+						if !self.is_malicious(&pov) || !self.should_fake(&candidate_receipt) {
 							return Some(FromOrchestra::Communication {
 								msg: CandidateValidationMessage::ValidateFromExhaustive(
 									validation_data,
@@ -239,17 +494,20 @@ where
 						create_validation_response(
 							validation_data,
 							candidate_receipt.descriptor,
+							&self.fake_commitments,
 							sender,
 						);
 						None
 					},
 					FakeCandidateValidation::ApprovalInvalid |
-					FakeCandidateValidation::BackingAndApprovalInvalid => {
+					FakeCandidateValidation::BackingAndApprovalInvalid
+						if self.should_fake(&candidate_receipt) =>
+					{
 						let validation_result =
 							ValidationResult::Invalid(InvalidCandidate::InvalidOutputs);
 
 						gum::debug!(
-							target: MALUS,
+							target: MALUS_FAKE_VALIDATION,
 							para_id = ?candidate_receipt.descriptor.para_id,
 							"ValidateFromExhaustive result: {:?}",
 							&validation_result
@@ -283,7 +541,7 @@ where
 					FakeCandidateValidation::BackingValid |
 					FakeCandidateValidation::BackingAndApprovalValid => {
 						// Behave normally if the `PoV` is not known to be malicious.
-						if pov.block_data.0.as_slice() != MALICIOUS_POV {
+						if !self.is_malicious(&pov) || !self.should_fake(&candidate_receipt) {
 							return Some(FromOrchestra::Communication {
 								msg: CandidateValidationMessage::ValidateFromChainState(
 									candidate_receipt,
@@ -293,19 +551,34 @@ where
 								),
 							})
 						}
-						self.send_validation_response(
-							candidate_receipt.descriptor,
-							subsystem_sender.clone(),
-							response_sender,
-						);
-						None
+						match self
+							.send_validation_response(
+								candidate_receipt.descriptor.clone(),
+								subsystem_sender,
+								response_sender,
+							)
+							.await
+						{
+							Ok(()) => None,
+							// Fall back to actual validation.
+							Err(response_sender) => Some(FromOrchestra::Communication {
+								msg: CandidateValidationMessage::ValidateFromChainState(
+									candidate_receipt,
+									pov,
+									timeout,
+									response_sender,
+								),
+							}),
+						}
 					},
 					FakeCandidateValidation::BackingInvalid |
-					FakeCandidateValidation::BackingAndApprovalInvalid => {
+					FakeCandidateValidation::BackingAndApprovalInvalid
+						if self.should_fake(&candidate_receipt) =>
+					{
 						let validation_result =
 							ValidationResult::Invalid(self.fake_validation_error.clone().into());
 						gum::debug!(
-							target: MALUS,
+							target: MALUS_FAKE_VALIDATION,
 							para_id = ?candidate_receipt.descriptor.para_id,
 							"ValidateFromChainState result: {:?}",
 							&validation_result
@@ -329,6 +602,12 @@ where
 		}
 	}
 
+	fn observe_signal(&self, signal: &OverseerSignal) {
+		if let Some(gate) = &self.gate {
+			gate.lock().expect("bad lock").observe_signal(signal);
+		}
+	}
+
 	fn intercept_outgoing(
 		&self,
 		msg: overseer::CandidateValidationOutgoingMessages,
@@ -336,3 +615,123 @@ where
 		Some(msg)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::FutureExt;
+	use polkadot_node_subsystem_test_helpers::{sender_receiver, TestSubsystemSender};
+	use polkadot_primitives_test_helpers::dummy_candidate_receipt;
+	use std::time::Duration;
+
+	#[test]
+	fn percentage_bounds_are_inclusive() {
+		assert_eq!(Percentage::new(0.0).map(Percentage::get), Ok(0.0));
+		assert_eq!(Percentage::try_from(100u8).map(Percentage::get), Ok(100.0));
+
+		let mut rng = rand::thread_rng();
+		assert!((0..100).all(|_| !Percentage::new(0.0).unwrap().sample(&mut rng)));
+		assert!((0..100).all(|_| Percentage::new(100.0).unwrap().sample(&mut rng)));
+	}
+
+	#[test]
+	fn out_of_range_percentage_is_rejected() {
+		assert!(Percentage::new(150.0).is_err());
+		assert!(Percentage::try_from(150u8).is_err());
+		assert!(Percentage::new(-1.0).is_err());
+		assert!(Percentage::new(f64::NAN).is_err());
+	}
+
+	#[test]
+	fn percentage_of_validations_is_faked() {
+		let filter = ReplaceValidationResult::new(
+			FakeCandidateValidation::BackingAndApprovalInvalid,
+			FakeCandidateValidationError::InvalidOutputs,
+		)
+		.with_percentage(Percentage::new(50.0).unwrap(), Some(7));
+		let (mut sender, _rx) = sender_receiver();
+
+		let batch = 1000;
+		let faked = (0..batch)
+			.filter(|n| {
+				let mut candidate_receipt = dummy_candidate_receipt(Hash::repeat_byte(1));
+				candidate_receipt.descriptor.para_id = (*n).into();
+				let (tx, _response) = oneshot::channel();
+				let msg = CandidateValidationMessage::ValidateFromExhaustive(
+					PersistedValidationData::default(),
+					ValidationCode(vec![1, 2, 3]),
+					candidate_receipt,
+					Arc::new(PoV { block_data: BlockData(vec![4, 5, 6]) }),
+					Duration::from_secs(1),
+					tx,
+				);
+
+				// faked validations are answered right away instead of being forwarded.
+				filter
+					.intercept_incoming(&mut sender, FromOrchestra::Communication { msg })
+					.now_or_never()
+					.expect("faking does not block")
+					.is_none()
+			})
+			.count();
+
+		assert!((400..=600).contains(&faked), "{} of {} faked", faked, batch);
+	}
+
+	#[test]
+	fn fake_decisions_are_forgotten_once_finalized() {
+		let filter = ReplaceValidationResult::new(
+			FakeCandidateValidation::BackingAndApprovalInvalid,
+			FakeCandidateValidationError::InvalidOutputs,
+		)
+		.with_percentage(Percentage::new(50.0).unwrap(), None);
+		let candidates: Vec<_> =
+			(0..=255).map(|n| dummy_candidate_receipt(Hash::repeat_byte(n))).collect();
+		let decide = || candidates.iter().map(|c| filter.should_fake(c)).collect::<Vec<_>>();
+
+		let first = decide();
+		assert_eq!(decide(), first);
+
+		MessageInterceptor::<TestSubsystemSender>::observe_signal(
+			&filter,
+			&OverseerSignal::BlockFinalized(Hash::repeat_byte(0), 0),
+		);
+		// with 256 candidates at 50%, the fresh decisions differ from the forgotten ones.
+		assert_ne!(decide(), first);
+	}
+
+	#[test]
+	fn empty_pov_is_faked_only_if_configured() {
+		let filter = ReplaceValidationResult::new(
+			FakeCandidateValidation::BackingAndApprovalValid,
+			FakeCandidateValidationError::InvalidOutputs,
+		);
+		let (mut sender, _rx) = sender_receiver();
+
+		let mut is_faked = |filter: &ReplaceValidationResult, block_data: Vec<u8>| {
+			let (tx, _response) = oneshot::channel();
+			let msg = CandidateValidationMessage::ValidateFromExhaustive(
+				PersistedValidationData::default(),
+				ValidationCode(vec![1, 2, 3]),
+				dummy_candidate_receipt(Hash::repeat_byte(1)),
+				Arc::new(PoV { block_data: BlockData(block_data) }),
+				Duration::from_secs(1),
+				tx,
+			);
+
+			filter
+				.intercept_incoming(&mut sender, FromOrchestra::Communication { msg })
+				.now_or_never()
+				.expect("faking does not block")
+				.is_none()
+		};
+
+		assert!(is_faked(&filter, MALICIOUS_POV.to_vec()));
+		assert!(!is_faked(&filter, Vec::new()));
+
+		let filter = filter.with_empty_pov();
+		assert!(is_faked(&filter, MALICIOUS_POV.to_vec()));
+		assert!(is_faked(&filter, Vec::new()));
+		assert!(!is_faked(&filter, vec![4, 5, 6]));
+	}
+}
@@ -0,0 +1,291 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that holds back the statements it gossips for a configurable
+//! duration. Statements are still handled honestly, they just reach the network
+//! late, in the order they were issued.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::SpawnGlue;
+use sp_core::traits::SpawnNamed;
+
+use futures::{channel::mpsc, StreamExt};
+use futures_timer::Delay;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{spawn_name, SpawnOptions, MALUS_DELAY_STATEMENTS},
+};
+
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+/// Name of the task releasing held back statements, after the spawn prefix.
+const TASK: &str = "delay-statements";
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct DelayStatementsOptions {
+	/// Duration in milliseconds for which gossiped statements are held back.
+	#[clap(long, default_value_t = 1000)]
+	pub delay_ms: u64,
+
+	#[clap(flatten)]
+	pub spawn: SpawnOptions,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// A network message held back until the given instant.
+type Delayed = (Instant, NetworkBridgeTxMessage);
+
+/// Hold back outgoing statements of the statement distribution subsystem.
+#[derive(Clone)]
+struct DelayStatements<Spawner> {
+	delay: Duration,
+	spawner: Spawner,
+	spawn_prefix: &'static str,
+	queue: mpsc::UnboundedSender<Delayed>,
+	/// Receiving end of the queue, until it's handed to the release task.
+	pending: Arc<Mutex<Option<mpsc::UnboundedReceiver<Delayed>>>>,
+}
+
+impl<Spawner> DelayStatements<Spawner> {
+	fn new(delay: Duration, spawner: Spawner, spawn_prefix: &'static str) -> Self {
+		let (queue, pending) = mpsc::unbounded();
+		Self { delay, spawner, spawn_prefix, queue, pending: Arc::new(Mutex::new(Some(pending))) }
+	}
+}
+
+/// Send out the queued messages once they are due. The delay is the same for all messages,
+/// so releasing them in queue order preserves the order they were issued in.
+async fn release_delayed<Sender>(mut queue: mpsc::UnboundedReceiver<Delayed>, mut sender: Sender)
+where
+	Sender: overseer::StatementDistributionSenderTrait,
+{
+	while let Some((due, msg)) = queue.next().await {
+		let now = Instant::now();
+		if due > now {
+			Delay::new(due - now).await;
+		}
+		sender.send_message(msg).await;
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender, Spawner> MessageInterceptor<Sender> for DelayStatements<Spawner>
+where
+	Sender: overseer::StatementDistributionSenderTrait + Clone + Send + 'static,
+	Spawner: SpawnNamed + Clone + 'static,
+{
+	type Message = StatementDistributionMessage;
+
+	/// Launch the task releasing held back statements, once we got hold of a sender.
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		let pending = self.pending.lock().expect("bad lock").take();
+		if let Some(queue) = pending {
+			self.spawner.spawn(
+				spawn_name(self.spawn_prefix, TASK),
+				Some(self.spawn_prefix),
+				Box::pin(release_delayed(queue, subsystem_sender.clone())),
+			);
+		}
+		Some(msg)
+	}
+
+	fn intercept_outgoing(
+		&self,
+		msg: overseer::StatementDistributionOutgoingMessages,
+	) -> Option<overseer::StatementDistributionOutgoingMessages> {
+		match msg {
+			overseer::StatementDistributionOutgoingMessages::NetworkBridgeTxMessage(
+				msg @ NetworkBridgeTxMessage::SendValidationMessage(..),
+			) |
+			overseer::StatementDistributionOutgoingMessages::NetworkBridgeTxMessage(
+				msg @ NetworkBridgeTxMessage::SendValidationMessages(..),
+			) => {
+				gum::trace!(target: MALUS_DELAY_STATEMENTS, delay = ?self.delay, "😈 Holding back statements");
+				match self.queue.unbounded_send((Instant::now() + self.delay, msg)) {
+					Ok(()) => None,
+					// The release task is gone, don't swallow the statements.
+					Err(err) => Some(err.into_inner().1.into()),
+				}
+			},
+			msg => Some(msg),
+		}
+	}
+}
+
+/// Generates an overseer that delays the statements gossiped by the statement distribution
+/// subsystem.
+pub(crate) struct DelayStatementDistribution {
+	/// Duration to hold back statements for.
+	delay: Duration,
+	/// Prefix of the names of spawned tasks.
+	spawn_prefix: &'static str,
+}
+
+impl DelayStatementDistribution {
+	pub fn new(opts: DelayStatementsOptions) -> Self {
+		Self {
+			delay: Duration::from_millis(opts.delay_ms),
+			spawn_prefix: opts.spawn.static_spawn_prefix(),
+		}
+	}
+}
+
+impl OverseerGen for DelayStatementDistribution {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_DELAY_STATEMENTS,
+			"😈 Started Malus node delaying statements by {:?}.",
+			self.delay,
+		);
+
+		let delay_statements =
+			DelayStatements::new(self.delay, args.spawner.clone(), self.spawn_prefix);
+
+		prepared_overseer_builder(args)?
+			.replace_statement_distribution(move |statement_distribution| {
+				InterceptedSubsystem::new(
+					"delay-statements",
+					self.spawn_prefix,
+					statement_distribution,
+					delay_statements,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shared::DEFAULT_SPAWN_PREFIX;
+	use futures::executor::block_on;
+	use polkadot_node_network_protocol::{v1 as protocol_v1, Versioned};
+	use polkadot_node_subsystem_test_helpers::{sender_receiver, TestSubsystemSender};
+	use polkadot_primitives::v2::{CandidateHash, Hash, ValidatorIndex};
+	use sp_core::testing::TaskExecutor;
+
+	fn hold_back(
+		interceptor: &DelayStatements<TaskExecutor>,
+		msg: NetworkBridgeTxMessage,
+	) -> Option<overseer::StatementDistributionOutgoingMessages> {
+		MessageInterceptor::<TestSubsystemSender>::intercept_outgoing(interceptor, msg.into())
+	}
+
+	fn statement(relay_parent: Hash, n: u8) -> NetworkBridgeTxMessage {
+		let metadata = protocol_v1::StatementMetadata {
+			relay_parent,
+			candidate_hash: CandidateHash(Hash::repeat_byte(n)),
+			signed_by: ValidatorIndex(0),
+			signature: sp_core::sr25519::Signature::from_raw([0u8; 64]).into(),
+		};
+		NetworkBridgeTxMessage::SendValidationMessage(
+			Vec::new(),
+			Versioned::V1(protocol_v1::ValidationProtocol::StatementDistribution(
+				protocol_v1::StatementDistributionMessage::LargeStatement(metadata),
+			)),
+		)
+	}
+
+	fn candidate_of(msg: AllMessages) -> (Hash, CandidateHash) {
+		match msg {
+			AllMessages::NetworkBridgeTx(NetworkBridgeTxMessage::SendValidationMessage(
+				_,
+				Versioned::V1(protocol_v1::ValidationProtocol::StatementDistribution(
+					protocol_v1::StatementDistributionMessage::LargeStatement(metadata),
+				)),
+			)) => (metadata.relay_parent, metadata.candidate_hash),
+			msg => panic!("Unexpected message: {:?}", msg),
+		}
+	}
+
+	#[test]
+	fn statements_are_released_in_order_after_delay() {
+		let delay = Duration::from_millis(50);
+		let interceptor = DelayStatements::new(delay, TaskExecutor::new(), DEFAULT_SPAWN_PREFIX);
+		let queue = interceptor.pending.lock().unwrap().take().unwrap();
+
+		let (a, b) = (Hash::repeat_byte(1), Hash::repeat_byte(2));
+		let issued = [(a, 1), (b, 2), (a, 3), (b, 4)];
+
+		let start = Instant::now();
+		for (relay_parent, n) in issued {
+			assert!(hold_back(&interceptor, statement(relay_parent, n)).is_none());
+		}
+		drop(interceptor);
+
+		let (sender, rx) = sender_receiver();
+		block_on(release_delayed(queue, sender));
+		assert!(start.elapsed() >= delay);
+
+		let released: Vec<_> =
+			block_on(rx.collect::<Vec<_>>()).into_iter().map(candidate_of).collect();
+		let expected: Vec<_> = issued
+			.iter()
+			.map(|(relay_parent, n)| (*relay_parent, CandidateHash(Hash::repeat_byte(*n))))
+			.collect();
+		assert_eq!(released, expected);
+	}
+
+	#[test]
+	fn other_messages_pass_through() {
+		let interceptor = DelayStatements::new(
+			Duration::from_secs(60),
+			TaskExecutor::new(),
+			DEFAULT_SPAWN_PREFIX,
+		);
+
+		let report = NetworkBridgeTxMessage::ReportPeer(
+			polkadot_node_network_protocol::PeerId::random(),
+			polkadot_node_network_protocol::UnifiedReputationChange::BenefitMinor("test"),
+		);
+		assert!(hold_back(&interceptor, report).is_some());
+	}
+}
@@ -36,7 +36,7 @@ use sp_core::traits::SpawnNamed;
 
 // Filter wrapping related types.
 use super::common::{FakeCandidateValidation, FakeCandidateValidationError};
-use crate::{interceptor::*, variants::ReplaceValidationResult};
+use crate::{interceptor::*, shared::DEFAULT_SPAWN_PREFIX, variants::ReplaceValidationResult};
 
 use std::sync::Arc;
 
@@ -81,16 +81,19 @@ impl OverseerGen for DisputeValidCandidates {
 		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
 		Spawner: 'static + SpawnNamed + Clone + Unpin,
 	{
-		let spawner = args.spawner.clone();
 		let validation_filter = ReplaceValidationResult::new(
 			self.opts.fake_validation,
 			self.opts.fake_validation_error,
-			SpawnGlue(spawner.clone()),
 		);
 
 		prepared_overseer_builder(args)?
 			.replace_candidate_validation(move |cv_subsystem| {
-				InterceptedSubsystem::new(cv_subsystem, validation_filter)
+				InterceptedSubsystem::new(
+					"dispute-ancestor",
+					DEFAULT_SPAWN_PREFIX,
+					cv_subsystem,
+					validation_filter,
+				)
 			})
 			.build_with_connector(connector)
 			.map_err(|e| e.into())
@@ -0,0 +1,267 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that replaces the candidates it seconds by candidates with a
+//! zero-length `PoV`, and fakes their validation to pass. The available data still
+//! contains the validation data, so it erasure codes fine and the crafted candidates
+//! make it through availability, but honest validators executing the empty `PoV`
+//! consider them invalid.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_primitives::BlockData;
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{ReplacedSubsystem, ReplacementGuard, DEFAULT_SPAWN_PREFIX, MALUS_EMPTY_POV},
+	variants::{
+		craft_fake_candidate_with_pov, parse_percentage, FakeCandidateValidation,
+		FakeCandidateValidationError, FakeCommitments, Percentage, ReplaceValidationResult,
+	},
+};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct EmptyPovOptions {
+	/// Determines the percentage of seconded candidates that get replaced.
+	/// Defaults to replacing all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Replaces candidates to be seconded by candidates with an empty `PoV`.
+#[derive(Clone)]
+struct SecondEmptyPov {
+	percentage: Percentage,
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for SecondEmptyPov
+where
+	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+{
+	type Message = CandidateBackingMessage;
+
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			} => {
+				if !self.percentage.sample(&mut rand::thread_rng()) {
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				// Our own validation of the empty `PoV` is faked to pass.
+				let (malicious_candidate, malicious_pov) = match craft_fake_candidate_with_pov(
+					subsystem_sender,
+					relay_parent,
+					&candidate,
+					&FakeCommitments::default(),
+					BlockData(Vec::new()),
+				)
+				.await
+				{
+					Some(crafted) => crafted,
+					None => {
+						gum::warn!(
+							target: MALUS_EMPTY_POV,
+							candidate_hash = ?candidate.hash(),
+							?relay_parent,
+							"Unable to craft candidate, not replacing candidate"
+						);
+						return Some(FromOrchestra::Communication {
+							msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+						})
+					},
+				};
+
+				gum::info!(
+					target: MALUS_EMPTY_POV,
+					candidate_hash = ?candidate.hash(),
+					malicious_candidate_hash = ?malicious_candidate.hash(),
+					"😈 Replaced candidate by one with an empty PoV"
+				);
+
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(
+						relay_parent,
+						malicious_candidate,
+						malicious_pov,
+					),
+				})
+			},
+			other => Some(other),
+		}
+	}
+}
+
+/// Generates an overseer that seconds candidates with an empty `PoV`.
+pub(crate) struct EmptyPov {
+	/// Percentage of candidates to replace.
+	percentage: u8,
+}
+
+impl EmptyPov {
+	pub fn new(opts: EmptyPovOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for EmptyPov {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_EMPTY_POV,
+			"😈 Started Malus node seconding candidates with an empty PoV for {:?} percent of seconded candidates.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the empty PoV percentage: {}", e))
+		})?;
+		let second_empty_pov = SecondEmptyPov { percentage };
+		let validation_filter = ReplaceValidationResult::new(
+			FakeCandidateValidation::BackingAndApprovalValid,
+			FakeCandidateValidationError::InvalidOutputs,
+		)
+		.with_empty_pov();
+
+		ReplacementGuard::new(prepared_overseer_builder(args)?)
+			.replace(ReplacedSubsystem::CandidateBacking, |b| {
+				b.replace_candidate_backing(move |cb| {
+					InterceptedSubsystem::new(
+						"empty-pov",
+						DEFAULT_SPAWN_PREFIX,
+						cb,
+						second_empty_pov,
+					)
+				})
+			})?
+			.replace(ReplacedSubsystem::CandidateValidation, |b| {
+				b.replace_candidate_validation(move |cv| {
+					InterceptedSubsystem::new(
+						"empty-pov",
+						DEFAULT_SPAWN_PREFIX,
+						cv,
+						validation_filter,
+					)
+				})
+			})?
+			.into_inner()
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{with_mock_sender, MockRuntime};
+	use polkadot_node_primitives::{AvailableData, PoV};
+	use polkadot_primitives::v2::Hash;
+
+	#[test]
+	fn seconded_candidate_has_empty_pov_with_matching_erasure_root() {
+		let runtime = MockRuntime::new(4);
+		let n_validators = runtime.n_validators;
+		let validation_data = runtime.validation_data.clone().unwrap();
+		let relay_parent = Hash::repeat_byte(1);
+		let candidate = runtime.candidate(relay_parent);
+
+		let interceptor = SecondEmptyPov { percentage: Percentage::new(100.0).unwrap() };
+		let (seconded, pov) = with_mock_sender(runtime, |mut sender| async move {
+			let msg = FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(
+					relay_parent,
+					candidate,
+					PoV { block_data: BlockData(vec![1, 2, 3]) },
+				),
+			};
+			match interceptor.intercept_incoming(&mut sender, msg).await {
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(_, candidate, pov),
+				}) => (candidate, pov),
+				_ => panic!("candidate is not seconded"),
+			}
+		});
+
+		assert!(pov.block_data.0.is_empty());
+		assert_eq!(seconded.descriptor.pov_hash, pov.hash());
+
+		// The validation data keeps the available data non-empty, so it erasure codes
+		// and the chunks match the erasure root of the candidate.
+		let available_data = AvailableData { pov: Arc::new(pov), validation_data };
+		let chunks = erasure::obtain_chunks_v1(n_validators, &available_data).unwrap();
+		assert_eq!(erasure::branches(chunks.as_ref()).root(), seconded.descriptor.erasure_root);
+
+		// Recovery yields the empty `PoV`, which is detected as invalid on execution.
+		let recovered = erasure::reconstruct_v1(
+			n_validators,
+			chunks.iter().enumerate().map(|(i, chunk)| (chunk.as_slice(), i)),
+		)
+		.unwrap();
+		assert_eq!(recovered, available_data);
+		assert!(recovered.pov.block_data.0.is_empty());
+	}
+
+	#[test]
+	fn erasure_coding_empty_data_fails_cleanly() {
+		let available_data = AvailableData {
+			pov: Arc::new(PoV { block_data: BlockData(Vec::new()) }),
+			validation_data: Default::default(),
+		};
+
+		assert_eq!(erasure::obtain_chunks(4, &()), Err(erasure::Error::BadPayload));
+		assert_eq!(
+			erasure::obtain_chunks_v1(1, &available_data),
+			Err(erasure::Error::NotEnoughValidators)
+		);
+	}
+}
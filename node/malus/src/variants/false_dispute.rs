@@ -0,0 +1,262 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that raises disputes against candidates it is about to check
+//! in approval voting, regardless of their validity. For a configurable fraction
+//! of candidates, an invalid local statement is issued to the dispute coordinator
+//! as soon as availability recovery is requested. Approval checking itself proceeds
+//! honestly.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{CandidateHash, CandidateReceipt, SessionIndex};
+use sp_core::traits::SpawnNamed;
+
+use futures::{channel::mpsc, StreamExt};
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{spawn_name, SpawnOptions, MALUS_FALSE_DISPUTE},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::{
+	collections::HashSet,
+	sync::{Arc, Mutex},
+};
+
+/// Name of the task issuing false disputes, after the spawn prefix.
+const TASK: &str = "false-dispute";
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct FalseDisputeOptions {
+	/// Determines the percentage of checked candidates that get disputed.
+	/// Defaults to disputing all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub spawn: SpawnOptions,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// The local statement disputing the given candidate, as sent by approval voting for
+/// candidates found to be invalid.
+fn false_dispute(session: SessionIndex, candidate: CandidateReceipt) -> DisputeCoordinatorMessage {
+	DisputeCoordinatorMessage::IssueLocalStatement(session, candidate.hash(), candidate, false)
+}
+
+/// Forward the false disputes to the dispute coordinator.
+async fn issue_false_disputes<Sender>(
+	mut queue: mpsc::UnboundedReceiver<DisputeCoordinatorMessage>,
+	mut sender: Sender,
+) where
+	Sender: overseer::ApprovalVotingSenderTrait,
+{
+	while let Some(msg) = queue.next().await {
+		// Unbounded, as approval voting does for local statements, to not
+		// block on the dispute coordinator.
+		sender.send_unbounded_message(msg);
+	}
+}
+
+/// Dispute candidates approval voting is about to check.
+#[derive(Clone)]
+struct FalseDisputes<Spawner> {
+	percentage: Percentage,
+	/// Candidates a decision has been made for.
+	decided: Arc<Mutex<HashSet<CandidateHash>>>,
+	spawner: Spawner,
+	spawn_prefix: &'static str,
+	queue: mpsc::UnboundedSender<DisputeCoordinatorMessage>,
+	/// Receiving end of the queue, until it's handed to the dispute task.
+	pending: Arc<Mutex<Option<mpsc::UnboundedReceiver<DisputeCoordinatorMessage>>>>,
+}
+
+impl<Spawner> FalseDisputes<Spawner> {
+	fn new(percentage: Percentage, spawner: Spawner, spawn_prefix: &'static str) -> Self {
+		let (queue, pending) = mpsc::unbounded();
+		Self {
+			percentage,
+			decided: Arc::new(Mutex::new(HashSet::new())),
+			spawner,
+			spawn_prefix,
+			queue,
+			pending: Arc::new(Mutex::new(Some(pending))),
+		}
+	}
+
+	/// Decide once per candidate whether it is disputed.
+	fn should_dispute(&self, candidate_hash: CandidateHash) -> bool {
+		self.decided.lock().expect("bad lock").insert(candidate_hash) &&
+			self.percentage.sample(&mut rand::thread_rng())
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender, Spawner> MessageInterceptor<Sender> for FalseDisputes<Spawner>
+where
+	Sender: overseer::ApprovalVotingSenderTrait + Clone + Send + 'static,
+	Spawner: SpawnNamed + Clone + 'static,
+{
+	type Message = ApprovalVotingMessage;
+
+	/// Launch the task issuing false disputes, once we got hold of a sender.
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		let pending = self.pending.lock().expect("bad lock").take();
+		if let Some(queue) = pending {
+			self.spawner.spawn(
+				spawn_name(self.spawn_prefix, TASK),
+				Some(self.spawn_prefix),
+				Box::pin(issue_false_disputes(queue, subsystem_sender.clone())),
+			);
+		}
+		Some(msg)
+	}
+
+	fn intercept_outgoing(
+		&self,
+		msg: overseer::ApprovalVotingOutgoingMessages,
+	) -> Option<overseer::ApprovalVotingOutgoingMessages> {
+		if let overseer::ApprovalVotingOutgoingMessages::AvailabilityRecoveryMessage(
+			AvailabilityRecoveryMessage::RecoverAvailableData(candidate, session, _, _),
+		) = &msg
+		{
+			let candidate_hash = candidate.hash();
+			if self.should_dispute(candidate_hash) {
+				gum::info!(
+					target: MALUS_FALSE_DISPUTE,
+					?candidate_hash,
+					?session,
+					"😈 Disputing candidate regardless of its validity"
+				);
+				if self.queue.unbounded_send(false_dispute(*session, candidate.clone())).is_err() {
+					gum::warn!(target: MALUS_FALSE_DISPUTE, ?candidate_hash, "Dispute task is gone");
+				}
+			}
+		}
+		Some(msg)
+	}
+}
+
+/// Generates an overseer that raises disputes against the candidates it checks.
+pub(crate) struct FalseDispute {
+	/// Percentage of candidates to dispute.
+	percentage: u8,
+	/// Prefix of the names of spawned tasks.
+	spawn_prefix: &'static str,
+}
+
+impl FalseDispute {
+	pub fn new(opts: FalseDisputeOptions) -> Self {
+		Self { percentage: opts.percentage, spawn_prefix: opts.spawn.static_spawn_prefix() }
+	}
+}
+
+impl OverseerGen for FalseDispute {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_FALSE_DISPUTE,
+			"😈 Started Malus node disputing {:?} percent of checked candidates.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the dispute percentage: {}", e))
+		})?;
+		let false_disputes =
+			FalseDisputes::new(percentage, args.spawner.clone(), self.spawn_prefix);
+
+		prepared_overseer_builder(args)?
+			.replace_approval_voting(move |approval_voting| {
+				InterceptedSubsystem::new(
+					"false-dispute",
+					self.spawn_prefix,
+					approval_voting,
+					false_disputes,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shared::DEFAULT_SPAWN_PREFIX;
+	use polkadot_primitives::v2::Hash;
+	use polkadot_primitives_test_helpers::dummy_candidate_receipt;
+	use sp_core::testing::TaskExecutor;
+
+	#[test]
+	fn false_dispute_votes_invalid() {
+		let candidate = dummy_candidate_receipt(Hash::repeat_byte(1));
+		let candidate_hash = candidate.hash();
+
+		assert_matches::assert_matches!(
+			false_dispute(3, candidate.clone()),
+			DisputeCoordinatorMessage::IssueLocalStatement(3, hash, receipt, false) => {
+				assert_eq!(hash, candidate_hash);
+				assert_eq!(receipt, candidate);
+			}
+		);
+	}
+
+	#[test]
+	fn decision_is_made_once_per_candidate() {
+		let interceptor = FalseDisputes::new(
+			Percentage::new(100.0).unwrap(),
+			TaskExecutor::new(),
+			DEFAULT_SPAWN_PREFIX,
+		);
+		let candidate_hash = CandidateHash(Hash::repeat_byte(1));
+
+		assert!(interceptor.should_dispute(candidate_hash));
+		assert!(!interceptor.should_dispute(candidate_hash));
+		assert!(interceptor.should_dispute(CandidateHash(Hash::repeat_byte(2))));
+	}
+}
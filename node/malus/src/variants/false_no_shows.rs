@@ -0,0 +1,251 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that suppresses its own approval assignments for a configurable
+//! fraction of candidates. Approval voting on the node itself proceeds as usual, but
+//! other validators never learn of the assignments, so the coverage they would have
+//! provided is missing like for a no-show and further tranches have to be taken.
+//! Unlike dropping the messages of others, only the node's own participation is
+//! affected.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{CandidateIndex, Hash};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_FALSE_NO_SHOWS},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct FalseNoShowsOptions {
+	/// Determines the percentage of candidates our assignments are suppressed for.
+	/// Defaults to all candidates.
+	#[clap(long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub noshow_percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Suppresses the assignments approval voting distributes for a subset of candidates.
+#[derive(Clone)]
+struct SuppressAssignments {
+	percentage: Percentage,
+	/// Decisions already taken, by block and candidate index, such that the assignment
+	/// and approval of a candidate are either both suppressed or both distributed.
+	decisions: Arc<Mutex<HashMap<(Hash, CandidateIndex), bool>>>,
+}
+
+impl SuppressAssignments {
+	fn new(percentage: Percentage) -> Self {
+		Self { percentage, decisions: Arc::new(Mutex::new(HashMap::new())) }
+	}
+
+	fn should_suppress(&self, block_hash: Hash, candidate_index: CandidateIndex) -> bool {
+		*self
+			.decisions
+			.lock()
+			.expect("bad lock")
+			.entry((block_hash, candidate_index))
+			.or_insert_with(|| self.percentage.sample(&mut rand::thread_rng()))
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for SuppressAssignments
+where
+	Sender: overseer::ApprovalVotingSenderTrait + Clone + Send + 'static,
+{
+	type Message = ApprovalVotingMessage;
+
+	/// Approval voting has already imported our assignment, only its distribution is
+	/// suppressed. The approval of a suppressed candidate is dropped as well, as it is
+	/// not accepted without the assignment.
+	fn intercept_outgoing(
+		&self,
+		msg: overseer::ApprovalVotingOutgoingMessages,
+	) -> Option<overseer::ApprovalVotingOutgoingMessages> {
+		match msg {
+			overseer::ApprovalVotingOutgoingMessages::ApprovalDistributionMessage(
+				ApprovalDistributionMessage::DistributeAssignment(cert, candidate_index),
+			) if self.should_suppress(cert.block_hash, candidate_index) => {
+				gum::debug!(
+					target: MALUS_FALSE_NO_SHOWS,
+					block_hash = ?cert.block_hash,
+					candidate_index,
+					validator = ?cert.validator,
+					"😈 Suppressing assignment"
+				);
+				None
+			},
+			overseer::ApprovalVotingOutgoingMessages::ApprovalDistributionMessage(
+				ApprovalDistributionMessage::DistributeApproval(vote),
+			) if self.should_suppress(vote.block_hash, vote.candidate_index) => {
+				gum::debug!(
+					target: MALUS_FALSE_NO_SHOWS,
+					block_hash = ?vote.block_hash,
+					candidate_index = vote.candidate_index,
+					validator = ?vote.validator,
+					"😈 Suppressing approval of a suppressed assignment"
+				);
+				None
+			},
+			msg => Some(msg),
+		}
+	}
+}
+
+/// Generates an overseer that suppresses our approval assignments.
+pub(crate) struct FalseNoShows {
+	/// Percentage of candidates to suppress our assignments for.
+	noshow_percentage: u8,
+}
+
+impl FalseNoShows {
+	pub fn new(opts: FalseNoShowsOptions) -> Self {
+		Self { noshow_percentage: opts.noshow_percentage }
+	}
+}
+
+impl OverseerGen for FalseNoShows {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_FALSE_NO_SHOWS,
+			"😈 Started Malus node suppressing its assignments for {:?} percent of candidates.",
+			self.noshow_percentage,
+		);
+
+		let percentage = Percentage::try_from(self.noshow_percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the no-show percentage: {}", e))
+		})?;
+		let suppress_assignments = SuppressAssignments::new(percentage);
+
+		prepared_overseer_builder(args)?
+			.replace_approval_voting(move |av| {
+				InterceptedSubsystem::new(
+					"false-no-shows",
+					DEFAULT_SPAWN_PREFIX,
+					av,
+					suppress_assignments,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use polkadot_node_primitives::approval::IndirectSignedApprovalVote;
+	use polkadot_node_subsystem_test_helpers::TestSubsystemSender;
+	use polkadot_primitives::v2::{ValidatorIndex, ValidatorPair};
+	use sp_core::crypto::Pair;
+
+	fn suppress(percentage: f64) -> SuppressAssignments {
+		SuppressAssignments::new(Percentage::new(percentage).unwrap())
+	}
+
+	fn is_distributed(interceptor: &SuppressAssignments, candidate_index: CandidateIndex) -> bool {
+		let vote = IndirectSignedApprovalVote {
+			block_hash: Hash::repeat_byte(1),
+			candidate_index,
+			validator: ValidatorIndex(0),
+			signature: ValidatorPair::generate().0.sign(&[1, 2, 3]),
+		};
+		let msg = overseer::ApprovalVotingOutgoingMessages::ApprovalDistributionMessage(
+			ApprovalDistributionMessage::DistributeApproval(vote),
+		);
+		MessageInterceptor::<TestSubsystemSender>::intercept_outgoing(interceptor, msg).is_some()
+	}
+
+	#[test]
+	fn suppression_respects_percentage() {
+		let always = suppress(100.0);
+		let never = suppress(0.0);
+
+		for candidate_index in 0..10 {
+			assert!(always.should_suppress(Hash::repeat_byte(1), candidate_index));
+			assert!(!never.should_suppress(Hash::repeat_byte(1), candidate_index));
+		}
+	}
+
+	#[test]
+	fn decision_is_per_candidate() {
+		let interceptor = suppress(50.0);
+
+		let mut decisions = Vec::new();
+		for block in 0..4 {
+			for candidate_index in 0..16 {
+				let decision =
+					interceptor.should_suppress(Hash::repeat_byte(block), candidate_index);
+				for _ in 0..4 {
+					assert_eq!(
+						interceptor.should_suppress(Hash::repeat_byte(block), candidate_index),
+						decision
+					);
+				}
+				decisions.push(decision);
+			}
+		}
+		// with 64 draws at 50%, both outcomes occur.
+		assert!(decisions.contains(&true) && decisions.contains(&false));
+	}
+
+	#[test]
+	fn approvals_of_suppressed_candidates_are_dropped() {
+		let interceptor = suppress(50.0);
+
+		for candidate_index in 0..16 {
+			let suppressed = interceptor.should_suppress(Hash::repeat_byte(1), candidate_index);
+			assert_eq!(is_distributed(&interceptor, candidate_index), !suppressed);
+		}
+		assert!(!is_distributed(&suppress(100.0), 0));
+		assert!(is_distributed(&suppress(0.0), 0));
+	}
+}
@@ -0,0 +1,242 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that re-broadcasts every `Seconded` statement it gossips a
+//! configurable number of times. The statements are valid, just redundant, which
+//! stresses the duplicate handling of the receiving peers.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_network_protocol::{
+	v1 as protocol_v1, PeerId, Versioned, VersionedValidationProtocol,
+};
+use polkadot_node_primitives::Statement;
+use polkadot_node_subsystem::SpawnGlue;
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_FLOOD_STATEMENTS},
+};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct FloodStatementsOptions {
+	/// The number of times each gossiped `Seconded` statement is re-broadcast, on top
+	/// of the honest message.
+	#[clap(long, default_value_t = 4)]
+	pub flood_factor: usize,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// A validation protocol message, along with the peers it is sent to.
+type ValidationMessage = (Vec<PeerId>, VersionedValidationProtocol);
+
+/// Whether the message gossips a `Seconded` statement. Large statements are only ever
+/// `Seconded` ones.
+fn is_seconded(msg: &VersionedValidationProtocol) -> bool {
+	match msg {
+		Versioned::V1(protocol_v1::ValidationProtocol::StatementDistribution(
+			protocol_v1::StatementDistributionMessage::Statement(_, statement),
+		)) => matches!(statement.unchecked_payload(), Statement::Seconded(_)),
+		Versioned::V1(protocol_v1::ValidationProtocol::StatementDistribution(
+			protocol_v1::StatementDistributionMessage::LargeStatement(_),
+		)) => true,
+		_ => false,
+	}
+}
+
+/// The batch of messages with each `Seconded` statement followed by `flood_factor` copies
+/// of it, sent to the same peers. Other messages are kept as they are, in order.
+fn with_rebroadcasts(
+	messages: Vec<ValidationMessage>,
+	flood_factor: usize,
+) -> Vec<ValidationMessage> {
+	messages
+		.into_iter()
+		.flat_map(|(peers, msg)| {
+			let copies = if is_seconded(&msg) { flood_factor } else { 0 };
+			std::iter::repeat((peers, msg)).take(copies + 1)
+		})
+		.collect()
+}
+
+/// Re-broadcast the `Seconded` statements sent by the statement distribution subsystem.
+#[derive(Clone)]
+struct FloodSeconded {
+	flood_factor: usize,
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for FloodSeconded
+where
+	Sender: overseer::StatementDistributionSenderTrait + Clone + Send + 'static,
+{
+	type Message = StatementDistributionMessage;
+
+	fn intercept_outgoing(
+		&self,
+		msg: overseer::StatementDistributionOutgoingMessages,
+	) -> Option<overseer::StatementDistributionOutgoingMessages> {
+		let messages = match msg {
+			overseer::StatementDistributionOutgoingMessages::NetworkBridgeTxMessage(
+				NetworkBridgeTxMessage::SendValidationMessage(peers, msg),
+			) => vec![(peers, msg)],
+			overseer::StatementDistributionOutgoingMessages::NetworkBridgeTxMessage(
+				NetworkBridgeTxMessage::SendValidationMessages(messages),
+			) => messages,
+			msg => return Some(msg),
+		};
+
+		gum::trace!(
+			target: MALUS_FLOOD_STATEMENTS,
+			flood_factor = self.flood_factor,
+			"😈 Re-broadcasting seconded statements"
+		);
+		Some(
+			NetworkBridgeTxMessage::SendValidationMessages(with_rebroadcasts(
+				messages,
+				self.flood_factor,
+			))
+			.into(),
+		)
+	}
+}
+
+/// Generates an overseer that floods peers with redundant `Seconded` statements.
+pub(crate) struct FloodStatementDistribution {
+	/// The number of re-broadcasts of each `Seconded` statement.
+	flood_factor: usize,
+}
+
+impl FloodStatementDistribution {
+	pub fn new(opts: FloodStatementsOptions) -> Self {
+		Self { flood_factor: opts.flood_factor }
+	}
+}
+
+impl OverseerGen for FloodStatementDistribution {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_FLOOD_STATEMENTS,
+			"😈 Started Malus node re-broadcasting seconded statements {} times.",
+			self.flood_factor,
+		);
+
+		let flood_seconded = FloodSeconded { flood_factor: self.flood_factor };
+
+		prepared_overseer_builder(args)?
+			.replace_statement_distribution(move |statement_distribution| {
+				InterceptedSubsystem::new(
+					"flood-statements",
+					DEFAULT_SPAWN_PREFIX,
+					statement_distribution,
+					flood_seconded,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use polkadot_node_primitives::UncheckedSignedFullStatement;
+	use polkadot_primitives::v2::{CandidateHash, Hash, ValidatorIndex};
+	use polkadot_primitives_test_helpers::dummy_committed_candidate_receipt;
+
+	fn signature() -> polkadot_primitives::v2::ValidatorSignature {
+		sp_core::sr25519::Signature::from_raw([0u8; 64]).into()
+	}
+
+	fn statement(statement: Statement) -> VersionedValidationProtocol {
+		Versioned::V1(protocol_v1::ValidationProtocol::StatementDistribution(
+			protocol_v1::StatementDistributionMessage::Statement(
+				Hash::repeat_byte(1),
+				UncheckedSignedFullStatement::new(statement, ValidatorIndex(0), signature()),
+			),
+		))
+	}
+
+	fn seconded(n: u8) -> VersionedValidationProtocol {
+		statement(Statement::Seconded(dummy_committed_candidate_receipt(Hash::repeat_byte(n))))
+	}
+
+	fn valid(n: u8) -> VersionedValidationProtocol {
+		statement(Statement::Valid(CandidateHash(Hash::repeat_byte(n))))
+	}
+
+	fn flooded(
+		messages: Vec<VersionedValidationProtocol>,
+		flood_factor: usize,
+	) -> Vec<VersionedValidationProtocol> {
+		let peers = vec![PeerId::random()];
+		let messages = messages.into_iter().map(|msg| (peers.clone(), msg)).collect();
+		with_rebroadcasts(messages, flood_factor)
+			.into_iter()
+			.map(|(to, msg)| {
+				assert_eq!(to, peers);
+				msg
+			})
+			.collect()
+	}
+
+	#[test]
+	fn seconded_statements_are_rebroadcast_flood_factor_times() {
+		let flood_factor = 3;
+		let messages = flooded(vec![seconded(1), valid(1), seconded(2)], flood_factor);
+
+		let expected: Vec<_> = std::iter::repeat(seconded(1))
+			.take(flood_factor + 1)
+			.chain(std::iter::once(valid(1)))
+			.chain(std::iter::repeat(seconded(2)).take(flood_factor + 1))
+			.collect();
+		assert_eq!(messages, expected);
+	}
+
+	#[test]
+	fn nothing_is_rebroadcast_without_flood_factor() {
+		let messages = vec![seconded(1), valid(1), seconded(2)];
+		assert_eq!(flooded(messages.clone(), 0), messages);
+	}
+}
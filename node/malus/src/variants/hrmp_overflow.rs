@@ -0,0 +1,281 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that replaces the candidates it seconds by candidates sending more
+//! outbound HRMP messages over a single channel than it can hold, and fakes their
+//! validation to pass. This exercises the HRMP limit checks of candidate validation
+//! on honest nodes and of the inclusion pipeline. Depending on the configuration, the
+//! messages exceed the number of messages permitted per candidate, break the rule of
+//! at most one message per recipient, or exceed the capacity of the channel.
+//!
+//! Other backers of the crafted candidates need to run this variant with the same
+//! options for their faked commitments to match.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{Id as ParaId, OutboundHrmpMessage};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{ReplacedSubsystem, ReplacementGuard, DEFAULT_SPAWN_PREFIX, MALUS_HRMP_OVERFLOW},
+	variants::{
+		craft_fake_candidate, parse_percentage, FakeCandidateValidation,
+		FakeCandidateValidationError, FakeCommitments, Percentage, ReplaceValidationResult,
+	},
+};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct HrmpOverflowOptions {
+	/// Determines the percentage of seconded candidates that get replaced.
+	/// Defaults to replacing all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	/// The parachain receiving the messages, which identifies the channel to overflow.
+	#[clap(long)]
+	pub recipient: u32,
+
+	/// The number of messages sent over the channel. Should exceed the capacity of the
+	/// channel.
+	#[clap(long, default_value_t = 100)]
+	pub message_count: u32,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// `count` distinct outbound messages to `recipient`.
+fn overflowing_messages(recipient: ParaId, count: u32) -> Vec<OutboundHrmpMessage<ParaId>> {
+	(0..count)
+		.map(|n| OutboundHrmpMessage { recipient, data: n.to_le_bytes().to_vec() })
+		.collect()
+}
+
+/// Replaces candidates to be seconded by candidates overflowing an HRMP channel.
+#[derive(Clone)]
+struct OverflowHrmpChannel {
+	percentage: Percentage,
+	fake_commitments: FakeCommitments,
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for OverflowHrmpChannel
+where
+	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+{
+	type Message = CandidateBackingMessage;
+
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			} => {
+				if !self.percentage.sample(&mut rand::thread_rng()) {
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				// Our own validation of the `PoV` is faked to yield the overflowing messages.
+				let (malicious_candidate, malicious_pov) = match craft_fake_candidate(
+					subsystem_sender,
+					relay_parent,
+					&candidate,
+					&self.fake_commitments,
+				)
+				.await
+				{
+					Some(crafted) => crafted,
+					None => {
+						gum::warn!(
+							target: MALUS_HRMP_OVERFLOW,
+							candidate_hash = ?candidate.hash(),
+							?relay_parent,
+							"Unable to craft candidate, not replacing candidate"
+						);
+						return Some(FromOrchestra::Communication {
+							msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+						})
+					},
+				};
+
+				gum::info!(
+					target: MALUS_HRMP_OVERFLOW,
+					candidate_hash = ?candidate.hash(),
+					malicious_candidate_hash = ?malicious_candidate.hash(),
+					message_count = self.fake_commitments.horizontal_messages.len(),
+					"😈 Replaced candidate by one overflowing an HRMP channel"
+				);
+
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(
+						relay_parent,
+						malicious_candidate,
+						malicious_pov,
+					),
+				})
+			},
+			other => Some(other),
+		}
+	}
+}
+
+/// Generates an overseer that seconds candidates overflowing an HRMP channel.
+pub(crate) struct HrmpOverflow {
+	/// Percentage of candidates to replace.
+	percentage: u8,
+	/// The parachain receiving the messages.
+	recipient: ParaId,
+	/// The number of messages sent to the recipient.
+	message_count: u32,
+}
+
+impl HrmpOverflow {
+	pub fn new(opts: HrmpOverflowOptions) -> Self {
+		Self {
+			percentage: opts.percentage,
+			recipient: ParaId::from(opts.recipient),
+			message_count: opts.message_count,
+		}
+	}
+}
+
+impl OverseerGen for HrmpOverflow {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_HRMP_OVERFLOW,
+			"😈 Started Malus node sending {} HRMP messages to {:?} for {:?} percent of seconded candidates.",
+			self.message_count,
+			self.recipient,
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the HRMP overflow percentage: {}", e))
+		})?;
+		let fake_commitments = FakeCommitments {
+			horizontal_messages: overflowing_messages(self.recipient, self.message_count),
+			..Default::default()
+		};
+		let overflow =
+			OverflowHrmpChannel { percentage, fake_commitments: fake_commitments.clone() };
+		let validation_filter = ReplaceValidationResult::new(
+			FakeCandidateValidation::BackingAndApprovalValid,
+			FakeCandidateValidationError::InvalidOutputs,
+		)
+		.with_fake_commitments(fake_commitments);
+
+		ReplacementGuard::new(prepared_overseer_builder(args)?)
+			.replace(ReplacedSubsystem::CandidateBacking, |b| {
+				b.replace_candidate_backing(move |cb| {
+					InterceptedSubsystem::new("hrmp-overflow", DEFAULT_SPAWN_PREFIX, cb, overflow)
+				})
+			})?
+			.replace(ReplacedSubsystem::CandidateValidation, |b| {
+				b.replace_candidate_validation(move |cv| {
+					InterceptedSubsystem::new(
+						"hrmp-overflow",
+						DEFAULT_SPAWN_PREFIX,
+						cv,
+						validation_filter,
+					)
+				})
+			})?
+			.into_inner()
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		mock::{with_mock_sender, MockRuntime},
+		variants::create_fake_candidate_commitments,
+	};
+	use polkadot_node_primitives::{BlockData, PoV};
+	use polkadot_primitives::v2::Hash;
+
+	#[test]
+	fn commitments_include_overflowing_messages() {
+		let runtime = MockRuntime::new(4);
+		let validation_data = runtime.validation_data.clone().unwrap();
+		let relay_parent = Hash::repeat_byte(1);
+		let candidate = runtime.candidate(relay_parent);
+
+		let messages = overflowing_messages(ParaId::from(2000), 5);
+		assert_eq!(messages.len(), 5);
+		assert!(messages.iter().all(|m| m.recipient == ParaId::from(2000)));
+
+		let fake_commitments =
+			FakeCommitments { horizontal_messages: messages.clone(), ..Default::default() };
+		let commitments = create_fake_candidate_commitments(&validation_data, &fake_commitments);
+		assert_eq!(commitments.horizontal_messages, messages);
+
+		let interceptor =
+			OverflowHrmpChannel { percentage: Percentage::new(100.0).unwrap(), fake_commitments };
+		let (seconded, pov) = with_mock_sender(runtime, |mut sender| async move {
+			let msg = FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(
+					relay_parent,
+					candidate,
+					PoV { block_data: BlockData(vec![1, 2, 3]) },
+				),
+			};
+			match interceptor.intercept_incoming(&mut sender, msg).await {
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(_, candidate, pov),
+				}) => (candidate, pov),
+				_ => panic!("candidate is not seconded"),
+			}
+		});
+
+		assert_eq!(seconded.commitments_hash, commitments.hash());
+		assert_eq!(seconded.descriptor.pov_hash, pov.hash());
+	}
+}
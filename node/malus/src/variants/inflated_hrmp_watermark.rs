@@ -0,0 +1,294 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that replaces the candidates it seconds by candidates committing
+//! to an HRMP watermark beyond their relay parent number, and fakes their validation
+//! to pass. Unlike garbage candidates, the commitments are otherwise those of a
+//! candidate that took no action, such that only the watermark checks of candidate
+//! validation on honest nodes and of the inclusion pipeline reject them.
+//!
+//! Other backers of the crafted candidates need to run this variant with the same
+//! `--watermark-offset` for their faked commitments to match.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::BlockNumber;
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{
+		ReplacedSubsystem, ReplacementGuard, DEFAULT_SPAWN_PREFIX, MALUS_INFLATED_HRMP_WATERMARK,
+	},
+	variants::{
+		craft_fake_candidate, parse_percentage, FakeCandidateValidation,
+		FakeCandidateValidationError, FakeCommitments, Percentage, ReplaceValidationResult,
+	},
+};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct InflatedHrmpWatermarkOptions {
+	/// Determines the percentage of seconded candidates that get replaced.
+	/// Defaults to replacing all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	/// The number of blocks beyond the relay parent number the HRMP watermark of the
+	/// replacing candidates is set to.
+	#[clap(long, default_value_t = 1)]
+	pub watermark_offset: BlockNumber,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Replaces candidates to be seconded by candidates with an inflated HRMP watermark.
+#[derive(Clone)]
+struct InflateHrmpWatermark {
+	percentage: Percentage,
+	fake_commitments: FakeCommitments,
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for InflateHrmpWatermark
+where
+	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+{
+	type Message = CandidateBackingMessage;
+
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			} => {
+				if !self.percentage.sample(&mut rand::thread_rng()) {
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				// Our own validation of the `PoV` is faked to yield the inflated watermark.
+				let (malicious_candidate, malicious_pov) = match craft_fake_candidate(
+					subsystem_sender,
+					relay_parent,
+					&candidate,
+					&self.fake_commitments,
+				)
+				.await
+				{
+					Some(crafted) => crafted,
+					None => {
+						gum::warn!(
+							target: MALUS_INFLATED_HRMP_WATERMARK,
+							candidate_hash = ?candidate.hash(),
+							?relay_parent,
+							"Unable to craft candidate, not replacing candidate"
+						);
+						return Some(FromOrchestra::Communication {
+							msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+						})
+					},
+				};
+
+				gum::info!(
+					target: MALUS_INFLATED_HRMP_WATERMARK,
+					candidate_hash = ?candidate.hash(),
+					malicious_candidate_hash = ?malicious_candidate.hash(),
+					watermark_offset = ?self.fake_commitments.hrmp_watermark_offset,
+					"😈 Replaced candidate by one with an inflated HRMP watermark"
+				);
+
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(
+						relay_parent,
+						malicious_candidate,
+						malicious_pov,
+					),
+				})
+			},
+			other => Some(other),
+		}
+	}
+}
+
+/// Generates an overseer that seconds candidates with an inflated HRMP watermark.
+pub(crate) struct InflatedHrmpWatermark {
+	/// Percentage of candidates to replace.
+	percentage: u8,
+	/// The number of blocks the watermark is set beyond the relay parent number.
+	watermark_offset: BlockNumber,
+}
+
+impl InflatedHrmpWatermark {
+	pub fn new(opts: InflatedHrmpWatermarkOptions) -> Self {
+		Self { percentage: opts.percentage, watermark_offset: opts.watermark_offset }
+	}
+}
+
+impl OverseerGen for InflatedHrmpWatermark {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_INFLATED_HRMP_WATERMARK,
+			"😈 Started Malus node setting the HRMP watermark {} blocks ahead for {:?} percent of seconded candidates.",
+			self.watermark_offset,
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!(
+				"configure the inflated HRMP watermark percentage: {}",
+				e
+			))
+		})?;
+		let fake_commitments = FakeCommitments {
+			hrmp_watermark_offset: Some(self.watermark_offset),
+			..Default::default()
+		};
+		let inflate =
+			InflateHrmpWatermark { percentage, fake_commitments: fake_commitments.clone() };
+		let validation_filter = ReplaceValidationResult::new(
+			FakeCandidateValidation::BackingAndApprovalValid,
+			FakeCandidateValidationError::InvalidOutputs,
+		)
+		.with_fake_commitments(fake_commitments);
+
+		ReplacementGuard::new(prepared_overseer_builder(args)?)
+			.replace(ReplacedSubsystem::CandidateBacking, |b| {
+				b.replace_candidate_backing(move |cb| {
+					InterceptedSubsystem::new(
+						"inflated-hrmp-watermark",
+						DEFAULT_SPAWN_PREFIX,
+						cb,
+						inflate,
+					)
+				})
+			})?
+			.replace(ReplacedSubsystem::CandidateValidation, |b| {
+				b.replace_candidate_validation(move |cv| {
+					InterceptedSubsystem::new(
+						"inflated-hrmp-watermark",
+						DEFAULT_SPAWN_PREFIX,
+						cv,
+						validation_filter,
+					)
+				})
+			})?
+			.into_inner()
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		mock::{with_mock_sender, MockRuntime},
+		variants::create_fake_candidate_commitments,
+	};
+	use polkadot_node_primitives::{BlockData, PoV};
+	use polkadot_primitives::v2::{CandidateReceipt, Hash, PersistedValidationData};
+
+	fn inflated(watermark_offset: BlockNumber) -> FakeCommitments {
+		FakeCommitments { hrmp_watermark_offset: Some(watermark_offset), ..Default::default() }
+	}
+
+	/// Second a candidate of `runtime` through the interceptor, returning what is seconded.
+	fn second(interceptor: &InflateHrmpWatermark, runtime: MockRuntime) -> (CandidateReceipt, PoV) {
+		let relay_parent = Hash::repeat_byte(1);
+		let candidate = runtime.candidate(relay_parent);
+		let pov = PoV { block_data: BlockData(vec![1, 2, 3]) };
+
+		with_mock_sender(runtime, |mut sender| async move {
+			let msg = FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			};
+			match interceptor.intercept_incoming(&mut sender, msg).await {
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(_, candidate, pov),
+				}) => (candidate, pov),
+				_ => panic!("candidate is not seconded"),
+			}
+		})
+	}
+
+	#[test]
+	fn fake_commitments_take_watermark_offset() {
+		let validation_data =
+			PersistedValidationData { relay_parent_number: 10, ..Default::default() };
+
+		let plain = create_fake_candidate_commitments(&validation_data, &Default::default());
+		assert_eq!(plain.hrmp_watermark, 10);
+
+		let commitments = create_fake_candidate_commitments(&validation_data, &inflated(5));
+		assert_eq!(commitments.hrmp_watermark, 15);
+		assert_eq!(commitments.head_data, plain.head_data);
+		assert_eq!(commitments.processed_downward_messages, plain.processed_downward_messages);
+
+		let saturated =
+			create_fake_candidate_commitments(&validation_data, &inflated(BlockNumber::MAX));
+		assert_eq!(saturated.hrmp_watermark, BlockNumber::MAX);
+	}
+
+	#[test]
+	fn seconded_candidate_commits_to_inflated_watermark() {
+		let runtime = MockRuntime::new(10);
+		let validation_data = runtime.validation_data.clone().unwrap();
+		let original = runtime.candidate(Hash::repeat_byte(1));
+
+		let interceptor = InflateHrmpWatermark {
+			percentage: Percentage::new(100.0).unwrap(),
+			fake_commitments: inflated(3),
+		};
+		let (candidate, pov) = second(&interceptor, runtime);
+
+		let commitments = create_fake_candidate_commitments(&validation_data, &inflated(3));
+		assert_eq!(commitments.hrmp_watermark, validation_data.relay_parent_number + 3);
+		assert_eq!(candidate.commitments_hash, commitments.hash());
+		assert_ne!(candidate.hash(), original.hash());
+		assert_eq!(candidate.descriptor.pov_hash, pov.hash());
+	}
+}
@@ -0,0 +1,281 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that alternates between honest and malicious phases, each lasting
+//! a configurable number of activated leaves. During malicious phases it backs garbage
+//! candidates just like `suggest-garbage-candidate`, during honest phases it leaves the
+//! candidates it seconds alone. This is harder to spot than a node that is always
+//! malicious.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::service::{
+	AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+	OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+	ProvideRuntimeApi,
+};
+use polkadot_node_subsystem::{OverseerSignal, SpawnGlue};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::MALUS_INTERMITTENT,
+	variants::{BackGarbageCandidateWrapper, SuggestGarbageCandidateOptions},
+};
+
+use std::sync::{
+	atomic::{AtomicUsize, Ordering},
+	Arc,
+};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct IntermittentOptions {
+	/// The number of activated leaves each honest and malicious phase lasts. The node
+	/// starts out honest.
+	#[clap(long, parse(try_from_str = parse_period), default_value_t = 1)]
+	pub period: usize,
+
+	#[clap(flatten)]
+	pub garbage: SuggestGarbageCandidateOptions,
+}
+
+fn parse_period(s: &str) -> Result<usize, String> {
+	let period: usize = s.parse().map_err(|e| format!("{}", e))?;
+	if period == 0 {
+		return Err("a phase must last at least one leaf".into())
+	}
+	Ok(period)
+}
+
+/// Whether the phase after `leaves` activated leaves is a malicious one. The first
+/// `period` leaves are honest, the next `period` leaves malicious, and so on.
+fn is_malicious_phase(leaves: usize, period: usize) -> bool {
+	leaves.saturating_sub(1) / period % 2 == 1
+}
+
+/// Applies the wrapped interceptor to incoming messages during malicious phases only,
+/// passing them through unchanged during honest phases.
+///
+/// Signals are always observed by the wrapped interceptor, and outgoing messages always
+/// pass it, such that it keeps track of what it did during previous malicious phases.
+#[derive(Clone)]
+pub(crate) struct Intermittent<I> {
+	inner: I,
+	/// The number of leaves per phase, malicious throughout if not set.
+	period: Option<usize>,
+	/// The number of activated leaves observed so far.
+	leaves: Arc<AtomicUsize>,
+}
+
+impl<I> Intermittent<I> {
+	pub fn new(inner: I, period: Option<usize>) -> Self {
+		Self { inner, period, leaves: Arc::new(AtomicUsize::new(0)) }
+	}
+
+	fn is_malicious(&self) -> bool {
+		self.period
+			.map_or(true, |period| is_malicious_phase(self.leaves.load(Ordering::SeqCst), period))
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender, I> MessageInterceptor<Sender> for Intermittent<I>
+where
+	Sender: overseer::SubsystemSender<<I::Message as overseer::AssociateOutgoing>::OutgoingMessages>
+		+ Clone
+		+ Send
+		+ 'static,
+	I: MessageInterceptor<Sender>,
+{
+	type Message = I::Message;
+
+	async fn intercept_incoming(
+		&self,
+		sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		if self.is_malicious() {
+			self.inner.intercept_incoming(sender, msg).await
+		} else {
+			Some(msg)
+		}
+	}
+
+	fn observe_signal(&self, signal: &OverseerSignal) {
+		if let (OverseerSignal::ActiveLeaves(update), Some(period)) = (signal, self.period) {
+			if let Some(leaf) = &update.activated {
+				let leaves = self.leaves.fetch_add(1, Ordering::SeqCst) + 1;
+				let malicious = is_malicious_phase(leaves, period);
+				if malicious != is_malicious_phase(leaves - 1, period) {
+					gum::info!(
+						target: MALUS_INTERMITTENT,
+						leaf_hash = ?leaf.hash,
+						leaf_number = leaf.number,
+						"😈 Switching to the {} phase",
+						if malicious { "malicious" } else { "honest" },
+					);
+				}
+			}
+		}
+		self.inner.observe_signal(signal);
+	}
+
+	fn intercept_outgoing(
+		&self,
+		msg: <Self::Message as overseer::AssociateOutgoing>::OutgoingMessages,
+	) -> Option<<Self::Message as overseer::AssociateOutgoing>::OutgoingMessages> {
+		self.inner.intercept_outgoing(msg)
+	}
+}
+
+/// Generates an overseer that backs garbage candidates in intermittent phases.
+pub(crate) struct IntermittentGarbageCandidate {
+	/// The number of leaves per phase.
+	period: usize,
+	inner: BackGarbageCandidateWrapper,
+}
+
+impl IntermittentGarbageCandidate {
+	pub fn new(opts: IntermittentOptions) -> Self {
+		Self {
+			period: opts.period,
+			inner: BackGarbageCandidateWrapper::new(opts.garbage).with_period(opts.period),
+		}
+	}
+}
+
+impl OverseerGen for IntermittentGarbageCandidate {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_INTERMITTENT,
+			"😈 Started Malus node alternating between honest and malicious phases of {} leaves.",
+			self.period,
+		);
+
+		self.inner.generate(connector, args)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use polkadot_node_primitives::{BlockData, PoV};
+	use polkadot_node_subsystem::{jaeger, ActivatedLeaf, ActiveLeavesUpdate, LeafStatus};
+	use polkadot_node_subsystem_test_helpers::{sender_receiver, TestSubsystemSender};
+	use polkadot_primitives::v2::{BlockNumber, Hash};
+	use polkadot_primitives_test_helpers::dummy_candidate_receipt;
+
+	/// Drops all incoming messages, standing in for malicious behavior.
+	#[derive(Clone)]
+	struct DropAll;
+
+	#[async_trait::async_trait]
+	impl<Sender> MessageInterceptor<Sender> for DropAll
+	where
+		Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+	{
+		type Message = CandidateBackingMessage;
+
+		async fn intercept_incoming(
+			&self,
+			_sender: &mut Sender,
+			_msg: FromOrchestra<Self::Message>,
+		) -> Option<FromOrchestra<Self::Message>> {
+			None
+		}
+	}
+
+	fn activate(interceptor: &Intermittent<DropAll>, number: BlockNumber) {
+		let update = ActiveLeavesUpdate::start_work(ActivatedLeaf {
+			hash: Hash::repeat_byte(number as u8),
+			number,
+			status: LeafStatus::Fresh,
+			span: Arc::new(jaeger::Span::Disabled),
+		});
+		MessageInterceptor::<TestSubsystemSender>::observe_signal(
+			interceptor,
+			&OverseerSignal::ActiveLeaves(update),
+		);
+	}
+
+	fn is_intercepted(interceptor: &Intermittent<DropAll>) -> bool {
+		let (mut sender, _receiver) = sender_receiver();
+		let msg = FromOrchestra::Communication {
+			msg: CandidateBackingMessage::Second(
+				Hash::repeat_byte(1),
+				dummy_candidate_receipt(Hash::repeat_byte(1)),
+				PoV { block_data: BlockData(vec![1, 2, 3]) },
+			),
+		};
+		futures::executor::block_on(interceptor.intercept_incoming(&mut sender, msg)).is_none()
+	}
+
+	#[test]
+	fn phase_toggles_every_period_leaves() {
+		let interceptor = Intermittent::new(DropAll, Some(2));
+		assert!(!is_intercepted(&interceptor));
+
+		let mut phases = Vec::new();
+		for number in 1..=8 {
+			activate(&interceptor, number);
+			phases.push(is_intercepted(&interceptor));
+		}
+		assert_eq!(phases, vec![false, false, true, true, false, false, true, true]);
+	}
+
+	#[test]
+	fn deactivated_leaves_do_not_toggle_the_phase() {
+		let interceptor = Intermittent::new(DropAll, Some(1));
+		activate(&interceptor, 1);
+		activate(&interceptor, 2);
+		assert!(is_intercepted(&interceptor));
+
+		MessageInterceptor::<TestSubsystemSender>::observe_signal(
+			&interceptor,
+			&OverseerSignal::ActiveLeaves(ActiveLeavesUpdate::stop_work(Hash::repeat_byte(1))),
+		);
+		assert!(is_intercepted(&interceptor));
+	}
+
+	#[test]
+	fn always_malicious_without_period() {
+		let interceptor = Intermittent::new(DropAll, None);
+		for number in 1..=4 {
+			assert!(is_intercepted(&interceptor));
+			activate(&interceptor, number);
+		}
+	}
+
+	#[test]
+	fn zero_period_is_rejected() {
+		assert!(parse_period("0").is_err());
+		assert_eq!(parse_period("3"), Ok(3));
+	}
+}
@@ -0,0 +1,196 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A node that drops a configurable fraction of the messages and requests it sends
+//! to peers, simulating packet loss without external tooling. Peer reports and
+//! connection requests are not affected.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_LOSSY_NETWORK},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct LossyNetworkOptions {
+	/// Determines the percentage of outgoing network messages that get dropped.
+	/// Defaults to dropping all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Whether the message ends up on the wire, as opposed to changing the state of the
+/// network bridge or its peers.
+fn is_sent_to_peers(msg: &NetworkBridgeTxMessage) -> bool {
+	matches!(
+		msg,
+		NetworkBridgeTxMessage::SendValidationMessage(..) |
+			NetworkBridgeTxMessage::SendValidationMessages(..) |
+			NetworkBridgeTxMessage::SendCollationMessage(..) |
+			NetworkBridgeTxMessage::SendCollationMessages(..) |
+			NetworkBridgeTxMessage::SendRequests(..)
+	)
+}
+
+/// Drops messages to be sent to peers.
+#[derive(Clone)]
+struct DropMessages {
+	percentage: Percentage,
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for DropMessages
+where
+	Sender: overseer::NetworkBridgeTxSenderTrait + Clone + Send + 'static,
+{
+	type Message = NetworkBridgeTxMessage;
+
+	/// The network bridge does not send anything to other subsystems, the messages it is
+	/// asked to send to peers are incoming ones.
+	async fn intercept_incoming(
+		&self,
+		_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication { msg }
+				if is_sent_to_peers(&msg) && self.percentage.sample(&mut rand::thread_rng()) =>
+			{
+				gum::trace!(target: MALUS_LOSSY_NETWORK, ?msg, "😈 Dropping network message");
+				None
+			},
+			msg => Some(msg),
+		}
+	}
+}
+
+/// Generates an overseer that drops outgoing network messages.
+pub(crate) struct LossyNetwork {
+	percentage: u8,
+}
+
+impl LossyNetwork {
+	pub fn new(opts: LossyNetworkOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for LossyNetwork {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_LOSSY_NETWORK,
+			"😈 Started Malus node dropping {:?} percent of outgoing network messages.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the message loss percentage: {}", e))
+		})?;
+		let drop_messages = DropMessages { percentage };
+
+		prepared_overseer_builder(args)?
+			.replace_network_bridge_tx(move |nb| {
+				InterceptedSubsystem::new("lossy-network", DEFAULT_SPAWN_PREFIX, nb, drop_messages)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::FutureExt;
+	use polkadot_node_network_protocol::{IfDisconnected, PeerId, UnifiedReputationChange};
+	use polkadot_node_subsystem_test_helpers::sender_receiver;
+
+	fn sends() -> Vec<NetworkBridgeTxMessage> {
+		vec![
+			NetworkBridgeTxMessage::SendValidationMessages(Vec::new()),
+			NetworkBridgeTxMessage::SendCollationMessages(Vec::new()),
+			NetworkBridgeTxMessage::SendRequests(Vec::new(), IfDisconnected::ImmediateError),
+		]
+	}
+
+	fn report_peer() -> NetworkBridgeTxMessage {
+		NetworkBridgeTxMessage::ReportPeer(
+			PeerId::random(),
+			UnifiedReputationChange::CostMinor("test"),
+		)
+	}
+
+	fn passes(interceptor: &DropMessages, msg: NetworkBridgeTxMessage) -> bool {
+		let (mut sender, _rx) = sender_receiver();
+		interceptor
+			.intercept_incoming(&mut sender, FromOrchestra::Communication { msg })
+			.now_or_never()
+			.expect("interceptor does not block")
+			.is_some()
+	}
+
+	#[test]
+	fn full_loss_drops_every_message() {
+		let interceptor = DropMessages { percentage: Percentage::new(100.0).unwrap() };
+
+		for _ in 0..10 {
+			assert!(sends().into_iter().all(|msg| !passes(&interceptor, msg)));
+		}
+		assert!(passes(&interceptor, report_peer()));
+	}
+
+	#[test]
+	fn no_loss_passes_every_message() {
+		let interceptor = DropMessages { percentage: Percentage::new(0.0).unwrap() };
+
+		for _ in 0..10 {
+			assert!(sends().into_iter().all(|msg| passes(&interceptor, msg)));
+		}
+		assert!(passes(&interceptor, report_peer()));
+	}
+}
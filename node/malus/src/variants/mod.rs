@@ -16,14 +16,66 @@
 
 //! Collection of behavior variants.
 
+mod approval_equivocation;
 mod back_garbage_candidate;
+mod bad_approval_sig;
+mod bad_erasure_root;
+mod bad_parahead;
+mod bad_pvd_hash;
+mod bitfield_equivocation;
+mod censor_chunks;
 mod common;
+mod delay_statements;
 mod dispute_valid_candidates;
+mod empty_pov;
+mod false_dispute;
+mod false_no_shows;
+mod flood_statements;
+mod hrmp_overflow;
+mod inflated_dmp;
+mod inflated_hrmp_watermark;
+mod intermittent;
+mod lossy_network;
+mod non_group_statements;
+mod oversized_code;
+mod reorder_seconded;
+mod seconded_overshoot;
+mod slow_dispute_participation;
+mod stale_code;
+mod stale_relay_parent;
 mod suggest_garbage_candidate;
+mod withhold_chunks;
+mod withhold_pov;
 
 pub(crate) use self::{
+	approval_equivocation::{ApprovalEquivocation, ApprovalEquivocationOptions},
 	back_garbage_candidate::BackGarbageCandidate,
+	bad_approval_sig::{BadApprovalSig, BadApprovalSigOptions},
+	bad_erasure_root::{BadErasureRoot, BadErasureRootOptions},
+	bad_parahead::{BadParaHead, BadParaHeadOptions},
+	bad_pvd_hash::{BadPvdHash, BadPvdHashOptions},
+	bitfield_equivocation::{BitfieldEquivocation, BitfieldEquivocationOptions},
+	censor_chunks::{CensorAvailabilityChunks, CensorChunksOptions},
+	delay_statements::{DelayStatementDistribution, DelayStatementsOptions},
 	dispute_valid_candidates::{DisputeAncestorOptions, DisputeValidCandidates},
-	suggest_garbage_candidate::BackGarbageCandidateWrapper,
+	empty_pov::{EmptyPov, EmptyPovOptions},
+	false_dispute::{FalseDispute, FalseDisputeOptions},
+	false_no_shows::{FalseNoShows, FalseNoShowsOptions},
+	flood_statements::{FloodStatementDistribution, FloodStatementsOptions},
+	hrmp_overflow::{HrmpOverflow, HrmpOverflowOptions},
+	inflated_dmp::{InflatedDmp, InflatedDmpOptions},
+	inflated_hrmp_watermark::{InflatedHrmpWatermark, InflatedHrmpWatermarkOptions},
+	intermittent::{Intermittent, IntermittentGarbageCandidate, IntermittentOptions},
+	lossy_network::{LossyNetwork, LossyNetworkOptions},
+	non_group_statements::{NonGroupStatements, NonGroupStatementsOptions},
+	oversized_code::{OversizedCode, OversizedCodeOptions},
+	reorder_seconded::{ReorderSecondedCollations, ReorderSecondedOptions},
+	seconded_overshoot::{SecondedOvershoot, SecondedOvershootOptions},
+	slow_dispute_participation::{SlowDisputeParticipation, SlowDisputeParticipationOptions},
+	stale_code::{StaleCode, StaleCodeOptions},
+	stale_relay_parent::{StaleRelayParent, StaleRelayParentOptions},
+	suggest_garbage_candidate::{BackGarbageCandidateWrapper, SuggestGarbageCandidateOptions},
+	withhold_chunks::{WithholdAvailabilityChunks, WithholdChunksOptions},
+	withhold_pov::{WithholdPoVOptions, WithholdPoVs},
 };
 pub(crate) use common::*;
@@ -0,0 +1,374 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that, for every candidate seconded by a backing group, forges
+//! `Seconded` statements of validators outside of that group and gossips them to
+//! all connected peers. Statements attributed to a validator whose key is in our
+//! keystore carry a valid signature, the others are signed by a freshly generated
+//! key. Either way they must be rejected by honest nodes, which exercises the group
+//! membership checks of backing statements.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_network_protocol::{v1 as protocol_v1, PeerId, Versioned};
+use polkadot_node_primitives::{Statement, UncheckedSignedFullStatement};
+use polkadot_node_subsystem::SpawnGlue;
+use polkadot_node_subsystem_util::{
+	request_session_index_for_child, request_validator_groups, request_validators,
+};
+use polkadot_primitives::v2::{
+	CandidateHash, CommittedCandidateReceipt, CompactStatement, Hash, SigningContext, ValidatorId,
+	ValidatorIndex, ValidatorPair,
+};
+use sc_keystore::LocalKeystore;
+use sp_core::{crypto::Pair, traits::SpawnNamed};
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_NON_GROUP_STATEMENTS},
+};
+
+use std::{
+	collections::HashSet,
+	sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct NonGroupStatementsOptions {
+	/// The number of validators outside of the backing group a `Seconded` statement
+	/// is forged for, per seconded candidate.
+	#[clap(long, default_value_t = 1)]
+	pub forge_count: usize,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// `Seconded` statements of `candidate` attributed to up to `forge_count` validators
+/// outside of `group`. Validators whose key is in our keystore come first and sign
+/// with that key, the others sign with a freshly generated key.
+fn forged_statements(
+	keystore: &LocalKeystore,
+	candidate: &CommittedCandidateReceipt,
+	group: &[ValidatorIndex],
+	validators: &[ValidatorId],
+	context: &SigningContext,
+	forge_count: usize,
+) -> Vec<UncheckedSignedFullStatement> {
+	let (held, foreign): (Vec<_>, Vec<_>) = validators
+		.iter()
+		.enumerate()
+		.map(|(index, public)| (ValidatorIndex(index as u32), public))
+		.filter(|(index, _)| !group.contains(index))
+		.map(|(index, public)| (index, keystore.key_pair::<ValidatorPair>(public).ok().flatten()))
+		.partition(|(_, pair)| pair.is_some());
+
+	let payload = CompactStatement::Seconded(candidate.hash()).signing_payload(context);
+	held.into_iter()
+		.chain(foreign)
+		.take(forge_count)
+		.map(|(index, pair)| {
+			let pair = pair.unwrap_or_else(|| ValidatorPair::generate().0);
+			UncheckedSignedFullStatement::new(
+				Statement::Seconded(candidate.clone()),
+				index,
+				pair.sign(&payload[..]),
+			)
+		})
+		.collect()
+}
+
+/// Forges `Seconded` statements of non-group validators for every seconded candidate.
+#[derive(Clone)]
+struct ForgeNonGroupStatements {
+	/// Peers currently connected on the validation peer set.
+	peers: Arc<Mutex<HashSet<PeerId>>>,
+	/// The candidates statements were forged for already.
+	forged: Arc<Mutex<HashSet<CandidateHash>>>,
+	keystore: Arc<LocalKeystore>,
+	forge_count: usize,
+}
+
+impl ForgeNonGroupStatements {
+	fn new(keystore: Arc<LocalKeystore>, forge_count: usize) -> Self {
+		Self {
+			peers: Arc::new(Mutex::new(HashSet::new())),
+			forged: Arc::new(Mutex::new(HashSet::new())),
+			keystore,
+			forge_count,
+		}
+	}
+
+	/// Gossip forged statements for the candidate seconded by `statement` to all peers,
+	/// unless done so before.
+	async fn forge<Sender>(
+		&self,
+		sender: &mut Sender,
+		relay_parent: Hash,
+		statement: &UncheckedSignedFullStatement,
+	) where
+		Sender: overseer::StatementDistributionSenderTrait,
+	{
+		let candidate = match statement.unchecked_payload() {
+			Statement::Seconded(candidate) => candidate,
+			Statement::Valid(_) => return,
+		};
+		if !self.forged.lock().expect("bad lock").insert(candidate.hash()) {
+			return
+		}
+
+		let (session_index, validators, groups) = match (
+			request_session_index_for_child(relay_parent, sender).await.await,
+			request_validators(relay_parent, sender).await.await,
+			request_validator_groups(relay_parent, sender).await.await,
+		) {
+			(Ok(Ok(session_index)), Ok(Ok(validators)), Ok(Ok((groups, _)))) =>
+				(session_index, validators, groups),
+			_ => {
+				gum::warn!(
+					target: MALUS_NON_GROUP_STATEMENTS,
+					?relay_parent,
+					"Unable to fetch the validator groups, not forging statements"
+				);
+				return
+			},
+		};
+
+		let originator = statement.unchecked_validator_index();
+		let group = match groups.iter().find(|group| group.contains(&originator)) {
+			Some(group) => group,
+			None => {
+				gum::debug!(
+					target: MALUS_NON_GROUP_STATEMENTS,
+					?relay_parent,
+					?originator,
+					"Originator is in no backing group, not forging statements"
+				);
+				return
+			},
+		};
+
+		let context = SigningContext { session_index, parent_hash: relay_parent };
+		let statements = forged_statements(
+			&self.keystore,
+			candidate,
+			group,
+			&validators,
+			&context,
+			self.forge_count,
+		);
+		let peers: Vec<_> = self.peers.lock().expect("bad lock").iter().cloned().collect();
+
+		gum::debug!(
+			target: MALUS_NON_GROUP_STATEMENTS,
+			?relay_parent,
+			candidate_hash = ?candidate.hash(),
+			n_statements = statements.len(),
+			n_peers = peers.len(),
+			"😈 Forging statements of validators outside of the backing group"
+		);
+
+		if peers.is_empty() {
+			return
+		}
+
+		for statement in statements {
+			sender
+				.send_message(NetworkBridgeTxMessage::SendValidationMessage(
+					peers.clone(),
+					Versioned::V1(protocol_v1::ValidationProtocol::StatementDistribution(
+						protocol_v1::StatementDistributionMessage::Statement(
+							relay_parent,
+							statement,
+						),
+					)),
+				))
+				.await;
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for ForgeNonGroupStatements
+where
+	Sender: overseer::StatementDistributionSenderTrait + Clone + Send + 'static,
+{
+	type Message = StatementDistributionMessage;
+
+	/// Track peers, and forge statements for the candidates seconded by us or our peers.
+	/// All messages are passed on to statement distribution unchanged.
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match &msg {
+			FromOrchestra::Communication {
+				msg: StatementDistributionMessage::NetworkBridgeUpdate(event),
+			} => match event {
+				NetworkBridgeEvent::PeerConnected(peer, ..) => {
+					self.peers.lock().expect("bad lock").insert(*peer);
+				},
+				NetworkBridgeEvent::PeerDisconnected(peer) => {
+					self.peers.lock().expect("bad lock").remove(peer);
+				},
+				NetworkBridgeEvent::PeerMessage(
+					_,
+					Versioned::V1(protocol_v1::StatementDistributionMessage::Statement(
+						relay_parent,
+						statement,
+					)),
+				) => {
+					self.forge(subsystem_sender, *relay_parent, statement).await;
+				},
+				_ => {},
+			},
+			FromOrchestra::Communication {
+				msg: StatementDistributionMessage::Share(relay_parent, statement),
+			} => {
+				self.forge(subsystem_sender, *relay_parent, statement.as_unchecked()).await;
+			},
+			_ => {},
+		}
+
+		Some(msg)
+	}
+}
+
+/// Generates an overseer that forges `Seconded` statements of non-group validators.
+pub(crate) struct NonGroupStatements {
+	/// The number of statements forged per seconded candidate.
+	forge_count: usize,
+}
+
+impl NonGroupStatements {
+	pub fn new(opts: NonGroupStatementsOptions) -> Self {
+		Self { forge_count: opts.forge_count }
+	}
+}
+
+impl OverseerGen for NonGroupStatements {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_NON_GROUP_STATEMENTS,
+			"😈 Started Malus node forging {} statements of non-group validators per seconded candidate.",
+			self.forge_count,
+		);
+
+		let forge_statements =
+			ForgeNonGroupStatements::new(args.keystore.clone(), self.forge_count);
+
+		prepared_overseer_builder(args)?
+			.replace_statement_distribution(move |statement_distribution| {
+				InterceptedSubsystem::new(
+					"non-group-statements",
+					DEFAULT_SPAWN_PREFIX,
+					statement_distribution,
+					forge_statements,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use polkadot_primitives::v2::PARACHAIN_KEY_TYPE_ID;
+	use polkadot_primitives_test_helpers::dummy_committed_candidate_receipt;
+	use sp_keystore::SyncCryptoStore;
+
+	fn context() -> SigningContext {
+		SigningContext { session_index: 1, parent_hash: Hash::repeat_byte(1) }
+	}
+
+	/// A keystore holding the key of validator 1 out of 4 validators.
+	fn setup() -> (LocalKeystore, Vec<ValidatorId>) {
+		let keystore = LocalKeystore::in_memory();
+		let ours: ValidatorId =
+			SyncCryptoStore::sr25519_generate_new(&keystore, PARACHAIN_KEY_TYPE_ID, None)
+				.unwrap()
+				.into();
+		let validators = (0..4)
+			.map(|n| if n == 1 { ours.clone() } else { ValidatorPair::generate().0.public() })
+			.collect();
+
+		(keystore, validators)
+	}
+
+	#[test]
+	fn forged_statements_are_signed_outside_of_group() {
+		let (keystore, validators) = setup();
+		let candidate = dummy_committed_candidate_receipt(Hash::repeat_byte(1));
+		let group = [ValidatorIndex(0), ValidatorIndex(2)];
+
+		let statements =
+			forged_statements(&keystore, &candidate, &group, &validators, &context(), 2);
+		assert_eq!(statements.len(), 2);
+
+		for statement in &statements {
+			let signer = statement.unchecked_validator_index();
+			assert!(!group.contains(&signer), "{:?} is in the group", signer);
+			assert_eq!(statement.unchecked_payload().candidate_hash(), candidate.hash());
+		}
+
+		// the validator whose key we hold comes first and signs validly, the other
+		// outsider's signature doesn't verify.
+		assert_eq!(statements[0].unchecked_validator_index(), ValidatorIndex(1));
+		assert!(statements[0].clone().try_into_checked(&context(), &validators[1]).is_ok());
+		assert_eq!(statements[1].unchecked_validator_index(), ValidatorIndex(3));
+		assert!(statements[1].clone().try_into_checked(&context(), &validators[3]).is_err());
+	}
+
+	#[test]
+	fn forge_count_is_capped_by_outsiders() {
+		let (keystore, validators) = setup();
+		let candidate = dummy_committed_candidate_receipt(Hash::repeat_byte(1));
+
+		let group = [ValidatorIndex(0), ValidatorIndex(2)];
+		let statements =
+			forged_statements(&keystore, &candidate, &group, &validators, &context(), 10);
+		assert_eq!(statements.len(), 2);
+
+		let group: Vec<_> = (0..4).map(ValidatorIndex).collect();
+		assert!(forged_statements(&keystore, &candidate, &group, &validators, &context(), 10)
+			.is_empty());
+	}
+}
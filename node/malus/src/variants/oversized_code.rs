@@ -0,0 +1,298 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that replaces the candidates it seconds by candidates upgrading
+//! the validation code of their parachain to a blob larger than the configured
+//! `max_code_size`, and fakes their validation to pass. Honest validators reject
+//! the candidates for the size of the new code, which exercises the code size checks
+//! of candidate validation and of the inclusion pipeline.
+//!
+//! Other backers of the crafted candidates need to run this variant with the same
+//! `--code-size` for their faked commitments to match.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{ValidationCode, MAX_CODE_SIZE};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{ReplacedSubsystem, ReplacementGuard, DEFAULT_SPAWN_PREFIX, MALUS_OVERSIZED_CODE},
+	variants::{
+		craft_fake_candidate, parse_percentage, FakeCandidateValidation,
+		FakeCandidateValidationError, FakeCommitments, Percentage, ReplaceValidationResult,
+	},
+};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct OversizedCodeOptions {
+	/// Determines the percentage of seconded candidates that get replaced.
+	/// Defaults to replacing all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	/// The size in bytes of the new validation code of the replacing candidates. Defaults
+	/// to exceeding the hard limit any `max_code_size` configuration is bound by.
+	#[clap(long, default_value_t = MAX_CODE_SIZE + 1)]
+	pub code_size: u32,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// A validation code blob of the given size.
+fn oversized_validation_code(code_size: u32) -> ValidationCode {
+	ValidationCode(vec![0u8; code_size as usize])
+}
+
+/// Replaces candidates to be seconded by candidates with oversized new validation code.
+#[derive(Clone)]
+struct AttachOversizedCode {
+	percentage: Percentage,
+	fake_commitments: FakeCommitments,
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for AttachOversizedCode
+where
+	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+{
+	type Message = CandidateBackingMessage;
+
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			} => {
+				if !self.percentage.sample(&mut rand::thread_rng()) {
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				// Our own validation of the `PoV` is faked to yield the oversized code.
+				let (malicious_candidate, malicious_pov) = match craft_fake_candidate(
+					subsystem_sender,
+					relay_parent,
+					&candidate,
+					&self.fake_commitments,
+				)
+				.await
+				{
+					Some(crafted) => crafted,
+					None => {
+						gum::warn!(
+							target: MALUS_OVERSIZED_CODE,
+							candidate_hash = ?candidate.hash(),
+							?relay_parent,
+							"Unable to craft candidate, not replacing candidate"
+						);
+						return Some(FromOrchestra::Communication {
+							msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+						})
+					},
+				};
+
+				let code_size =
+					self.fake_commitments.new_validation_code.as_ref().map(|code| code.0.len());
+				gum::info!(
+					target: MALUS_OVERSIZED_CODE,
+					candidate_hash = ?candidate.hash(),
+					malicious_candidate_hash = ?malicious_candidate.hash(),
+					?code_size,
+					"😈 Replaced candidate by one with oversized validation code"
+				);
+
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(
+						relay_parent,
+						malicious_candidate,
+						malicious_pov,
+					),
+				})
+			},
+			other => Some(other),
+		}
+	}
+}
+
+/// Generates an overseer that seconds candidates with oversized new validation code.
+pub(crate) struct OversizedCode {
+	/// Percentage of candidates to replace.
+	percentage: u8,
+	/// The size of the new validation code of the candidates.
+	code_size: u32,
+}
+
+impl OversizedCode {
+	pub fn new(opts: OversizedCodeOptions) -> Self {
+		Self { percentage: opts.percentage, code_size: opts.code_size }
+	}
+}
+
+impl OverseerGen for OversizedCode {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_OVERSIZED_CODE,
+			"😈 Started Malus node upgrading to {} bytes of validation code for {:?} percent of seconded candidates.",
+			self.code_size,
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the oversized code percentage: {}", e))
+		})?;
+		let fake_commitments = FakeCommitments {
+			new_validation_code: Some(oversized_validation_code(self.code_size)),
+			..Default::default()
+		};
+		let inflate =
+			AttachOversizedCode { percentage, fake_commitments: fake_commitments.clone() };
+		let validation_filter = ReplaceValidationResult::new(
+			FakeCandidateValidation::BackingAndApprovalValid,
+			FakeCandidateValidationError::InvalidOutputs,
+		)
+		.with_fake_commitments(fake_commitments);
+
+		ReplacementGuard::new(prepared_overseer_builder(args)?)
+			.replace(ReplacedSubsystem::CandidateBacking, |b| {
+				b.replace_candidate_backing(move |cb| {
+					InterceptedSubsystem::new("oversized-code", DEFAULT_SPAWN_PREFIX, cb, inflate)
+				})
+			})?
+			.replace(ReplacedSubsystem::CandidateValidation, |b| {
+				b.replace_candidate_validation(move |cv| {
+					InterceptedSubsystem::new(
+						"oversized-code",
+						DEFAULT_SPAWN_PREFIX,
+						cv,
+						validation_filter,
+					)
+				})
+			})?
+			.into_inner()
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		mock::{with_mock_sender, MockRuntime},
+		variants::create_fake_candidate_commitments,
+	};
+	use clap::Parser;
+	use polkadot_node_primitives::{BlockData, PoV};
+	use polkadot_primitives::v2::{CandidateReceipt, Hash, PersistedValidationData};
+
+	fn oversized(code_size: u32) -> FakeCommitments {
+		FakeCommitments {
+			new_validation_code: Some(oversized_validation_code(code_size)),
+			..Default::default()
+		}
+	}
+
+	/// Second a candidate of `runtime` through the interceptor, returning what is seconded.
+	fn second(interceptor: &AttachOversizedCode, runtime: MockRuntime) -> (CandidateReceipt, PoV) {
+		let relay_parent = Hash::repeat_byte(1);
+		let candidate = runtime.candidate(relay_parent);
+		let pov = PoV { block_data: BlockData(vec![1, 2, 3]) };
+
+		with_mock_sender(runtime, |mut sender| async move {
+			let msg = FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			};
+			match interceptor.intercept_incoming(&mut sender, msg).await {
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(_, candidate, pov),
+				}) => (candidate, pov),
+				_ => panic!("candidate is not seconded"),
+			}
+		})
+	}
+
+	#[test]
+	fn fake_commitments_carry_code_exceeding_threshold() {
+		let validation_data = PersistedValidationData::default();
+
+		let plain = create_fake_candidate_commitments(&validation_data, &Default::default());
+		assert_eq!(plain.new_validation_code, None);
+
+		let max_code_size = 1024;
+		let commitments =
+			create_fake_candidate_commitments(&validation_data, &oversized(max_code_size + 1));
+		let code = commitments.new_validation_code.as_ref().unwrap();
+		assert!(code.0.len() > max_code_size as usize);
+		assert_eq!(commitments.head_data, plain.head_data);
+		assert_eq!(commitments.hrmp_watermark, plain.hrmp_watermark);
+	}
+
+	#[test]
+	fn default_code_size_exceeds_hard_limit() {
+		let opts = OversizedCodeOptions::try_parse_from(["malus", "--bob"]).unwrap();
+		assert!(opts.code_size > MAX_CODE_SIZE);
+	}
+
+	#[test]
+	fn seconded_candidate_commits_to_oversized_code() {
+		let runtime = MockRuntime::new(10);
+		let validation_data = runtime.validation_data.clone().unwrap();
+		let original = runtime.candidate(Hash::repeat_byte(1));
+
+		let interceptor = AttachOversizedCode {
+			percentage: Percentage::new(100.0).unwrap(),
+			fake_commitments: oversized(2048),
+		};
+		let (candidate, pov) = second(&interceptor, runtime);
+
+		let commitments = create_fake_candidate_commitments(&validation_data, &oversized(2048));
+		assert_eq!(candidate.commitments_hash, commitments.hash());
+		assert_ne!(candidate.hash(), original.hash());
+		assert_eq!(candidate.descriptor.pov_hash, pov.hash());
+	}
+}
@@ -0,0 +1,300 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that confirms seconded collations to collators out of order.
+//! `Seconded` notifications are buffered and flushed in reverse order once the
+//! window is full, or once they have been held back for `MAX_HOLD`, to stress the
+//! handling of out-of-order confirmations on the collator side.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::SpawnGlue;
+use sp_core::traits::SpawnNamed;
+
+use futures::{
+	channel::mpsc,
+	future::{self, Either},
+	StreamExt,
+};
+use futures_timer::Delay;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{spawn_name, SpawnOptions, MALUS_REORDER_SECONDED},
+};
+
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+/// Name of the task releasing reordered notifications, after the spawn prefix.
+const TASK: &str = "reorder-seconded";
+
+/// The longest a `Seconded` notification is held back while waiting for the window to fill.
+const MAX_HOLD: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct ReorderSecondedOptions {
+	/// Number of `Seconded` notifications that are buffered and flushed in reverse order.
+	/// Values below 2 disable the reordering.
+	#[clap(long, default_value_t = 2)]
+	pub window_size: usize,
+
+	#[clap(flatten)]
+	pub spawn: SpawnOptions,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Buffers items until `window` of them are collected and hands them out in reverse order.
+struct ReorderBuffer<T> {
+	window: usize,
+	buffered: Vec<T>,
+}
+
+impl<T> ReorderBuffer<T> {
+	fn new(window: usize) -> Self {
+		Self { window, buffered: Vec::with_capacity(window) }
+	}
+
+	fn is_empty(&self) -> bool {
+		self.buffered.is_empty()
+	}
+
+	/// Buffer an item, returning the reversed window if it is full now.
+	fn push(&mut self, item: T) -> Vec<T> {
+		self.buffered.push(item);
+		if self.buffered.len() >= self.window {
+			self.flush()
+		} else {
+			Vec::new()
+		}
+	}
+
+	/// Hand out all buffered items, in reverse order.
+	fn flush(&mut self) -> Vec<T> {
+		let mut flushed = std::mem::take(&mut self.buffered);
+		flushed.reverse();
+		flushed
+	}
+}
+
+/// Send out the queued messages, reversed in windows of `window` messages.
+async fn release_reordered<Sender>(
+	mut queue: mpsc::UnboundedReceiver<CollatorProtocolMessage>,
+	mut sender: Sender,
+	window: usize,
+) where
+	Sender: overseer::CandidateBackingSenderTrait,
+{
+	let mut buffer = ReorderBuffer::new(window);
+	// When the oldest buffered message is due, regardless of the window being full.
+	let mut flush_at = Instant::now();
+	loop {
+		let next = if buffer.is_empty() {
+			let next = queue.next().await;
+			flush_at = Instant::now() + MAX_HOLD;
+			next
+		} else {
+			let hold = flush_at.saturating_duration_since(Instant::now());
+			match future::select(queue.next(), Delay::new(hold)).await {
+				Either::Left((next, _)) => next,
+				Either::Right(((), _)) => {
+					gum::trace!(target: MALUS_REORDER_SECONDED, "😈 Flushing incomplete window of seconded collations");
+					for msg in buffer.flush() {
+						sender.send_message(msg).await;
+					}
+					continue
+				},
+			}
+		};
+
+		match next {
+			Some(msg) =>
+				for msg in buffer.push(msg) {
+					sender.send_message(msg).await;
+				},
+			None => {
+				for msg in buffer.flush() {
+					sender.send_message(msg).await;
+				}
+				return
+			},
+		}
+	}
+}
+
+/// Reorder the `Seconded` notifications sent by candidate backing.
+#[derive(Clone)]
+struct ReorderSeconded<Spawner> {
+	window: usize,
+	spawner: Spawner,
+	spawn_prefix: &'static str,
+	queue: mpsc::UnboundedSender<CollatorProtocolMessage>,
+	/// Receiving end of the queue, until it's handed to the release task.
+	pending: Arc<Mutex<Option<mpsc::UnboundedReceiver<CollatorProtocolMessage>>>>,
+}
+
+impl<Spawner> ReorderSeconded<Spawner> {
+	fn new(window: usize, spawner: Spawner, spawn_prefix: &'static str) -> Self {
+		let (queue, pending) = mpsc::unbounded();
+		Self { window, spawner, spawn_prefix, queue, pending: Arc::new(Mutex::new(Some(pending))) }
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender, Spawner> MessageInterceptor<Sender> for ReorderSeconded<Spawner>
+where
+	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+	Spawner: SpawnNamed + Clone + 'static,
+{
+	type Message = CandidateBackingMessage;
+
+	/// Launch the task releasing reordered notifications, once we got hold of a sender.
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		let pending = self.pending.lock().expect("bad lock").take();
+		if let Some(queue) = pending {
+			self.spawner.spawn(
+				spawn_name(self.spawn_prefix, TASK),
+				Some(self.spawn_prefix),
+				Box::pin(release_reordered(queue, subsystem_sender.clone(), self.window)),
+			);
+		}
+		Some(msg)
+	}
+
+	fn intercept_outgoing(
+		&self,
+		msg: overseer::CandidateBackingOutgoingMessages,
+	) -> Option<overseer::CandidateBackingOutgoingMessages> {
+		match msg {
+			overseer::CandidateBackingOutgoingMessages::CollatorProtocolMessage(
+				msg @ CollatorProtocolMessage::Seconded(..),
+			) if self.window > 1 => match self.queue.unbounded_send(msg) {
+				Ok(()) => None,
+				// The release task is gone, don't swallow the notification.
+				Err(err) => Some(err.into_inner().into()),
+			},
+			msg => Some(msg),
+		}
+	}
+}
+
+/// Generates an overseer that confirms seconded collations out of order.
+pub(crate) struct ReorderSecondedCollations {
+	/// Number of notifications reversed at a time.
+	window: usize,
+	/// Prefix of the names of spawned tasks.
+	spawn_prefix: &'static str,
+}
+
+impl ReorderSecondedCollations {
+	pub fn new(opts: ReorderSecondedOptions) -> Self {
+		Self { window: opts.window_size, spawn_prefix: opts.spawn.static_spawn_prefix() }
+	}
+}
+
+impl OverseerGen for ReorderSecondedCollations {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_REORDER_SECONDED,
+			"😈 Started Malus node reordering seconded collations in windows of {}.",
+			self.window,
+		);
+
+		let reorder_seconded =
+			ReorderSeconded::new(self.window, args.spawner.clone(), self.spawn_prefix);
+
+		prepared_overseer_builder(args)?
+			.replace_candidate_backing(move |cb| {
+				InterceptedSubsystem::new(
+					"reorder-seconded",
+					self.spawn_prefix,
+					cb,
+					reorder_seconded,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn full_windows_are_reversed() {
+		let mut buffer = ReorderBuffer::new(3);
+
+		assert!(buffer.push(1).is_empty());
+		assert!(buffer.push(2).is_empty());
+		assert_eq!(buffer.push(3), vec![3, 2, 1]);
+		assert!(buffer.is_empty());
+
+		assert!(buffer.push(4).is_empty());
+		assert!(buffer.push(5).is_empty());
+		assert_eq!(buffer.push(6), vec![6, 5, 4]);
+	}
+
+	#[test]
+	fn incomplete_window_is_flushed_reversed() {
+		let mut buffer = ReorderBuffer::new(4);
+
+		assert!(buffer.push(1).is_empty());
+		assert!(buffer.push(2).is_empty());
+		assert_eq!(buffer.flush(), vec![2, 1]);
+		assert!(buffer.is_empty());
+		assert!(buffer.flush().is_empty());
+	}
+
+	#[test]
+	fn window_of_one_keeps_order() {
+		let mut buffer = ReorderBuffer::new(1);
+
+		assert_eq!(buffer.push(1), vec![1]);
+		assert_eq!(buffer.push(2), vec![2]);
+	}
+}
@@ -0,0 +1,403 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that, whenever it seconds a candidate, also seconds conflicting
+//! candidates of the same para at the same relay parent, such that it originates more
+//! distinct `Seconded` statements than the seconding limit of its group permits. The
+//! conflicting statements are signed with our validator key and gossiped straight to
+//! all connected peers, ahead of the honest statement. This exercises the enforcement
+//! of the per-originator limit on the receiving side.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use parity_scale_codec::Encode;
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_network_protocol::{v1 as protocol_v1, PeerId, Versioned};
+use polkadot_node_primitives::{SignedFullStatement, Statement, UncheckedSignedFullStatement};
+use polkadot_node_subsystem::SpawnGlue;
+use polkadot_node_subsystem_util::request_session_index_for_child;
+use polkadot_primitives::v2::{
+	CandidateDescriptor, CommittedCandidateReceipt, CompactStatement, Hash, SigningContext,
+	ValidatorPair, PARACHAIN_KEY_TYPE_ID,
+};
+use sc_keystore::LocalKeystore;
+use sp_core::{crypto::Pair, traits::SpawnNamed};
+use sp_keystore::SyncCryptoStore;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_SECONDED_OVERSHOOT},
+	variants::sign_with_new_collator,
+};
+
+use std::{
+	collections::HashSet,
+	sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct SecondedOvershootOptions {
+	/// The number of distinct candidates the group permits each validator to second.
+	/// Defaults to the limit of the current statement distribution protocol.
+	#[clap(long, default_value_t = 2)]
+	pub seconding_limit: usize,
+
+	/// The number of distinct candidates seconded beyond the seconding limit.
+	#[clap(long, default_value_t = 1)]
+	pub overshoot: usize,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// `count` distinct candidates of the same para and relay parent as `candidate`, none of
+/// which is `candidate` itself.
+fn conflicting_candidates(
+	candidate: &CommittedCandidateReceipt,
+	count: usize,
+) -> Vec<CommittedCandidateReceipt> {
+	(0..count as u32)
+		.map(|n| CommittedCandidateReceipt {
+			descriptor: sign_with_new_collator(CandidateDescriptor {
+				pov_hash: sp_core::hashing::blake2_256(
+					&(candidate.descriptor.pov_hash, n).encode(),
+				)
+				.into(),
+				..candidate.descriptor.clone()
+			}),
+			commitments: candidate.commitments.clone(),
+		})
+		.collect()
+}
+
+/// `Seconded` statements of `count` candidates conflicting with the one seconded by
+/// `statement`, signed by the same key. Returns `None` if `statement` does not second
+/// a candidate or the key that signed it is not in our keystore.
+fn overshooting_statements(
+	keystore: &LocalKeystore,
+	statement: &SignedFullStatement,
+	context: &SigningContext,
+	count: usize,
+) -> Option<Vec<UncheckedSignedFullStatement>> {
+	let candidate = match statement.payload() {
+		Statement::Seconded(candidate) => candidate,
+		Statement::Valid(_) => return None,
+	};
+
+	let key = SyncCryptoStore::sr25519_public_keys(keystore, PARACHAIN_KEY_TYPE_ID)
+		.into_iter()
+		.filter_map(|public| keystore.key_pair::<ValidatorPair>(&public.into()).ok()?)
+		.find(|pair| statement.as_unchecked().check_signature(context, &pair.public()).is_ok())?;
+
+	Some(
+		conflicting_candidates(candidate, count)
+			.into_iter()
+			.map(|candidate| {
+				let payload = CompactStatement::Seconded(candidate.hash()).signing_payload(context);
+				UncheckedSignedFullStatement::new(
+					Statement::Seconded(candidate),
+					statement.validator_index(),
+					key.sign(&payload[..]),
+				)
+			})
+			.collect(),
+	)
+}
+
+/// Second conflicting candidates alongside each candidate we second.
+#[derive(Clone)]
+struct OvershootSeconded {
+	/// Peers currently connected on the validation peer set.
+	peers: Arc<Mutex<HashSet<PeerId>>>,
+	keystore: Arc<LocalKeystore>,
+	/// The number of conflicting candidates seconded alongside each honest one.
+	conflicting: usize,
+}
+
+impl OvershootSeconded {
+	fn new(keystore: Arc<LocalKeystore>, seconding_limit: usize, overshoot: usize) -> Self {
+		Self {
+			peers: Arc::new(Mutex::new(HashSet::new())),
+			keystore,
+			// the honest candidate counts against the limit as well.
+			conflicting: (seconding_limit + overshoot).saturating_sub(1),
+		}
+	}
+
+	/// Gossip the conflicting statements for our `Seconded` statement to all peers.
+	async fn overshoot<Sender>(
+		&self,
+		sender: &mut Sender,
+		relay_parent: Hash,
+		statement: &SignedFullStatement,
+	) where
+		Sender: overseer::StatementDistributionSenderTrait,
+	{
+		let session_index = match request_session_index_for_child(relay_parent, sender).await.await
+		{
+			Ok(Ok(session_index)) => session_index,
+			_ => {
+				gum::warn!(
+					target: MALUS_SECONDED_OVERSHOOT,
+					?relay_parent,
+					"Unable to fetch the session index, not overshooting"
+				);
+				return
+			},
+		};
+		let context = SigningContext { session_index, parent_hash: relay_parent };
+
+		let statements =
+			match overshooting_statements(&self.keystore, statement, &context, self.conflicting) {
+				Some(statements) => statements,
+				None => {
+					gum::warn!(
+						target: MALUS_SECONDED_OVERSHOOT,
+						?relay_parent,
+						validator_index = ?statement.validator_index(),
+						"Unable to sign conflicting statements, not overshooting"
+					);
+					return
+				},
+			};
+
+		let peers: Vec<_> = self.peers.lock().expect("bad lock").iter().cloned().collect();
+
+		gum::debug!(
+			target: MALUS_SECONDED_OVERSHOOT,
+			?relay_parent,
+			validator_index = ?statement.validator_index(),
+			n_statements = statements.len(),
+			n_peers = peers.len(),
+			"😈 Seconding conflicting candidates"
+		);
+
+		if peers.is_empty() {
+			return
+		}
+
+		for statement in statements {
+			sender
+				.send_message(NetworkBridgeTxMessage::SendValidationMessage(
+					peers.clone(),
+					Versioned::V1(protocol_v1::ValidationProtocol::StatementDistribution(
+						protocol_v1::StatementDistributionMessage::Statement(
+							relay_parent,
+							statement,
+						),
+					)),
+				))
+				.await;
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for OvershootSeconded
+where
+	Sender: overseer::StatementDistributionSenderTrait + Clone + Send + 'static,
+{
+	type Message = StatementDistributionMessage;
+
+	/// Track peers, and gossip conflicting statements for our `Seconded` statements. All
+	/// messages are passed on to statement distribution unchanged.
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match &msg {
+			FromOrchestra::Communication {
+				msg: StatementDistributionMessage::NetworkBridgeUpdate(event),
+			} => match event {
+				NetworkBridgeEvent::PeerConnected(peer, ..) => {
+					self.peers.lock().expect("bad lock").insert(*peer);
+				},
+				NetworkBridgeEvent::PeerDisconnected(peer) => {
+					self.peers.lock().expect("bad lock").remove(peer);
+				},
+				_ => {},
+			},
+			FromOrchestra::Communication {
+				msg: StatementDistributionMessage::Share(relay_parent, statement),
+			} if matches!(statement.payload(), Statement::Seconded(_)) => {
+				self.overshoot(subsystem_sender, *relay_parent, statement).await;
+			},
+			_ => {},
+		}
+
+		Some(msg)
+	}
+}
+
+/// Generates an overseer that seconds more candidates than the seconding limit permits.
+pub(crate) struct SecondedOvershoot {
+	/// The number of distinct candidates each validator may second.
+	seconding_limit: usize,
+	/// The number of distinct candidates seconded beyond the limit.
+	overshoot: usize,
+}
+
+impl SecondedOvershoot {
+	pub fn new(opts: SecondedOvershootOptions) -> Self {
+		Self { seconding_limit: opts.seconding_limit, overshoot: opts.overshoot }
+	}
+}
+
+impl OverseerGen for SecondedOvershoot {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_SECONDED_OVERSHOOT,
+			"😈 Started Malus node seconding {} candidates beyond the seconding limit of {}.",
+			self.overshoot,
+			self.seconding_limit,
+		);
+
+		let overshoot_seconded =
+			OvershootSeconded::new(args.keystore.clone(), self.seconding_limit, self.overshoot);
+
+		prepared_overseer_builder(args)?
+			.replace_statement_distribution(move |statement_distribution| {
+				InterceptedSubsystem::new(
+					"seconded-overshoot",
+					DEFAULT_SPAWN_PREFIX,
+					statement_distribution,
+					overshoot_seconded,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use polkadot_primitives::v2::{ValidatorId, ValidatorIndex};
+	use polkadot_primitives_test_helpers::dummy_committed_candidate_receipt;
+
+	fn context() -> SigningContext {
+		SigningContext { session_index: 1, parent_hash: Hash::repeat_byte(1) }
+	}
+
+	/// A keystore with a single validator key, along with our `Seconded` statement signed
+	/// by it.
+	fn setup() -> (LocalKeystore, SignedFullStatement, ValidatorId) {
+		let keystore = LocalKeystore::in_memory();
+		let public: ValidatorId =
+			SyncCryptoStore::sr25519_generate_new(&keystore, PARACHAIN_KEY_TYPE_ID, None)
+				.unwrap()
+				.into();
+		let pair = keystore.key_pair::<ValidatorPair>(&public).unwrap().unwrap();
+
+		let candidate = dummy_committed_candidate_receipt(Hash::repeat_byte(1));
+		let payload = CompactStatement::Seconded(candidate.hash()).signing_payload(&context());
+		let statement = SignedFullStatement::new(
+			Statement::Seconded(candidate),
+			ValidatorIndex(3),
+			pair.sign(&payload[..]),
+			&context(),
+			&public,
+		)
+		.unwrap();
+
+		(keystore, statement, public)
+	}
+
+	fn seconded(statement: &UncheckedSignedFullStatement) -> &CommittedCandidateReceipt {
+		match statement.unchecked_payload() {
+			Statement::Seconded(candidate) => candidate,
+			Statement::Valid(_) => panic!("statement does not second a candidate"),
+		}
+	}
+
+	#[test]
+	fn over_limit_statements_are_distinct_and_signed() {
+		let (keystore, statement, public) = setup();
+		let interceptor = OvershootSeconded::new(Arc::new(LocalKeystore::in_memory()), 2, 3);
+		assert_eq!(interceptor.conflicting, 4);
+
+		let statements =
+			overshooting_statements(&keystore, &statement, &context(), interceptor.conflicting)
+				.unwrap();
+		assert_eq!(statements.len(), 4);
+
+		// together with the honest statement, `seconding_limit + overshoot` distinct
+		// candidates are seconded.
+		let candidates: HashSet<_> = statements
+			.iter()
+			.map(|s| s.unchecked_payload().candidate_hash())
+			.chain(std::iter::once(statement.payload().candidate_hash()))
+			.collect();
+		assert_eq!(candidates.len(), 5);
+
+		let honest = seconded(statement.as_unchecked());
+		for s in statements {
+			let candidate = seconded(&s);
+			assert_eq!(candidate.descriptor.para_id, honest.descriptor.para_id);
+			assert_eq!(candidate.descriptor.relay_parent, honest.descriptor.relay_parent);
+			assert!(candidate.descriptor.check_collator_signature().is_ok());
+			assert_eq!(s.unchecked_validator_index(), statement.validator_index());
+			assert!(s.try_into_checked(&context(), &public).is_ok());
+		}
+	}
+
+	#[test]
+	fn foreign_or_valid_statements_are_not_overshot() {
+		let (_, statement, _) = setup();
+
+		// the key that signed the statement is not ours.
+		let keystore = LocalKeystore::in_memory();
+		SyncCryptoStore::sr25519_generate_new(&keystore, PARACHAIN_KEY_TYPE_ID, None).unwrap();
+		assert!(overshooting_statements(&keystore, &statement, &context(), 2).is_none());
+
+		let (keystore, statement, public) = setup();
+		let candidate_hash = statement.payload().candidate_hash();
+		let payload = CompactStatement::Valid(candidate_hash).signing_payload(&context());
+		let pair = keystore.key_pair::<ValidatorPair>(&public).unwrap().unwrap();
+		let valid = SignedFullStatement::new(
+			Statement::Valid(candidate_hash),
+			ValidatorIndex(3),
+			pair.sign(&payload[..]),
+			&context(),
+			&public,
+		)
+		.unwrap();
+		assert!(overshooting_statements(&keystore, &valid, &context(), 2).is_none());
+	}
+}
@@ -0,0 +1,334 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A node that participates in disputes honestly, but slowly. The confirmations of
+//! dispute statement imports the dispute coordinator hands out are held back for a
+//! configurable duration plus jitter, in the order the imports were requested. No
+//! confirmation is dropped.
+//!
+//! Unlike `false-dispute`, nothing is disputed falsely, which allows testing the timing
+//! of dispute resolution in the presence of slow validators.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::SpawnGlue;
+use sp_core::traits::SpawnNamed;
+
+use futures::{
+	channel::{mpsc, oneshot},
+	StreamExt,
+};
+use futures_timer::Delay;
+use rand::Rng;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{spawn_name, SpawnOptions, MALUS_SLOW_DISPUTE_PARTICIPATION},
+};
+
+use std::{
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+/// Name of the task releasing held back confirmations, after the spawn prefix.
+const TASK: &str = "slow-dispute-participation";
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct SlowDisputeParticipationOptions {
+	/// Duration in milliseconds for which confirmations of dispute statement imports are
+	/// held back.
+	#[clap(long, default_value_t = 1000)]
+	pub participation_delay_ms: u64,
+
+	/// Upper bound in milliseconds of a random duration added to the delay of each
+	/// confirmation.
+	#[clap(long, default_value_t = 0)]
+	pub jitter_ms: u64,
+
+	#[clap(flatten)]
+	pub spawn: SpawnOptions,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// A confirmation held back until the given instant. The result is forwarded from the
+/// receiver handed out by the dispute coordinator to the sender of the requester.
+struct Delayed {
+	due: Instant,
+	result: oneshot::Receiver<ImportStatementsResult>,
+	confirm: oneshot::Sender<ImportStatementsResult>,
+}
+
+/// Hold back the confirmations of dispute statement imports.
+#[derive(Clone)]
+struct SlowParticipation {
+	delay: Duration,
+	jitter: Duration,
+	queue: mpsc::UnboundedSender<Delayed>,
+}
+
+impl SlowParticipation {
+	/// Returns the interceptor alongside the receiving end of its queue, to be handed to
+	/// `release_delayed`.
+	fn new(delay: Duration, jitter: Duration) -> (Self, mpsc::UnboundedReceiver<Delayed>) {
+		let (queue, pending) = mpsc::unbounded();
+		(Self { delay, jitter, queue }, pending)
+	}
+
+	/// The instant a confirmation requested now is due.
+	fn due(&self) -> Instant {
+		let jitter = rand::thread_rng().gen_range(Duration::ZERO..=self.jitter);
+		Instant::now() + self.delay + jitter
+	}
+}
+
+/// Forward the queued confirmations once they are due. Releasing them in queue order
+/// preserves the order they were requested in, even if jitter makes a later one due first.
+async fn release_delayed(mut queue: mpsc::UnboundedReceiver<Delayed>) {
+	while let Some(Delayed { due, result, confirm }) = queue.next().await {
+		let now = Instant::now();
+		if due > now {
+			Delay::new(due - now).await;
+		}
+		// If the dispute coordinator dropped the request, so do we.
+		if let Ok(result) = result.await {
+			let _ = confirm.send(result);
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for SlowParticipation
+where
+	Sender: overseer::DisputeCoordinatorSenderTrait + Clone + Send + 'static,
+{
+	type Message = DisputeCoordinatorMessage;
+
+	async fn intercept_incoming(
+		&self,
+		_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg:
+					DisputeCoordinatorMessage::ImportStatements {
+						candidate_receipt,
+						session,
+						statements,
+						pending_confirmation: Some(confirm),
+					},
+			} => {
+				let (tx, result) = oneshot::channel();
+				let due = self.due();
+				gum::trace!(
+					target: MALUS_SLOW_DISPUTE_PARTICIPATION,
+					candidate_hash = ?candidate_receipt.hash(),
+					?session,
+					"😈 Holding back import confirmation"
+				);
+
+				let pending_confirmation =
+					match self.queue.unbounded_send(Delayed { due, result, confirm }) {
+						Ok(()) => tx,
+						// The release task is gone, don't swallow the confirmation.
+						Err(err) => err.into_inner().confirm,
+					};
+
+				Some(FromOrchestra::Communication {
+					msg: DisputeCoordinatorMessage::ImportStatements {
+						candidate_receipt,
+						session,
+						statements,
+						pending_confirmation: Some(pending_confirmation),
+					},
+				})
+			},
+			msg => Some(msg),
+		}
+	}
+}
+
+/// Generates an overseer that holds back the confirmations of dispute statement imports.
+pub(crate) struct SlowDisputeParticipation {
+	/// Duration to hold back confirmations for.
+	delay: Duration,
+	/// Upper bound of the random duration added to the delay.
+	jitter: Duration,
+	/// Prefix of the names of spawned tasks.
+	spawn_prefix: &'static str,
+}
+
+impl SlowDisputeParticipation {
+	pub fn new(opts: SlowDisputeParticipationOptions) -> Self {
+		Self {
+			delay: Duration::from_millis(opts.participation_delay_ms),
+			jitter: Duration::from_millis(opts.jitter_ms),
+			spawn_prefix: opts.spawn.static_spawn_prefix(),
+		}
+	}
+}
+
+impl OverseerGen for SlowDisputeParticipation {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_SLOW_DISPUTE_PARTICIPATION,
+			"😈 Started Malus node delaying dispute import confirmations by {:?} plus up to {:?}.",
+			self.delay,
+			self.jitter,
+		);
+
+		let (slow_participation, queue) = SlowParticipation::new(self.delay, self.jitter);
+		args.spawner.spawn(
+			spawn_name(self.spawn_prefix, TASK),
+			Some(self.spawn_prefix),
+			Box::pin(release_delayed(queue)),
+		);
+
+		prepared_overseer_builder(args)?
+			.replace_dispute_coordinator(move |dispute_coordinator| {
+				InterceptedSubsystem::new(
+					"slow-dispute-participation",
+					self.spawn_prefix,
+					dispute_coordinator,
+					slow_participation,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::{executor::block_on, future::join, stream::FuturesUnordered};
+	use polkadot_node_subsystem_test_helpers::sender_receiver;
+	use polkadot_primitives::v2::Hash;
+	use polkadot_primitives_test_helpers::dummy_candidate_receipt;
+
+	fn import(
+		n: u8,
+		pending_confirmation: Option<oneshot::Sender<ImportStatementsResult>>,
+	) -> FromOrchestra<DisputeCoordinatorMessage> {
+		FromOrchestra::Communication {
+			msg: DisputeCoordinatorMessage::ImportStatements {
+				candidate_receipt: dummy_candidate_receipt(Hash::repeat_byte(n)),
+				session: 1,
+				statements: Vec::new(),
+				pending_confirmation,
+			},
+		}
+	}
+
+	/// Pass the import through the interceptor, returning the confirmation sender the
+	/// dispute coordinator receives.
+	fn intercept(
+		interceptor: &SlowParticipation,
+		msg: FromOrchestra<DisputeCoordinatorMessage>,
+	) -> Option<oneshot::Sender<ImportStatementsResult>> {
+		let (mut sender, _) = sender_receiver();
+		match block_on(interceptor.intercept_incoming(&mut sender, msg)) {
+			Some(FromOrchestra::Communication {
+				msg: DisputeCoordinatorMessage::ImportStatements { pending_confirmation, .. },
+			}) => pending_confirmation,
+			_ => panic!("import is not passed on"),
+		}
+	}
+
+	#[test]
+	fn confirmations_are_released_in_order_after_delay() {
+		let delay = Duration::from_millis(20);
+		let (interceptor, queue) = SlowParticipation::new(delay, Duration::from_millis(30));
+
+		let results = [
+			ImportStatementsResult::ValidImport,
+			ImportStatementsResult::InvalidImport,
+			ImportStatementsResult::ValidImport,
+			ImportStatementsResult::InvalidImport,
+		];
+
+		let start = Instant::now();
+		let (requested, coordinator): (Vec<_>, Vec<_>) = (0..results.len())
+			.map(|n| {
+				let (tx, rx) = oneshot::channel();
+				(rx, intercept(&interceptor, import(n as u8, Some(tx))).unwrap())
+			})
+			.unzip();
+		drop(interceptor);
+
+		// the dispute coordinator confirms the imports right away, in reverse order.
+		for (confirm, result) in coordinator.into_iter().zip(results).rev() {
+			confirm.send(result).unwrap();
+		}
+
+		let confirmed = requested
+			.into_iter()
+			.enumerate()
+			.map(|(n, rx)| async move { (n, rx.await.unwrap()) })
+			.collect::<FuturesUnordered<_>>()
+			.collect::<Vec<_>>();
+		let ((), confirmed) = block_on(join(release_delayed(queue), confirmed));
+
+		assert!(start.elapsed() >= delay);
+		assert_eq!(confirmed, results.into_iter().enumerate().collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn dropped_requests_stay_dropped() {
+		let (interceptor, queue) = SlowParticipation::new(Duration::ZERO, Duration::ZERO);
+
+		let (tx, rx) = oneshot::channel();
+		drop(intercept(&interceptor, import(1, Some(tx))));
+		drop(interceptor);
+
+		block_on(release_delayed(queue));
+		assert!(block_on(rx).is_err());
+	}
+
+	#[test]
+	fn unconfirmed_imports_pass_through() {
+		let (interceptor, mut queue) = SlowParticipation::new(Duration::ZERO, Duration::ZERO);
+
+		assert!(intercept(&interceptor, import(1, None)).is_none());
+		assert!(queue.try_next().is_err());
+	}
+}
@@ -0,0 +1,268 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that validates candidates in backing against stale validation
+//! code, whose hash no longer matches the `validation_code_hash` of the descriptor.
+//! The code a para used before its latest upgrade is served if it is known,
+//! otherwise the current code is tampered with.
+//!
+//! This exercises the rejection of code hash mismatches by candidate validation.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_core_candidate_validation::find_validation_data;
+use polkadot_node_subsystem::{SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{Id as ParaId, ValidationCode};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_STALE_CODE},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct StaleCodeOptions {
+	/// Determines the percentage of candidates validated against stale code in backing.
+	/// Defaults to all candidates.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Validation code of a para, as seen by this node.
+struct KnownCode {
+	current: ValidationCode,
+	/// The code used before the current one, if an upgrade has been observed.
+	previous: Option<ValidationCode>,
+}
+
+/// Pick validation code which does not match `current`: the `previous` code if there
+/// is one, otherwise `current` with a trailing byte appended.
+fn stale_code(current: &ValidationCode, previous: Option<&ValidationCode>) -> ValidationCode {
+	match previous {
+		Some(previous) if previous.hash() != current.hash() => previous.clone(),
+		_ => {
+			let mut code = current.0.clone();
+			code.push(0);
+			ValidationCode(code)
+		},
+	}
+}
+
+/// Validate candidates against stale validation code.
+#[derive(Clone)]
+struct ServeStaleCode {
+	percentage: Percentage,
+	codes: Arc<Mutex<HashMap<ParaId, KnownCode>>>,
+}
+
+impl ServeStaleCode {
+	fn new(percentage: Percentage) -> Self {
+		Self { percentage, codes: Arc::new(Mutex::new(HashMap::new())) }
+	}
+
+	/// Note the current code of the para and return stale code for it.
+	fn stale_code_for(&self, para_id: ParaId, current: &ValidationCode) -> ValidationCode {
+		let mut codes = self.codes.lock().expect("bad lock");
+		let known = codes
+			.entry(para_id)
+			.or_insert_with(|| KnownCode { current: current.clone(), previous: None });
+		if known.current.hash() != current.hash() {
+			known.previous = Some(std::mem::replace(&mut known.current, current.clone()));
+		}
+		stale_code(current, known.previous.as_ref())
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for ServeStaleCode
+where
+	Sender: overseer::CandidateValidationSenderTrait + Clone + Send + 'static,
+{
+	type Message = CandidateValidationMessage;
+
+	async fn intercept_incoming(
+		&self,
+		subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg:
+					CandidateValidationMessage::ValidateFromChainState(
+						candidate_receipt,
+						pov,
+						timeout,
+						response_sender,
+					),
+			} => {
+				if !self.percentage.sample(&mut rand::thread_rng()) {
+					return Some(FromOrchestra::Communication {
+						msg: CandidateValidationMessage::ValidateFromChainState(
+							candidate_receipt,
+							pov,
+							timeout,
+							response_sender,
+						),
+					})
+				}
+
+				let (validation_data, validation_code) =
+					match find_validation_data(subsystem_sender, &candidate_receipt.descriptor)
+						.await
+					{
+						Ok(Some(data)) => data,
+						_ => {
+							gum::warn!(
+								target: MALUS_STALE_CODE,
+								candidate_hash = ?candidate_receipt.hash(),
+								"Unable to fetch validation data, validating honestly"
+							);
+							return Some(FromOrchestra::Communication {
+								msg: CandidateValidationMessage::ValidateFromChainState(
+									candidate_receipt,
+									pov,
+									timeout,
+									response_sender,
+								),
+							})
+						},
+					};
+
+				let stale =
+					self.stale_code_for(candidate_receipt.descriptor.para_id, &validation_code);
+				gum::info!(
+					target: MALUS_STALE_CODE,
+					candidate_hash = ?candidate_receipt.hash(),
+					validation_code_hash = ?validation_code.hash(),
+					stale_code_hash = ?stale.hash(),
+					"😈 Validating candidate against stale code"
+				);
+
+				Some(FromOrchestra::Communication {
+					msg: CandidateValidationMessage::ValidateFromExhaustive(
+						validation_data,
+						stale,
+						candidate_receipt,
+						pov,
+						timeout,
+						response_sender,
+					),
+				})
+			},
+			msg => Some(msg),
+		}
+	}
+}
+
+/// Generates an overseer that validates backed candidates against stale code.
+pub(crate) struct StaleCode {
+	/// Percentage of candidates to validate against stale code.
+	percentage: u8,
+}
+
+impl StaleCode {
+	pub fn new(opts: StaleCodeOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for StaleCode {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_STALE_CODE,
+			"😈 Started Malus node validating {:?} percent of candidates against stale code.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the stale code percentage: {}", e))
+		})?;
+		let serve_stale_code = ServeStaleCode::new(percentage);
+
+		prepared_overseer_builder(args)?
+			.replace_candidate_validation(move |cv| {
+				InterceptedSubsystem::new("stale-code", DEFAULT_SPAWN_PREFIX, cv, serve_stale_code)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn code(n: u8) -> ValidationCode {
+		ValidationCode(vec![n; 32])
+	}
+
+	#[test]
+	fn stale_code_hash_differs() {
+		let current = code(1);
+
+		assert_ne!(stale_code(&current, None).hash(), current.hash());
+		assert_ne!(stale_code(&current, Some(&current)).hash(), current.hash());
+		assert_eq!(stale_code(&current, Some(&code(2))), code(2));
+	}
+
+	#[test]
+	fn previous_code_is_served_after_upgrade() {
+		let interceptor = ServeStaleCode::new(Percentage::new(100.0).unwrap());
+		let para_id = ParaId::from(1);
+
+		let before = interceptor.stale_code_for(para_id, &code(1));
+		assert_ne!(before.hash(), code(1).hash());
+
+		assert_eq!(interceptor.stale_code_for(para_id, &code(2)), code(1));
+		assert_eq!(interceptor.stale_code_for(para_id, &code(2)), code(1));
+		assert_eq!(interceptor.stale_code_for(para_id, &code(3)), code(2));
+
+		// other paras are tracked separately.
+		assert_ne!(interceptor.stale_code_for(ParaId::from(2), &code(3)), code(2));
+	}
+}
@@ -0,0 +1,362 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that seconds candidates rebuilt against an ancestor of their
+//! relay parent. The descriptor is signed anew, so everything but the freshness of
+//! the relay parent stays consistent, which exercises the relay parent checks of
+//! backing.
+//!
+//! Ancestors are looked up among the leaves this node activated, so candidates
+//! whose relay parent wasn't around long enough are seconded honestly.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{OverseerSignal, SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::{BlockNumber, CandidateDescriptor, CandidateReceipt, Hash};
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{DEFAULT_SPAWN_PREFIX, MALUS_STALE_RELAY_PARENT},
+	variants::{parse_percentage, sign_with_new_collator, Percentage},
+};
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::{Arc, Mutex},
+};
+
+/// How far behind the most recent leaf relay parents of collations may be, on top
+/// of the ancestor depth, for their ancestor to still be known.
+const RELAY_PARENT_LAG: BlockNumber = 8;
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct StaleRelayParentOptions {
+	/// Determines the percentage of seconded candidates rebuilt against an ancestor.
+	/// Defaults to all of them.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	/// The number of blocks between the relay parent of a candidate and the ancestor
+	/// it is rebuilt against. Defaults to the parent of the relay parent.
+	#[clap(long, parse(try_from_str = parse_ancestor_depth), default_value_t = 1)]
+	pub ancestor_depth: BlockNumber,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Parses the ancestor depth, which must be at least one block.
+fn parse_ancestor_depth(s: &str) -> Result<BlockNumber, String> {
+	let depth: BlockNumber = s.parse().map_err(|e| format!("{}", e))?;
+	if depth == 0 {
+		return Err("the ancestor depth must be at least 1".into())
+	}
+	Ok(depth)
+}
+
+/// Rebuild the candidate against the given relay parent, signing it anew.
+fn rebase_candidate(candidate: &CandidateReceipt, relay_parent: Hash) -> CandidateReceipt {
+	CandidateReceipt {
+		descriptor: sign_with_new_collator(CandidateDescriptor {
+			relay_parent,
+			..candidate.descriptor.clone()
+		}),
+		commitments_hash: candidate.commitments_hash,
+	}
+}
+
+/// The recently activated leaves, by number. On forks, the most recently activated
+/// block of a given number is kept.
+struct RecentLeaves {
+	ancestor_depth: BlockNumber,
+	numbers: HashMap<Hash, BlockNumber>,
+	hashes: BTreeMap<BlockNumber, Hash>,
+}
+
+impl RecentLeaves {
+	fn new(ancestor_depth: BlockNumber) -> Self {
+		Self { ancestor_depth, numbers: HashMap::new(), hashes: BTreeMap::new() }
+	}
+
+	/// Note an activated leaf, dropping the leaves too old to be an ancestor of interest.
+	fn note_leaf(&mut self, hash: Hash, number: BlockNumber) {
+		if let Some(replaced) = self.hashes.insert(number, hash) {
+			self.numbers.remove(&replaced);
+		}
+		self.numbers.insert(hash, number);
+
+		let newest = self.hashes.keys().next_back().copied().unwrap_or(number);
+		let retained = self
+			.hashes
+			.split_off(&newest.saturating_sub(self.ancestor_depth + RELAY_PARENT_LAG));
+		for (_, pruned) in std::mem::replace(&mut self.hashes, retained) {
+			self.numbers.remove(&pruned);
+		}
+	}
+
+	/// The leaf `ancestor_depth` blocks before the given one, if known.
+	fn ancestor(&self, hash: &Hash) -> Option<Hash> {
+		let number = self.numbers.get(hash)?.checked_sub(self.ancestor_depth)?;
+		self.hashes.get(&number).copied()
+	}
+}
+
+/// Rebuilds candidates to be seconded against an ancestor of their relay parent.
+#[derive(Clone)]
+struct RebaseOnAncestor {
+	percentage: Percentage,
+	leaves: Arc<Mutex<RecentLeaves>>,
+}
+
+impl RebaseOnAncestor {
+	fn new(percentage: Percentage, ancestor_depth: BlockNumber) -> Self {
+		Self { percentage, leaves: Arc::new(Mutex::new(RecentLeaves::new(ancestor_depth))) }
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for RebaseOnAncestor
+where
+	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
+{
+	type Message = CandidateBackingMessage;
+
+	/// The candidate is still handed to the backing job of the actual relay parent,
+	/// which seconds it unless it checks the relay parent of the descriptor.
+	async fn intercept_incoming(
+		&self,
+		_subsystem_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			} => {
+				if !self.percentage.sample(&mut rand::thread_rng()) {
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				let ancestor = self
+					.leaves
+					.lock()
+					.expect("bad lock")
+					.ancestor(&candidate.descriptor.relay_parent);
+				let ancestor = match ancestor {
+					Some(ancestor) => ancestor,
+					None => {
+						gum::debug!(
+							target: MALUS_STALE_RELAY_PARENT,
+							candidate_hash = ?candidate.hash(),
+							?relay_parent,
+							"Ancestor of the relay parent unknown, not replacing candidate"
+						);
+						return Some(FromOrchestra::Communication {
+							msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+						})
+					},
+				};
+
+				let stale_candidate = rebase_candidate(&candidate, ancestor);
+				gum::info!(
+					target: MALUS_STALE_RELAY_PARENT,
+					candidate_hash = ?candidate.hash(),
+					stale_candidate_hash = ?stale_candidate.hash(),
+					?relay_parent,
+					?ancestor,
+					"😈 Rebuilt candidate against an ancestor of its relay parent"
+				);
+
+				Some(FromOrchestra::Communication {
+					msg: CandidateBackingMessage::Second(relay_parent, stale_candidate, pov),
+				})
+			},
+			msg => Some(msg),
+		}
+	}
+
+	fn observe_signal(&self, signal: &OverseerSignal) {
+		if let OverseerSignal::ActiveLeaves(update) = signal {
+			if let Some(leaf) = &update.activated {
+				self.leaves.lock().expect("bad lock").note_leaf(leaf.hash, leaf.number);
+			}
+		}
+	}
+}
+
+/// Generates an overseer that seconds candidates against stale relay parents.
+pub(crate) struct StaleRelayParent {
+	/// Percentage of candidates to rebuild against an ancestor.
+	percentage: u8,
+	/// Number of blocks between the relay parent and the ancestor.
+	ancestor_depth: BlockNumber,
+}
+
+impl StaleRelayParent {
+	pub fn new(opts: StaleRelayParentOptions) -> Self {
+		Self { percentage: opts.percentage, ancestor_depth: opts.ancestor_depth }
+	}
+}
+
+impl OverseerGen for StaleRelayParent {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_STALE_RELAY_PARENT,
+			"😈 Started Malus node seconding {:?} percent of candidates against the relay parent {} blocks back.",
+			self.percentage,
+			self.ancestor_depth,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the stale relay parent percentage: {}", e))
+		})?;
+		let rebase_on_ancestor = RebaseOnAncestor::new(percentage, self.ancestor_depth);
+
+		prepared_overseer_builder(args)?
+			.replace_candidate_backing(move |cb| {
+				InterceptedSubsystem::new(
+					"stale-relay-parent",
+					DEFAULT_SPAWN_PREFIX,
+					cb,
+					rebase_on_ancestor,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::FutureExt;
+	use polkadot_node_primitives::{BlockData, PoV};
+	use polkadot_node_subsystem::{jaeger, ActivatedLeaf, ActiveLeavesUpdate, LeafStatus};
+	use polkadot_node_subsystem_test_helpers::{sender_receiver, TestSubsystemSender};
+	use polkadot_primitives_test_helpers::dummy_candidate_receipt;
+
+	fn leaf(number: BlockNumber) -> Hash {
+		Hash::repeat_byte(number as u8)
+	}
+
+	fn activate(interceptor: &RebaseOnAncestor, number: BlockNumber) {
+		let update = ActiveLeavesUpdate::start_work(ActivatedLeaf {
+			hash: leaf(number),
+			number,
+			status: LeafStatus::Fresh,
+			span: Arc::new(jaeger::Span::Disabled),
+		});
+		MessageInterceptor::<TestSubsystemSender>::observe_signal(
+			interceptor,
+			&OverseerSignal::ActiveLeaves(update),
+		);
+	}
+
+	#[test]
+	fn ancestor_is_looked_up_by_depth() {
+		let mut leaves = RecentLeaves::new(2);
+		for n in 1..=5 {
+			leaves.note_leaf(leaf(n), n);
+		}
+
+		assert_eq!(leaves.ancestor(&leaf(5)), Some(leaf(3)));
+		assert_eq!(leaves.ancestor(&leaf(3)), Some(leaf(1)));
+		assert_eq!(leaves.ancestor(&leaf(2)), None);
+		assert_eq!(leaves.ancestor(&Hash::repeat_byte(0xff)), None);
+	}
+
+	#[test]
+	fn old_leaves_are_pruned() {
+		let mut leaves = RecentLeaves::new(1);
+		for n in 1..=100 {
+			leaves.note_leaf(leaf(n), n);
+		}
+
+		let kept = 1 + RELAY_PARENT_LAG as usize + 1;
+		assert_eq!(leaves.hashes.len(), kept);
+		assert_eq!(leaves.numbers.len(), kept);
+		assert_eq!(leaves.ancestor(&leaf(100)), Some(leaf(99)));
+	}
+
+	#[test]
+	fn ancestor_depth_must_be_positive() {
+		assert_eq!(parse_ancestor_depth("3"), Ok(3));
+		assert!(parse_ancestor_depth("0").is_err());
+		assert!(parse_ancestor_depth("-1").is_err());
+	}
+
+	#[test]
+	fn descriptor_carries_substituted_relay_parent() {
+		let interceptor = RebaseOnAncestor::new(Percentage::new(100.0).unwrap(), 2);
+		for n in 1..=4 {
+			activate(&interceptor, n);
+		}
+
+		let candidate = dummy_candidate_receipt(leaf(4));
+		let pov = PoV { block_data: BlockData(vec![1, 2, 3]) };
+		let second = FromOrchestra::Communication {
+			msg: CandidateBackingMessage::Second(leaf(4), candidate.clone(), pov.clone()),
+		};
+		let (mut sender, _rx) = sender_receiver();
+
+		assert_matches::assert_matches!(
+			interceptor.intercept_incoming(&mut sender, second).now_or_never(),
+			Some(Some(FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, stale, p),
+			})) => {
+				assert_eq!(relay_parent, leaf(4));
+				assert_eq!(stale.descriptor.relay_parent, leaf(2));
+				assert!(stale.descriptor.check_collator_signature().is_ok());
+				assert_eq!(
+					CandidateDescriptor {
+						relay_parent: candidate.descriptor.relay_parent,
+						collator: candidate.descriptor.collator.clone(),
+						signature: candidate.descriptor.signature.clone(),
+						..stale.descriptor
+					},
+					candidate.descriptor,
+				);
+				assert_eq!(stale.commitments_hash, candidate.commitments_hash);
+				assert_eq!(p, pov);
+			}
+		);
+	}
+}
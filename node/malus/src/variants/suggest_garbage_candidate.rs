@@ -29,20 +29,30 @@ use polkadot_cli::{
 		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
 		ProvideRuntimeApi,
 	},
+	RunCmd,
 };
-use polkadot_node_core_candidate_validation::find_validation_data;
 use polkadot_node_primitives::{AvailableData, BlockData, PoV};
-use polkadot_primitives::v2::{CandidateDescriptor, CandidateHash};
+use polkadot_primitives::v2::{
+	BlockNumber, CandidateDescriptor, CandidateHash, Hash, Id as ParaId, MAX_POV_SIZE,
+};
 
-use polkadot_node_subsystem_util::request_validators;
+use polkadot_node_subsystem_util::metrics::Metrics as _;
 use sp_core::traits::SpawnNamed;
 
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
 // Filter wrapping related types.
 use crate::{
 	interceptor::*,
-	shared::{MALICIOUS_POV, MALUS},
+	metrics::Metrics,
+	shared::{
+		BernoulliGate, ReplacedSubsystem, ReplacementGuard, DEFAULT_SPAWN_PREFIX, MALICIOUS_POV,
+		MALUS_GARBAGE,
+	},
 	variants::{
-		create_fake_candidate_commitments, FakeCandidateValidation, FakeCandidateValidationError,
+		create_fake_candidate_commitments, fetch_validation_data, parse_percentage,
+		sign_with_new_collator, sign_with_seeded_collator, FakeCandidateValidation,
+		FakeCandidateValidationError, FakeCommitments, Intermittent, Percentage,
 		ReplaceValidationResult,
 	},
 };
@@ -51,91 +61,462 @@ use crate::{
 // subsystem.
 use polkadot_node_subsystem::{
 	messages::{CandidateBackingMessage, CollatorProtocolMessage},
-	SpawnGlue,
+	OverseerSignal, SpawnGlue, SubsystemError,
 };
 use polkadot_primitives::v2::CandidateReceipt;
 
+use parity_scale_codec::Encode;
+
 use std::{
-	collections::HashMap,
+	collections::{BTreeMap, HashMap, HashSet},
+	fs::{File, OpenOptions},
+	io::{BufWriter, Write},
+	path::{Path, PathBuf},
 	sync::{Arc, Mutex},
+	time::{SystemTime, UNIX_EPOCH},
 };
 
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct SuggestGarbageCandidateOptions {
+	/// Determines the percentage of candidates that should be replaced by garbage candidates.
+	/// Defaults to replacing all candidates.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	/// Seed for the decisions whether to replace a candidate, for reproducible runs.
+	/// Decisions are random if not provided.
+	#[clap(long)]
+	pub seed: Option<u64>,
+
+	/// Derive the collator key garbage candidates are signed with from `--seed`, such that
+	/// the same inputs yield the same malicious candidate hash. A fresh key is generated
+	/// per candidate if not set.
+	#[clap(long, requires = "seed")]
+	pub deterministic_collator: bool,
+
+	/// Only replace candidates of the given parachain. Candidates of all parachains
+	/// are subject to replacement if not provided.
+	#[clap(long)]
+	pub target_para: Option<u32>,
+
+	/// Append whether a candidate got replaced to the given file, one line of
+	/// `<unix time in ms> <candidate hash> <replaced>` per candidate, such that the attacked
+	/// candidates can be reconstructed after a run. Candidates that could not be replaced,
+	/// e.g. because their validation data was unavailable, are logged as not replaced.
+	#[clap(long, parse(from_os_str))]
+	pub decision_log: Option<PathBuf>,
+
+	/// Size in bytes of the malicious `PoV` block data, which is padded deterministically
+	/// from the seed. Defaults to the bare malicious marker.
+	#[clap(long, parse(try_from_str = parse_malicious_pov_bytes))]
+	pub malicious_pov_bytes: Option<usize>,
+
+	/// Only log the candidates that would be replaced, without replacing them. Useful to
+	/// validate the targeting and percentage before running an actual attack.
+	#[clap(long)]
+	pub dry_run: bool,
+
+	/// Skip replacing candidates if the validator set is larger than this, as erasure coding
+	/// the malicious available data gets expensive. Candidates are always replaced if not provided.
+	#[clap(long)]
+	pub max_chunk_validators: Option<usize>,
+
+	/// Replace candidates in bursts, given as `<n>,<cooldown>`: `n` consecutive candidates
+	/// are replaced, then `cooldown` candidates are skipped, and so on. Replaces
+	/// `--percentage`, candidates are replaced at random if not provided.
+	#[clap(long, parse(try_from_str = parse_burst))]
+	pub burst: Option<Burst>,
+
+	/// Malicious candidate validation subsystem configuration. Determines whether backing and/or
+	/// approval checks of candidates pass or fail, regardless of their actual validity.
+	#[clap(long, arg_enum, ignore_case = true, default_value_t = FakeCandidateValidation::BackingAndApprovalValid)]
+	pub fake_validation: FakeCandidateValidation,
+
+	/// Applies only when `--fake-validation` is configured to reject candidates as invalid. It allows
+	/// to specify the exact error to return from the malicious candidate validation subsystem.
+	#[clap(long, arg_enum, ignore_case = true, default_value_t = FakeCandidateValidationError::InvalidOutputs)]
+	pub fake_validation_error: FakeCandidateValidationError,
+
+	/// Determines the percentage of candidates whose validation is faked as configured by
+	/// `--fake-validation`, the others are validated honestly. Defaults to all candidates.
+	#[clap(long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub fake_validation_percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Parses the size of the malicious `PoV` block data, which must fit the malicious marker
+/// and must not exceed the maximum `PoV` size.
+fn parse_malicious_pov_bytes(s: &str) -> Result<usize, String> {
+	let bytes: usize = s.parse().map_err(|e| format!("{}", e))?;
+	if bytes < MALICIOUS_POV.len() {
+		return Err(format!(
+			"{} bytes don't fit the {} byte malicious marker",
+			bytes,
+			MALICIOUS_POV.len()
+		))
+	}
+	if bytes > MAX_POV_SIZE as usize {
+		return Err(format!("{} bytes exceed the maximum PoV size of {} bytes", bytes, MAX_POV_SIZE))
+	}
+	Ok(bytes)
+}
+
+/// Replace `n` consecutive candidates, then skip `cooldown` candidates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Burst {
+	pub n: usize,
+	pub cooldown: usize,
+}
+
+/// Parses a burst given as `<n>,<cooldown>`. A burst must replace at least one candidate.
+fn parse_burst(s: &str) -> Result<Burst, String> {
+	let (n, cooldown) = s
+		.split_once(',')
+		.ok_or_else(|| format!("{} is not of the form <n>,<cooldown>", s))?;
+	let n: usize = n.trim().parse().map_err(|e| format!("{}", e))?;
+	let cooldown: usize = cooldown.trim().parse().map_err(|e| format!("{}", e))?;
+	if n == 0 {
+		return Err("a burst must replace at least one candidate".into())
+	}
+	if n.checked_add(cooldown).is_none() {
+		return Err(format!("a burst of {} and a cooldown of {} overflow", n, cooldown))
+	}
+	Ok(Burst { n, cooldown })
+}
+
+/// Decides which candidates are replaced, in bursts of consecutive candidates. Like
+/// `BernoulliGate`, the decision is taken once per candidate and cached.
+struct BurstGate {
+	burst: Burst,
+	/// The number of distinct candidates decided on so far.
+	seen: usize,
+	decisions: HashMap<CandidateHash, bool>,
+}
+
+impl BurstGate {
+	fn new(burst: Burst) -> Self {
+		Self { burst, seen: 0, decisions: HashMap::new() }
+	}
+
+	fn should_act(&mut self, candidate_hash: CandidateHash) -> bool {
+		let Self { burst, seen, decisions } = self;
+		*decisions.entry(candidate_hash).or_insert_with(|| {
+			let act = *seen % (burst.n + burst.cooldown) < burst.n;
+			*seen += 1;
+			act
+		})
+	}
+
+	fn forget(&mut self, candidate_hash: &CandidateHash) {
+		self.decisions.remove(candidate_hash);
+	}
+}
+
+/// Create the malicious `PoV` block data of the given length, the marker followed by
+/// padding derived from `seed`.
+fn malicious_block_data(len: Option<usize>, seed: u64) -> BlockData {
+	let mut data = MALICIOUS_POV.to_vec();
+	if let Some(len) = len {
+		let mut padding = vec![0u8; len.saturating_sub(data.len())];
+		StdRng::seed_from_u64(seed).fill_bytes(&mut padding);
+		data.extend(padding);
+	}
+	BlockData(data)
+}
+
+/// Log of the replacement decisions, appended to a file for post-hoc analysis.
+struct DecisionLog {
+	writer: BufWriter<File>,
+	/// Candidates whose decision is logged already.
+	logged: HashSet<CandidateHash>,
+}
+
+impl DecisionLog {
+	/// Open the log at the given path, appending to it if it exists.
+	fn open(path: &Path) -> std::io::Result<Self> {
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+		Ok(Self { writer: BufWriter::new(file), logged: HashSet::new() })
+	}
+
+	/// Log the decision for the given candidate, unless it is logged already.
+	fn record(&mut self, candidate_hash: CandidateHash, replaced: bool) {
+		if !self.logged.insert(candidate_hash) {
+			return
+		}
+
+		let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+		// Flushed per line, so the log is complete even if the node gets killed.
+		let written =
+			writeln!(self.writer, "{} {:?} {}", timestamp.as_millis(), candidate_hash.0, replaced)
+				.and_then(|_| self.writer.flush());
+		if let Err(err) = written {
+			gum::warn!(target: MALUS_GARBAGE, ?err, ?candidate_hash, "Failed to log decision");
+		}
+	}
+
+	/// Stop tracking the given candidate, once it can't be decided on anymore.
+	fn forget(&mut self, candidate_hash: &CandidateHash) {
+		self.logged.remove(candidate_hash);
+	}
+}
+
 struct Inner {
-	/// Maps malicious candidate hash to original candidate hash.
-	/// It is used to replace outgoing collator protocol seconded messages.
+	/// Maps malicious candidate hash to original candidate hash. Outgoing collator protocol
+	/// seconded messages are not replaced with it yet, see `intercept_outgoing`.
 	map: HashMap<CandidateHash, CandidateHash>,
+	/// Decides which candidates are replaced, unless replacing in bursts.
+	gate: BernoulliGate,
+	/// Decides which candidates are replaced, if replacing in bursts.
+	burst: Option<BurstGate>,
+	/// Log the decisions are appended to, if enabled.
+	decision_log: Option<DecisionLog>,
+	/// Malicious candidates, by the number of their relay parent.
+	noted_at: BTreeMap<BlockNumber, Vec<CandidateHash>>,
+	/// The highest activated leaf observed so far.
+	best_number: BlockNumber,
+	/// Candidates decided on, by the highest leaf observed when deciding. Their relay
+	/// parent is at most that leaf, so they can't be seconded once it is finalized.
+	decided_at: HashMap<CandidateHash, BlockNumber>,
+}
+
+impl Inner {
+	fn new(gate: BernoulliGate) -> Self {
+		Self {
+			map: HashMap::new(),
+			gate,
+			burst: None,
+			decision_log: None,
+			noted_at: BTreeMap::new(),
+			best_number: 0,
+			decided_at: HashMap::new(),
+		}
+	}
+
+	/// Replace candidates in bursts instead of at random, if `burst` is provided.
+	fn with_burst(self, burst: Option<Burst>) -> Self {
+		Self { burst: burst.map(BurstGate::new), ..self }
+	}
+
+	/// Log the decisions, if `decision_log` is provided.
+	fn with_decision_log(self, decision_log: Option<DecisionLog>) -> Self {
+		Self { decision_log, ..self }
+	}
+
+	/// Decide whether the given candidate is replaced.
+	fn should_manipulate(&mut self, candidate_hash: CandidateHash) -> bool {
+		self.decided_at.entry(candidate_hash).or_insert(self.best_number);
+		match self.burst.as_mut() {
+			Some(burst) => burst.should_act(candidate_hash),
+			None => self.gate.should_act(candidate_hash),
+		}
+	}
+
+	/// Log whether the given candidate ended up replaced, if logging decisions.
+	fn log_outcome(&mut self, candidate_hash: CandidateHash, replaced: bool) {
+		if let Some(decision_log) = self.decision_log.as_mut() {
+			decision_log.record(candidate_hash, replaced);
+		}
+	}
+
+	/// Map a malicious candidate to the original one.
+	fn note_mapping(
+		&mut self,
+		malicious: CandidateHash,
+		original: CandidateHash,
+		relay_parent_number: BlockNumber,
+	) {
+		self.map.insert(malicious, original);
+		self.noted_at.entry(relay_parent_number).or_default().push(malicious);
+	}
+
+	/// Note the number of an activated leaf, to track the highest one observed.
+	fn note_leaf(&mut self, number: BlockNumber) {
+		self.best_number = self.best_number.max(number);
+	}
+
+	/// Drop the burst decisions on and the mappings of candidates whose relay parent is
+	/// finalized. The gate observes the signals and forgets its decisions by itself.
+	fn prune_finalized(&mut self, finalized_number: BlockNumber) {
+		let Self { burst, decision_log, decided_at, .. } = self;
+		decided_at.retain(|candidate_hash, number| {
+			if *number > finalized_number {
+				return true
+			}
+			if let Some(burst) = burst.as_mut() {
+				burst.forget(candidate_hash);
+			}
+			if let Some(decision_log) = decision_log.as_mut() {
+				decision_log.forget(candidate_hash);
+			}
+			false
+		});
+
+		let retained = self.noted_at.split_off(&(finalized_number + 1));
+		let pruned = std::mem::replace(&mut self.noted_at, retained);
+		for malicious in pruned.into_values().flatten() {
+			self.map.remove(&malicious);
+		}
+	}
 }
 
 /// Replace outgoing approval messages with disputes.
 #[derive(Clone)]
-struct NoteCandidate<Spawner> {
+struct NoteCandidate {
 	inner: Arc<Mutex<Inner>>,
-	spawner: Spawner,
+	/// The only parachain to replace candidates of, if any.
+	target_para: Option<ParaId>,
+	/// Block data of the `PoV` garbage candidates are created with.
+	malicious_pov: BlockData,
+	/// Whether to only log the candidates that would be replaced.
+	dry_run: bool,
+	/// The largest validator set to erasure code malicious available data for, if capped.
+	max_chunk_validators: Option<usize>,
+	/// Seed to derive the collator key from, if not generated per candidate.
+	collator_seed: Option<u64>,
+	metrics: Metrics,
+}
+
+impl NoteCandidate {
+	/// Whether candidates of the given parachain are subject to replacement.
+	fn is_target(&self, para_id: ParaId) -> bool {
+		self.target_para.map_or(true, |target| target == para_id)
+	}
+
+	/// Decide whether the given candidate is replaced by a garbage candidate. The decision
+	/// is taken once per candidate, so re-seconding a candidate yields the same outcome.
+	fn sample_manipulate(&self, candidate_hash: CandidateHash) -> bool {
+		self.inner.lock().expect("bad lock").should_manipulate(candidate_hash)
+	}
+
+	/// Note that a candidate subject to replacement is seconded unchanged.
+	fn on_candidate_passed(&self, candidate_hash: CandidateHash) {
+		self.metrics.on_candidate_passed();
+		self.inner.lock().expect("bad lock").log_outcome(candidate_hash, false);
+	}
+
+	/// Sign the descriptor of a garbage candidate as its collator.
+	fn sign(&self, descriptor: CandidateDescriptor) -> CandidateDescriptor {
+		match self.collator_seed {
+			Some(seed) => sign_with_seeded_collator(descriptor, seed),
+			None => sign_with_new_collator(descriptor),
+		}
+	}
 }
 
-impl<Sender, Spawner> MessageInterceptor<Sender> for NoteCandidate<Spawner>
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for NoteCandidate
 where
 	Sender: overseer::CandidateBackingSenderTrait + Clone + Send + 'static,
-	Spawner: overseer::gen::Spawner + Clone + 'static,
 {
 	type Message = CandidateBackingMessage;
 
 	/// Intercept incoming `Second` requests from the `collator-protocol` subsystem. We take
-	fn intercept_incoming(
+	async fn intercept_incoming(
 		&self,
 		subsystem_sender: &mut Sender,
 		msg: FromOrchestra<Self::Message>,
 	) -> Option<FromOrchestra<Self::Message>> {
 		match msg {
 			FromOrchestra::Communication {
-				msg: CandidateBackingMessage::Second(relay_parent, candidate, _pov),
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
 			} => {
 				gum::debug!(
-					target: MALUS,
+					target: MALUS_GARBAGE,
 					candidate_hash = ?candidate.hash(),
 					?relay_parent,
 					"Received request to second candidate"
 				);
 
-				let pov = PoV { block_data: BlockData(MALICIOUS_POV.into()) };
-
-				let (sender, receiver) = std::sync::mpsc::channel();
-				let mut new_sender = subsystem_sender.clone();
-				let _candidate = candidate.clone();
-				self.spawner.spawn_blocking(
-					"malus-get-validation-data",
-					Some("malus"),
-					Box::pin(async move {
-						gum::trace!(target: MALUS, "Requesting validators");
-						let n_validators = request_validators(relay_parent, &mut new_sender)
-							.await
-							.await
-							.unwrap()
-							.unwrap()
-							.len();
-						gum::trace!(target: MALUS, "Validators {}", n_validators);
-						match find_validation_data(&mut new_sender, &_candidate.descriptor()).await
-						{
-							Ok(Some((validation_data, validation_code))) => {
-								sender
-									.send((validation_data, validation_code, n_validators))
-									.expect("channel is still open");
-							},
-							_ => {
-								panic!("Unable to fetch validation data");
-							},
-						}
-					}),
-				);
+				if !self.is_target(candidate.descriptor().para_id) {
+					self.metrics.on_candidate_passed();
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
 
-				let (validation_data, validation_code, n_validators) = receiver.recv().unwrap();
+				if !self.sample_manipulate(candidate.hash()) {
+					self.on_candidate_passed(candidate.hash());
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				if self.dry_run {
+					gum::info!(
+						target: MALUS_GARBAGE,
+						candidate_hash = ?candidate.hash(),
+						para_id = ?candidate.descriptor().para_id,
+						?relay_parent,
+						"😈 Dry run, would replace candidate with a garbage candidate"
+					);
+					self.on_candidate_passed(candidate.hash());
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				let (validation_data, validation_code, n_validators) = match fetch_validation_data(
+					subsystem_sender,
+					relay_parent,
+					candidate.descriptor(),
+				)
+				.await
+				{
+					Some(data) => data,
+					None => {
+						gum::warn!(
+							target: MALUS_GARBAGE,
+							candidate_hash = ?candidate.hash(),
+							?relay_parent,
+							"Unable to fetch validation data, not replacing candidate"
+						);
+						self.on_candidate_passed(candidate.hash());
+						return Some(FromOrchestra::Communication {
+							msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+						})
+					},
+				};
+
+				if let Some(max_chunk_validators) =
+					self.max_chunk_validators.filter(|max| n_validators > *max)
+				{
+					gum::warn!(
+						target: MALUS_GARBAGE,
+						candidate_hash = ?candidate.hash(),
+						?n_validators,
+						?max_chunk_validators,
+						"Too many validators to erasure code for, not replacing candidate"
+					);
+					self.on_candidate_passed(candidate.hash());
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
+
+				let malicious_pov = PoV { block_data: self.malicious_pov.clone() };
+				if malicious_pov.encoded_size() > validation_data.max_pov_size as usize {
+					gum::warn!(
+						target: MALUS_GARBAGE,
+						candidate_hash = ?candidate.hash(),
+						max_pov_size = validation_data.max_pov_size,
+						"Malicious PoV exceeds the maximum PoV size, not replacing candidate"
+					);
+					self.on_candidate_passed(candidate.hash());
+					return Some(FromOrchestra::Communication {
+						msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+					})
+				}
 
 				let validation_data_hash = validation_data.hash();
 				let validation_code_hash = validation_code.hash();
 				let validation_data_relay_parent_number = validation_data.relay_parent_number;
 
 				gum::trace!(
-					target: MALUS,
+					target: MALUS_GARBAGE,
 					candidate_hash = ?candidate.hash(),
 					?relay_parent,
 					?n_validators,
@@ -146,55 +527,49 @@ where
 				);
 
 				let malicious_available_data =
-					AvailableData { pov: Arc::new(pov.clone()), validation_data };
+					AvailableData { pov: Arc::new(malicious_pov.clone()), validation_data };
 
-				let pov_hash = pov.hash();
-				let erasure_root = {
-					let chunks =
-						erasure::obtain_chunks_v1(n_validators as usize, &malicious_available_data)
-							.unwrap();
-
-					let branches = erasure::branches(chunks.as_ref());
-					branches.root()
-				};
+				let pov_hash = malicious_pov.hash();
+				let erasure_root =
+					match erasure::obtain_chunks_v1(n_validators, &malicious_available_data) {
+						Ok(chunks) => erasure::branches(chunks.as_ref()).root(),
+						Err(err) => {
+							gum::warn!(
+								target: MALUS_GARBAGE,
+								candidate_hash = ?candidate.hash(),
+								?n_validators,
+								?err,
+								"Erasure coding failed, not replacing candidate"
+							);
+							self.on_candidate_passed(candidate.hash());
+							return Some(FromOrchestra::Communication {
+								msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+							})
+						},
+					};
 
-				let (collator_id, collator_signature) = {
-					use polkadot_primitives::v2::CollatorPair;
-					use sp_core::crypto::Pair;
-
-					let collator_pair = CollatorPair::generate().0;
-					let signature_payload = polkadot_primitives::v2::collator_signature_payload(
-						&relay_parent,
-						&candidate.descriptor().para_id,
-						&validation_data_hash,
-						&pov_hash,
-						&validation_code_hash,
-					);
-
-					(collator_pair.public(), collator_pair.sign(&signature_payload))
-				};
-
-				let malicious_commitments =
-					create_fake_candidate_commitments(&malicious_available_data.validation_data);
+				let malicious_commitments = create_fake_candidate_commitments(
+					&malicious_available_data.validation_data,
+					&FakeCommitments::default(),
+				);
 
 				let malicious_candidate = CandidateReceipt {
-					descriptor: CandidateDescriptor {
+					descriptor: self.sign(CandidateDescriptor {
 						para_id: candidate.descriptor().para_id,
 						relay_parent,
-						collator: collator_id,
 						persisted_validation_data_hash: validation_data_hash,
 						pov_hash,
 						erasure_root,
-						signature: collator_signature,
 						para_head: malicious_commitments.head_data.hash(),
 						validation_code_hash,
-					},
+						..candidate.descriptor().clone()
+					}),
 					commitments_hash: malicious_commitments.hash(),
 				};
 				let malicious_candidate_hash = malicious_candidate.hash();
 
 				gum::debug!(
-					target: MALUS,
+					target: MALUS_GARBAGE,
 					candidate_hash = ?candidate.hash(),
 					?malicious_candidate_hash,
 					"Created malicious candidate"
@@ -202,16 +577,23 @@ where
 
 				// Map malicious candidate to the original one. We need this mapping to send back the correct seconded statement
 				// to the collators.
-				self.inner
-					.lock()
-					.expect("bad lock")
-					.map
-					.insert(malicious_candidate_hash, candidate.hash());
+				self.inner.lock().expect("bad lock").note_mapping(
+					malicious_candidate_hash,
+					candidate.hash(),
+					validation_data_relay_parent_number,
+				);
+
+				self.metrics.on_candidate_manipulated();
 
 				let message = FromOrchestra::Communication {
-					msg: CandidateBackingMessage::Second(relay_parent, malicious_candidate, pov),
+					msg: CandidateBackingMessage::Second(
+						relay_parent,
+						malicious_candidate,
+						malicious_pov,
+					),
 				};
 
+				self.inner.lock().expect("bad lock").log_outcome(candidate.hash(), true);
 				Some(message)
 			},
 			FromOrchestra::Communication { msg } => Some(FromOrchestra::Communication { msg }),
@@ -219,6 +601,21 @@ where
 		}
 	}
 
+	fn observe_signal(&self, signal: &OverseerSignal) {
+		let mut inner = self.inner.lock().expect("bad lock");
+		inner.gate.observe_signal(signal);
+		match signal {
+			OverseerSignal::ActiveLeaves(update) =>
+				if let Some(leaf) = &update.activated {
+					inner.note_leaf(leaf.number);
+				},
+			OverseerSignal::BlockFinalized(_, finalized_number) => {
+				inner.prune_finalized(*finalized_number);
+			},
+			_ => {},
+		}
+	}
+
 	fn intercept_outgoing(
 		&self,
 		msg: overseer::CandidateBackingOutgoingMessages,
@@ -242,7 +639,60 @@ where
 }
 
 /// Garbage candidate implementation wrapper which implements `OverseerGen` glue.
-pub(crate) struct BackGarbageCandidateWrapper;
+pub(crate) struct BackGarbageCandidateWrapper {
+	/// Percentage of candidates to replace.
+	percentage: f64,
+	/// Seed for the replacement decisions.
+	seed: Option<u64>,
+	/// The only parachain to replace candidates of, if any.
+	target_para: Option<ParaId>,
+	/// File to log the decisions to, if any.
+	decision_log: Option<PathBuf>,
+	/// Size of the malicious `PoV` block data, if padded.
+	malicious_pov_bytes: Option<usize>,
+	/// Whether to only log the candidates that would be replaced.
+	dry_run: bool,
+	/// The largest validator set to erasure code malicious available data for, if capped.
+	max_chunk_validators: Option<usize>,
+	/// Whether to derive the collator key from the seed.
+	deterministic_collator: bool,
+	/// Bursts to replace candidates in, if not at random.
+	burst: Option<Burst>,
+	/// Fake validation config.
+	fake_validation: FakeCandidateValidation,
+	/// Error returned for candidates faked to be invalid.
+	fake_validation_error: FakeCandidateValidationError,
+	/// Percentage of candidates whose validation is faked.
+	fake_validation_percentage: u8,
+	/// Leaves per honest and malicious phase, malicious throughout if not set.
+	period: Option<usize>,
+}
+
+impl BackGarbageCandidateWrapper {
+	pub fn new(opts: SuggestGarbageCandidateOptions) -> Self {
+		Self {
+			percentage: f64::from(opts.percentage),
+			seed: opts.seed,
+			target_para: opts.target_para.map(ParaId::from),
+			decision_log: opts.decision_log,
+			malicious_pov_bytes: opts.malicious_pov_bytes,
+			dry_run: opts.dry_run,
+			max_chunk_validators: opts.max_chunk_validators,
+			deterministic_collator: opts.deterministic_collator,
+			burst: opts.burst,
+			fake_validation: opts.fake_validation,
+			fake_validation_error: opts.fake_validation_error,
+			fake_validation_percentage: opts.fake_validation_percentage,
+			period: None,
+		}
+	}
+
+	/// Alternate between honest and malicious phases of `period` activated leaves each.
+	pub fn with_period(mut self, period: usize) -> Self {
+		self.period = Some(period);
+		self
+	}
+}
 
 impl OverseerGen for BackGarbageCandidateWrapper {
 	fn generate<'a, Spawner, RuntimeClient>(
@@ -255,23 +705,540 @@ impl OverseerGen for BackGarbageCandidateWrapper {
 		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
 		Spawner: 'static + SpawnNamed + Clone + Unpin,
 	{
-		let inner = Inner { map: std::collections::HashMap::new() };
-		let inner_mut = Arc::new(Mutex::new(inner));
-		let note_candidate =
-			NoteCandidate { inner: inner_mut.clone(), spawner: SpawnGlue(args.spawner.clone()) };
+		match self.burst {
+			Some(Burst { n, cooldown }) => gum::info!(
+				target: MALUS_GARBAGE,
+				"😈 Started Malus node behaving maliciously for {} consecutive candidates, then skipping {}.",
+				n,
+				cooldown,
+			),
+			None => gum::info!(
+				target: MALUS_GARBAGE,
+				"😈 Started Malus node with a {:?} percent chance of behaving maliciously for a given candidate.",
+				self.percentage,
+			),
+		}
 
-		let validation_filter = ReplaceValidationResult::new(
-			FakeCandidateValidation::BackingAndApprovalValid,
-			FakeCandidateValidationError::InvalidOutputs,
-			SpawnGlue(args.spawner.clone()),
+		let percentage = Percentage::new(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the replacement percentage: {}", e))
+		})?;
+		let decision_log = self
+			.decision_log
+			.as_deref()
+			.map(|path| {
+				DecisionLog::open(path).map_err(|e| {
+					SubsystemError::Context(format!(
+						"open the decision log {}: {}",
+						path.display(),
+						e
+					))
+				})
+			})
+			.transpose()?;
+		let inner = Inner::new(BernoulliGate::new(percentage, self.seed))
+			.with_burst(self.burst)
+			.with_decision_log(decision_log);
+		let inner_mut = Arc::new(Mutex::new(inner));
+		let note_candidate = Intermittent::new(
+			NoteCandidate {
+				inner: inner_mut.clone(),
+				target_para: self.target_para,
+				malicious_pov: malicious_block_data(
+					self.malicious_pov_bytes,
+					self.seed.unwrap_or_default(),
+				),
+				dry_run: self.dry_run,
+				max_chunk_validators: self.max_chunk_validators,
+				collator_seed: self.seed.filter(|_| self.deterministic_collator),
+				metrics: Metrics::register(args.registry)?,
+			},
+			self.period,
 		);
 
-		prepared_overseer_builder(args)?
-			.replace_candidate_backing(move |cb| InterceptedSubsystem::new(cb, note_candidate))
-			.replace_candidate_validation(move |cb| {
-				InterceptedSubsystem::new(cb, validation_filter)
-			})
+		let fake_validation_percentage = Percentage::try_from(self.fake_validation_percentage)
+			.map_err(|e| {
+				SubsystemError::Context(format!("configure the fake validation percentage: {}", e))
+			})?;
+		let validation_filter =
+			ReplaceValidationResult::new(self.fake_validation, self.fake_validation_error)
+				.with_percentage(fake_validation_percentage, self.seed);
+
+		ReplacementGuard::new(prepared_overseer_builder(args)?)
+			.replace(ReplacedSubsystem::CandidateBacking, |b| {
+				b.replace_candidate_backing(move |cb| {
+					InterceptedSubsystem::new("garbage", DEFAULT_SPAWN_PREFIX, cb, note_candidate)
+				})
+			})?
+			.replace(ReplacedSubsystem::CandidateValidation, |b| {
+				b.replace_candidate_validation(move |cv| {
+					InterceptedSubsystem::new(
+						"garbage",
+						DEFAULT_SPAWN_PREFIX,
+						cv,
+						validation_filter,
+					)
+				})
+			})?
+			.into_inner()
 			.build_with_connector(connector)
 			.map_err(|e| e.into())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{activate_leaf, with_mock_sender, MockRuntime};
+	use futures::FutureExt;
+	use polkadot_node_subsystem_test_helpers::{sender_receiver, TestSubsystemSender};
+	use polkadot_node_subsystem_util::metrics::prometheus;
+	use polkadot_primitives_test_helpers::dummy_candidate_receipt;
+
+	fn gate(percentage: f64, seed: Option<u64>) -> BernoulliGate {
+		BernoulliGate::new(Percentage::new(percentage).unwrap(), seed)
+	}
+
+	fn note_candidate(percentage: f64, seed: Option<u64>) -> NoteCandidate {
+		NoteCandidate {
+			inner: Arc::new(Mutex::new(Inner::new(gate(percentage, seed)))),
+			target_para: None,
+			malicious_pov: malicious_block_data(None, 0),
+			dry_run: false,
+			max_chunk_validators: None,
+			collator_seed: None,
+			metrics: Metrics::default(),
+		}
+	}
+
+	fn second(para_id: ParaId) -> FromOrchestra<CandidateBackingMessage> {
+		let mut candidate = dummy_candidate_receipt(Hash::repeat_byte(1));
+		candidate.descriptor.para_id = para_id;
+		let pov = PoV { block_data: BlockData(vec![1, 2, 3]) };
+
+		FromOrchestra::Communication {
+			msg: CandidateBackingMessage::Second(Hash::repeat_byte(1), candidate, pov),
+		}
+	}
+
+	#[test]
+	fn same_seed_yields_same_decisions() {
+		let a = note_candidate(50.0, Some(42));
+		let b = note_candidate(50.0, Some(42));
+
+		let decisions_a: Vec<_> = (0..64)
+			.map(|n| a.sample_manipulate(CandidateHash(Hash::repeat_byte(n))))
+			.collect();
+		let decisions_b: Vec<_> = (0..64)
+			.map(|n| b.sample_manipulate(CandidateHash(Hash::repeat_byte(n))))
+			.collect();
+
+		assert_eq!(decisions_a, decisions_b);
+		// with 64 draws at 50%, both outcomes occur.
+		assert!(decisions_a.contains(&true) && decisions_a.contains(&false));
+	}
+
+	#[test]
+	fn bursts_alternate_with_cooldowns() {
+		let inner = Inner::new(gate(0.0, None)).with_burst(Some(Burst { n: 2, cooldown: 3 }));
+		let interceptor =
+			NoteCandidate { inner: Arc::new(Mutex::new(inner)), ..note_candidate(0.0, None) };
+
+		let decisions: Vec<_> = (0..12)
+			.map(|n| interceptor.sample_manipulate(CandidateHash(Hash::repeat_byte(n))))
+			.collect();
+		assert_eq!(
+			decisions,
+			[true, true, false, false, false, true, true, false, false, false, true, true]
+		);
+
+		// candidates seen again keep their decision and don't advance the cycle.
+		assert!(interceptor.sample_manipulate(CandidateHash(Hash::repeat_byte(0))));
+		assert!(!interceptor.sample_manipulate(CandidateHash(Hash::repeat_byte(2))));
+		assert!(!interceptor.sample_manipulate(CandidateHash(Hash::repeat_byte(12))));
+	}
+
+	#[test]
+	fn decisions_are_logged_once_per_candidate() {
+		let path = std::env::temp_dir().join(format!(
+			"malus-decision-log-{}-{}",
+			std::process::id(),
+			line!()
+		));
+		let _ = std::fs::remove_file(&path);
+
+		let inner = Inner::new(gate(0.0, None))
+			.with_burst(Some(Burst { n: 1, cooldown: 2 }))
+			.with_decision_log(Some(DecisionLog::open(&path).unwrap()));
+		let interceptor =
+			NoteCandidate { inner: Arc::new(Mutex::new(inner)), ..note_candidate(0.0, None) };
+
+		let runtime = MockRuntime::new(4);
+		let candidates: Vec<_> = (0..4)
+			.map(|n| CandidateReceipt {
+				commitments_hash: Hash::repeat_byte(n),
+				..runtime.candidate(Hash::repeat_byte(1))
+			})
+			.collect();
+		for n in [0, 1, 2, 0] {
+			second_with(&interceptor, runtime.clone(), candidates[n].clone());
+		}
+		// sampled for replacement, but seconded unchanged as the validation data is missing.
+		let unavailable = MockRuntime { validation_data: None, ..runtime };
+		second_with(&interceptor, unavailable, candidates[3].clone());
+
+		let log = std::fs::read_to_string(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		let entries: Vec<_> = log
+			.lines()
+			.map(|line| {
+				let fields: Vec<_> = line.split(' ').collect();
+				assert_eq!(fields.len(), 3, "{}", line);
+				assert!(fields[0].parse::<u128>().is_ok(), "{}", line);
+				(fields[1].to_owned(), fields[2].to_owned())
+			})
+			.collect();
+		let expected: Vec<_> = [(0, true), (1, false), (2, false), (3, false)]
+			.iter()
+			.map(|&(n, replaced)| (format!("{:?}", candidates[n].hash().0), replaced.to_string()))
+			.collect();
+		assert_eq!(entries, expected);
+	}
+
+	#[test]
+	fn burst_parses() {
+		assert_eq!(parse_burst("3,5"), Ok(Burst { n: 3, cooldown: 5 }));
+		assert_eq!(parse_burst("1,0"), Ok(Burst { n: 1, cooldown: 0 }));
+		assert!(parse_burst("0,5").is_err());
+		assert!(parse_burst("3").is_err());
+		assert!(parse_burst("3,x").is_err());
+		assert!(parse_burst(&format!("{},1", usize::MAX)).is_err());
+	}
+
+	#[test]
+	fn sample_manipulate_respects_bounds() {
+		let never = note_candidate(0.0, Some(0));
+		let always = note_candidate(100.0, Some(0));
+
+		assert!((0..100).all(|n| !never.sample_manipulate(CandidateHash(Hash::repeat_byte(n)))));
+		assert!((0..100).all(|n| always.sample_manipulate(CandidateHash(Hash::repeat_byte(n)))));
+	}
+
+	#[test]
+	fn only_target_para_is_manipulated() {
+		let interceptor =
+			NoteCandidate { target_para: Some(ParaId::from(2)), ..note_candidate(100.0, None) };
+		let (mut sender, mut rx) = sender_receiver();
+
+		// Candidates of other paras are passed through untouched.
+		assert_matches::assert_matches!(
+			interceptor.intercept_incoming(&mut sender, second(ParaId::from(1))).now_or_never(),
+			Some(Some(FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(_, candidate, pov),
+			})) => {
+				assert_eq!(candidate.descriptor.para_id, ParaId::from(1));
+				assert_eq!(pov.block_data.0, vec![1, 2, 3]);
+			}
+		);
+		assert!(rx.try_next().is_err());
+
+		// Candidates of the targeted para are manipulated, which starts by fetching validators.
+		let fut = interceptor.intercept_incoming(&mut sender, second(ParaId::from(2)));
+		futures::pin_mut!(fut);
+		assert!(fut.as_mut().now_or_never().is_none());
+		assert_matches::assert_matches!(
+			rx.try_next(),
+			Ok(Some(AllMessages::RuntimeApi(RuntimeApiMessage::Request(
+				_,
+				RuntimeApiRequest::Validators(_),
+			))))
+		);
+	}
+
+	#[test]
+	fn dry_run_forwards_candidate_unchanged() {
+		let interceptor = NoteCandidate { dry_run: true, ..note_candidate(100.0, None) };
+		let (mut sender, mut rx) = sender_receiver();
+
+		let (relay_parent, candidate, pov) = match second(ParaId::from(1)) {
+			FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(relay_parent, candidate, pov),
+			} => (relay_parent, candidate, pov),
+			_ => unreachable!(),
+		};
+		assert!(interceptor.sample_manipulate(candidate.hash()));
+
+		assert_matches::assert_matches!(
+			interceptor.intercept_incoming(&mut sender, second(ParaId::from(1))).now_or_never(),
+			Some(Some(FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(r, c, p),
+			})) => {
+				assert_eq!(r, relay_parent);
+				assert_eq!(c, candidate);
+				assert_eq!(p, pov);
+			}
+		);
+		// Nothing is fetched for a candidate that is not replaced.
+		assert!(rx.try_next().is_err());
+		assert!(interceptor.inner.lock().unwrap().map.is_empty());
+	}
+
+	#[test]
+	fn finalization_prunes_mapping() {
+		let interceptor = note_candidate(100.0, None);
+
+		{
+			let mut inner = interceptor.inner.lock().unwrap();
+			for n in 1..=4u8 {
+				inner.note_mapping(
+					CandidateHash(Hash::repeat_byte(n)),
+					CandidateHash(Hash::repeat_byte(n + 10)),
+					BlockNumber::from(n),
+				);
+			}
+		}
+
+		MessageInterceptor::<TestSubsystemSender>::observe_signal(
+			&interceptor,
+			&OverseerSignal::BlockFinalized(Hash::repeat_byte(2), 2),
+		);
+
+		let inner = interceptor.inner.lock().unwrap();
+		for n in 1..=2 {
+			assert_eq!(inner.map.get(&CandidateHash(Hash::repeat_byte(n))), None);
+		}
+		for n in 3..=4 {
+			assert_eq!(
+				inner.map.get(&CandidateHash(Hash::repeat_byte(n))),
+				Some(&CandidateHash(Hash::repeat_byte(n + 10)))
+			);
+		}
+	}
+
+	#[test]
+	fn finalization_prunes_decisions() {
+		let path = std::env::temp_dir().join(format!(
+			"malus-decision-log-{}-{}",
+			std::process::id(),
+			line!()
+		));
+		let _ = std::fs::remove_file(&path);
+
+		let inner = Inner::new(gate(0.0, None))
+			.with_burst(Some(Burst { n: 1, cooldown: 0 }))
+			.with_decision_log(Some(DecisionLog::open(&path).unwrap()));
+		let interceptor =
+			NoteCandidate { inner: Arc::new(Mutex::new(inner)), ..note_candidate(0.0, None) };
+		let observe = |signal| {
+			MessageInterceptor::<TestSubsystemSender>::observe_signal(&interceptor, &signal)
+		};
+
+		let early = CandidateHash(Hash::repeat_byte(1));
+		let late = CandidateHash(Hash::repeat_byte(2));
+		observe(activate_leaf(1));
+		interceptor.sample_manipulate(early);
+		interceptor.on_candidate_passed(early);
+		observe(activate_leaf(3));
+		interceptor.sample_manipulate(late);
+		interceptor.on_candidate_passed(late);
+		observe(OverseerSignal::BlockFinalized(Hash::repeat_byte(2), 2));
+
+		let inner = interceptor.inner.lock().unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let burst = inner.burst.as_ref().unwrap();
+		let decision_log = inner.decision_log.as_ref().unwrap();
+
+		assert!(!inner.decided_at.contains_key(&early));
+		assert!(!burst.decisions.contains_key(&early));
+		assert!(!decision_log.logged.contains(&early));
+
+		assert_eq!(inner.decided_at.get(&late), Some(&3));
+		assert!(burst.decisions.contains_key(&late));
+		assert!(decision_log.logged.contains(&late));
+	}
+
+	#[test]
+	fn malicious_block_data_has_requested_length() {
+		assert_eq!(malicious_block_data(None, 0).0, MALICIOUS_POV);
+
+		for len in [MALICIOUS_POV.len(), 1024, 1024 * 1024] {
+			let block_data = malicious_block_data(Some(len), 7);
+			assert_eq!(block_data.0.len(), len);
+			assert!(crate::shared::is_malicious_pov(&block_data.0));
+			assert_eq!(block_data, malicious_block_data(Some(len), 7));
+		}
+		assert_ne!(malicious_block_data(Some(64), 1), malicious_block_data(Some(64), 2));
+	}
+
+	#[test]
+	fn malicious_pov_bytes_are_bounded() {
+		assert_eq!(parse_malicious_pov_bytes("1024"), Ok(1024));
+		assert!(parse_malicious_pov_bytes("1").is_err());
+		assert!(parse_malicious_pov_bytes(&(MAX_POV_SIZE as usize + 1).to_string()).is_err());
+	}
+
+	#[test]
+	fn reseconded_candidate_keeps_its_decision() {
+		let interceptor = note_candidate(50.0, None);
+		let candidates: Vec<_> = (0..64).map(|n| CandidateHash(Hash::repeat_byte(n))).collect();
+
+		let first: Vec<_> = candidates.iter().map(|c| interceptor.sample_manipulate(*c)).collect();
+		for _ in 0..4 {
+			let again: Vec<_> =
+				candidates.iter().map(|c| interceptor.sample_manipulate(*c)).collect();
+			assert_eq!(again, first);
+		}
+		// with 64 candidates at 50%, both outcomes occur.
+		assert!(first.contains(&true) && first.contains(&false));
+	}
+
+	/// Second the candidate through the interceptor, with runtime requests answered by
+	/// `runtime`.
+	fn second_with(
+		interceptor: &NoteCandidate,
+		runtime: MockRuntime,
+		candidate: CandidateReceipt,
+	) -> (CandidateReceipt, PoV) {
+		let interceptor = interceptor.clone();
+		let second = FromOrchestra::Communication {
+			msg: CandidateBackingMessage::Second(
+				Hash::repeat_byte(1),
+				candidate,
+				PoV { block_data: BlockData(vec![1, 2, 3]) },
+			),
+		};
+
+		let forwarded = with_mock_sender(runtime, |mut sender| async move {
+			interceptor.intercept_incoming(&mut sender, second).await
+		});
+		match forwarded {
+			Some(FromOrchestra::Communication {
+				msg: CandidateBackingMessage::Second(_, candidate, pov),
+			}) => (candidate, pov),
+			msg => panic!("Unexpected message: {:?}", msg),
+		}
+	}
+
+	#[test]
+	fn unavailable_validation_data_passes_candidate_through() {
+		let interceptor = note_candidate(100.0, None);
+		// like a runtime that pruned the relay parent's state.
+		let runtime = MockRuntime { validation_data: None, ..MockRuntime::new(0) };
+		let candidate = runtime.candidate(Hash::repeat_byte(1));
+
+		let (forwarded, pov) = second_with(&interceptor, runtime, candidate.clone());
+		assert_eq!(forwarded, candidate);
+		assert_eq!(pov.block_data.0, vec![1, 2, 3]);
+	}
+
+	#[test]
+	fn too_many_validators_pass_candidate_through() {
+		let interceptor =
+			NoteCandidate { max_chunk_validators: Some(4), ..note_candidate(100.0, None) };
+		let runtime = MockRuntime::new(5);
+		let candidate = runtime.candidate(Hash::repeat_byte(1));
+
+		let (forwarded, pov) = second_with(&interceptor, runtime, candidate.clone());
+		assert_eq!(forwarded, candidate);
+		assert_eq!(pov.block_data.0, vec![1, 2, 3]);
+		assert!(interceptor.inner.lock().unwrap().map.is_empty());
+	}
+
+	#[test]
+	fn empty_validator_set_passes_candidate_through() {
+		let interceptor = note_candidate(100.0, None);
+		let runtime = MockRuntime::new(0);
+		let candidate = runtime.candidate(Hash::repeat_byte(1));
+
+		let (forwarded, pov) = second_with(&interceptor, runtime, candidate.clone());
+		assert_eq!(forwarded, candidate);
+		assert_eq!(pov.block_data.0, vec![1, 2, 3]);
+		assert!(interceptor.inner.lock().unwrap().map.is_empty());
+	}
+
+	#[test]
+	fn single_validator_passes_candidate_through() {
+		let interceptor = note_candidate(100.0, None);
+		// erasure coding needs at least two validators.
+		let runtime = MockRuntime::new(1);
+		let candidate = runtime.candidate(Hash::repeat_byte(1));
+
+		let (forwarded, pov) = second_with(&interceptor, runtime, candidate.clone());
+		assert_eq!(forwarded, candidate);
+		assert_eq!(pov.block_data.0, vec![1, 2, 3]);
+		assert!(interceptor.inner.lock().unwrap().map.is_empty());
+	}
+
+	#[test]
+	fn manipulated_candidate_is_mapped_to_original() {
+		let interceptor = note_candidate(100.0, None);
+		let runtime = MockRuntime::new(4);
+		let candidate = runtime.candidate(Hash::repeat_byte(1));
+		let validation_data = runtime.validation_data.clone().unwrap();
+
+		let (malicious, pov) = second_with(&interceptor, runtime, candidate.clone());
+		assert_ne!(malicious.hash(), candidate.hash());
+		assert_eq!(pov.block_data, interceptor.malicious_pov);
+		assert_eq!(malicious.descriptor.pov_hash, pov.hash());
+		assert_eq!(malicious.descriptor.persisted_validation_data_hash, validation_data.hash());
+		assert!(malicious.descriptor.check_collator_signature().is_ok());
+
+		let inner = interceptor.inner.lock().unwrap();
+		assert_eq!(inner.map.get(&malicious.hash()), Some(&candidate.hash()));
+		assert_eq!(inner.map.len(), 1);
+	}
+
+	/// Second three candidates through an interceptor acting on `percentage` of them, and
+	/// return the values of the passed and manipulated candidates counters.
+	fn metered_outcomes(percentage: f64) -> (Option<f64>, Option<f64>) {
+		let registry = prometheus::Registry::new();
+		let interceptor = NoteCandidate {
+			metrics: Metrics::try_register(&registry).unwrap(),
+			..note_candidate(percentage, None)
+		};
+
+		for n in 1..=3 {
+			let runtime = MockRuntime::new(4);
+			let candidate = runtime.candidate(Hash::repeat_byte(n));
+			second_with(&interceptor, runtime, candidate);
+		}
+
+		let gathered = registry.gather();
+		let value = |name: &str| {
+			gathered
+				.iter()
+				.find(|family| family.get_name() == name)
+				.map(|family| family.get_metric()[0].get_counter().get_value())
+		};
+		(value("malus_candidates_passed_total"), value("malus_candidates_manipulated_total"))
+	}
+
+	#[test]
+	fn candidate_outcomes_are_metered() {
+		assert_eq!(metered_outcomes(0.0), (Some(3.0), Some(0.0)));
+		assert_eq!(metered_outcomes(100.0), (Some(0.0), Some(3.0)));
+	}
+
+	/// Replace the same candidate with a fresh interceptor, signing with the collator key
+	/// derived from `collator_seed`, if any.
+	fn malicious_candidate_hash(collator_seed: Option<u64>) -> CandidateHash {
+		let interceptor = NoteCandidate { collator_seed, ..note_candidate(100.0, Some(1)) };
+		let runtime = MockRuntime::new(4);
+		let candidate = runtime.candidate(Hash::repeat_byte(1));
+
+		let (malicious, _) = second_with(&interceptor, runtime, candidate);
+		assert!(malicious.descriptor.check_collator_signature().is_ok());
+		malicious.hash()
+	}
+
+	#[test]
+	fn seeded_collator_yields_reproducible_candidate_hash() {
+		assert_eq!(malicious_candidate_hash(Some(7)), malicious_candidate_hash(Some(7)));
+		assert_ne!(malicious_candidate_hash(Some(7)), malicious_candidate_hash(Some(8)));
+	}
+
+	#[test]
+	fn collator_is_generated_per_candidate_by_default() {
+		assert_ne!(malicious_candidate_hash(None), malicious_candidate_hash(None));
+	}
+}
@@ -0,0 +1,262 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that refuses to serve its availability chunks for a
+//! configurable fraction of candidates. Chunks are still stored and reported
+//! as available, so the node keeps signing bitfields honestly, but approval
+//! checkers trying to recover the data from it will not get a response. This
+//! should lead to no-shows in approval voting.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{OverseerSignal, SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::CandidateHash;
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{BernoulliGate, DEFAULT_SPAWN_PREFIX, MALUS_WITHHOLD_CHUNKS},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct WithholdChunksOptions {
+	/// Determines the percentage of candidates for which chunks are withheld.
+	/// Defaults to withholding the chunks of all candidates.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Refuse to serve chunks of a subset of candidates.
+#[derive(Clone)]
+struct WithholdChunks {
+	/// Decides once per candidate, such that a candidate is either
+	/// always or never withheld.
+	gate: Arc<Mutex<BernoulliGate>>,
+}
+
+impl WithholdChunks {
+	fn new(percentage: Percentage) -> Self {
+		Self { gate: Arc::new(Mutex::new(BernoulliGate::new(percentage, None))) }
+	}
+
+	fn should_withhold(&self, candidate_hash: CandidateHash) -> bool {
+		self.gate.lock().expect("bad lock").should_act(candidate_hash)
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for WithholdChunks
+where
+	Sender: overseer::AvailabilityStoreSenderTrait + Clone + Send + 'static,
+{
+	type Message = AvailabilityStoreMessage;
+
+	/// Intercept chunk queries and answer them with "not available" for withheld candidates.
+	async fn intercept_incoming(
+		&self,
+		_sender: &mut Sender,
+		msg: FromOrchestra<Self::Message>,
+	) -> Option<FromOrchestra<Self::Message>> {
+		match msg {
+			FromOrchestra::Communication {
+				msg: AvailabilityStoreMessage::QueryChunk(candidate_hash, validator_index, tx),
+			} if self.should_withhold(candidate_hash) => {
+				gum::debug!(
+					target: MALUS_WITHHOLD_CHUNKS,
+					?candidate_hash,
+					?validator_index,
+					"😈 Withholding chunk"
+				);
+				let _ = tx.send(None);
+				None
+			},
+			FromOrchestra::Communication {
+				msg: AvailabilityStoreMessage::QueryAllChunks(candidate_hash, tx),
+			} if self.should_withhold(candidate_hash) => {
+				gum::debug!(target: MALUS_WITHHOLD_CHUNKS, ?candidate_hash, "😈 Withholding all chunks");
+				let _ = tx.send(Vec::new());
+				None
+			},
+			msg => Some(msg),
+		}
+	}
+
+	fn observe_signal(&self, signal: &OverseerSignal) {
+		self.gate.lock().expect("bad lock").observe_signal(signal);
+	}
+}
+
+/// Generates an overseer that replaces the availability store subsystem with our
+/// malicious variant.
+pub(crate) struct WithholdAvailabilityChunks {
+	/// Percentage of candidates to withhold chunks for.
+	percentage: u8,
+}
+
+impl WithholdAvailabilityChunks {
+	pub fn new(opts: WithholdChunksOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for WithholdAvailabilityChunks {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_WITHHOLD_CHUNKS,
+			"😈 Started Malus node withholding chunks of {:?} percent of candidates.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the withheld chunks percentage: {}", e))
+		})?;
+		let withhold_chunks = WithholdChunks::new(percentage);
+
+		prepared_overseer_builder(args)?
+			.replace_availability_store(move |av_store| {
+				InterceptedSubsystem::new(
+					"withhold-chunks",
+					DEFAULT_SPAWN_PREFIX,
+					av_store,
+					withhold_chunks,
+				)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::AlwaysSupportsParachains;
+	use futures::{channel::oneshot, executor::block_on, FutureExt};
+	use polkadot_node_primitives::ErasureChunk;
+	use polkadot_node_subsystem::overseer::{dummy::dummy_overseer_builder, Handle};
+	use polkadot_node_subsystem_test_helpers::sender_receiver;
+	use polkadot_primitives::v2::{Hash, ValidatorIndex};
+	use sp_core::testing::TaskExecutor;
+
+	fn withhold(percentage: f64) -> WithholdChunks {
+		WithholdChunks::new(Percentage::new(percentage).unwrap())
+	}
+
+	fn query_chunk(
+		candidate_hash: CandidateHash,
+	) -> (FromOrchestra<AvailabilityStoreMessage>, oneshot::Receiver<Option<ErasureChunk>>) {
+		let (tx, rx) = oneshot::channel();
+		let msg = FromOrchestra::Communication {
+			msg: AvailabilityStoreMessage::QueryChunk(candidate_hash, ValidatorIndex(0), tx),
+		};
+		(msg, rx)
+	}
+
+	#[test]
+	fn withholds_all_chunks() {
+		let interceptor = withhold(100.0);
+		let (mut sender, _) = sender_receiver();
+
+		let (msg, mut rx) = query_chunk(CandidateHash(Hash::repeat_byte(1)));
+		assert!(block_on(interceptor.intercept_incoming(&mut sender, msg)).is_none());
+		assert_matches::assert_matches!(rx.try_recv(), Ok(Some(None)));
+	}
+
+	#[test]
+	fn serves_all_chunks() {
+		let interceptor = withhold(0.0);
+		let (mut sender, _) = sender_receiver();
+
+		let (msg, _rx) = query_chunk(CandidateHash(Hash::repeat_byte(1)));
+		assert_matches::assert_matches!(
+			block_on(interceptor.intercept_incoming(&mut sender, msg)),
+			Some(FromOrchestra::Communication { msg: AvailabilityStoreMessage::QueryChunk(..) })
+		);
+	}
+
+	#[test]
+	fn decision_is_per_candidate() {
+		let interceptor = withhold(50.0);
+
+		for n in 0..32 {
+			let candidate_hash = CandidateHash(Hash::repeat_byte(n));
+			let decision = interceptor.should_withhold(candidate_hash);
+			for _ in 0..8 {
+				assert_eq!(interceptor.should_withhold(candidate_hash), decision);
+			}
+		}
+	}
+
+	#[test]
+	fn overseer_with_withholding_availability_store_builds() {
+		let spawner = TaskExecutor::new();
+		let withhold_chunks = withhold(100.0);
+		let (overseer, handle) =
+			dummy_overseer_builder(spawner.clone(), AlwaysSupportsParachains, None)
+				.unwrap()
+				.replace_availability_store(move |av_store| {
+					InterceptedSubsystem::new(
+						"withhold-chunks",
+						DEFAULT_SPAWN_PREFIX,
+						av_store,
+						withhold_chunks,
+					)
+				})
+				.build()
+				.unwrap();
+		let mut handle = Handle::new(handle);
+		spawner.spawn("overseer", None, overseer.run().map(|_| ()).boxed());
+
+		// The query is answered by the interceptor, the dummy store would drop it.
+		let (tx, rx) = oneshot::channel();
+		block_on(handle.send_msg_anon(AvailabilityStoreMessage::QueryChunk(
+			CandidateHash(Hash::repeat_byte(1)),
+			ValidatorIndex(0),
+			tx,
+		)));
+		assert_matches::assert_matches!(block_on(rx), Ok(None));
+
+		block_on(handle.stop());
+	}
+}
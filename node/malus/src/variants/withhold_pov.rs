@@ -0,0 +1,209 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A malicious node that backs candidates honestly, but refuses to serve their `PoV`
+//! to peers for a configurable fraction of candidates. `PoV` fetching requests are
+//! answered as if the `PoV` was unknown, while chunks are still served, which
+//! reproduces stalls where backing succeeded but the data can't be fetched.
+//!
+//! Attention: For usage with `zombienet` only!
+
+#![allow(missing_docs)]
+
+use polkadot_cli::{
+	prepared_overseer_builder,
+	service::{
+		AuthorityDiscoveryApi, AuxStore, BabeApi, Block, Error, HeaderBackend, Overseer,
+		OverseerConnector, OverseerGen, OverseerGenArgs, OverseerHandle, ParachainHost,
+		ProvideRuntimeApi,
+	},
+	RunCmd,
+};
+use polkadot_node_subsystem::{OverseerSignal, SpawnGlue, SubsystemError};
+use polkadot_primitives::v2::CandidateHash;
+use sp_core::traits::SpawnNamed;
+
+// Filter wrapping related types.
+use crate::{
+	interceptor::*,
+	shared::{BernoulliGate, DEFAULT_SPAWN_PREFIX, MALUS_WITHHOLD_POV},
+	variants::{parse_percentage, Percentage},
+};
+
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug, clap::Parser)]
+#[clap(rename_all = "kebab-case")]
+#[allow(missing_docs)]
+pub struct WithholdPoVOptions {
+	/// Determines the percentage of candidates whose `PoV` is withheld.
+	/// Defaults to withholding the `PoV` of all candidates.
+	#[clap(short, long, parse(try_from_str = parse_percentage), default_value_t = 100)]
+	pub percentage: u8,
+
+	#[clap(flatten)]
+	pub cmd: RunCmd,
+}
+
+/// Refuse to serve the `PoV` of a subset of candidates.
+#[derive(Clone)]
+struct WithholdPoV {
+	gate: Arc<Mutex<BernoulliGate>>,
+}
+
+impl WithholdPoV {
+	fn new(percentage: Percentage) -> Self {
+		Self { gate: Arc::new(Mutex::new(BernoulliGate::new(percentage, None))) }
+	}
+
+	fn should_withhold(&self, candidate_hash: CandidateHash) -> bool {
+		self.gate.lock().expect("bad lock").should_act(candidate_hash)
+	}
+}
+
+#[async_trait::async_trait]
+impl<Sender> MessageInterceptor<Sender> for WithholdPoV
+where
+	Sender: overseer::AvailabilityDistributionSenderTrait + Clone + Send + 'static,
+{
+	type Message = AvailabilityDistributionMessage;
+
+	fn observe_signal(&self, signal: &OverseerSignal) {
+		self.gate.lock().expect("bad lock").observe_signal(signal);
+	}
+
+	/// `PoV` requests of peers are answered with the available data queried from the
+	/// availability store, so withheld candidates are reported as unavailable there.
+	fn intercept_outgoing(
+		&self,
+		msg: overseer::AvailabilityDistributionOutgoingMessages,
+	) -> Option<overseer::AvailabilityDistributionOutgoingMessages> {
+		match msg {
+			overseer::AvailabilityDistributionOutgoingMessages::AvailabilityStoreMessage(
+				AvailabilityStoreMessage::QueryAvailableData(candidate_hash, tx),
+			) if self.should_withhold(candidate_hash) => {
+				gum::debug!(target: MALUS_WITHHOLD_POV, ?candidate_hash, "😈 Withholding PoV");
+				let _ = tx.send(None);
+				None
+			},
+			msg => Some(msg),
+		}
+	}
+}
+
+/// Generates an overseer that withholds the `PoV` of backed candidates.
+pub(crate) struct WithholdPoVs {
+	percentage: u8,
+}
+
+impl WithholdPoVs {
+	pub fn new(opts: WithholdPoVOptions) -> Self {
+		Self { percentage: opts.percentage }
+	}
+}
+
+impl OverseerGen for WithholdPoVs {
+	fn generate<'a, Spawner, RuntimeClient>(
+		&self,
+		connector: OverseerConnector,
+		args: OverseerGenArgs<'a, Spawner, RuntimeClient>,
+	) -> Result<(Overseer<SpawnGlue<Spawner>, Arc<RuntimeClient>>, OverseerHandle), Error>
+	where
+		RuntimeClient: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+		RuntimeClient::Api: ParachainHost<Block> + BabeApi<Block> + AuthorityDiscoveryApi<Block>,
+		Spawner: 'static + SpawnNamed + Clone + Unpin,
+	{
+		gum::info!(
+			target: MALUS_WITHHOLD_POV,
+			"😈 Started Malus node withholding the PoV of {:?} percent of candidates.",
+			self.percentage,
+		);
+
+		let percentage = Percentage::try_from(self.percentage).map_err(|e| {
+			SubsystemError::Context(format!("configure the withheld PoV percentage: {}", e))
+		})?;
+		let withhold_pov = WithholdPoV::new(percentage);
+
+		prepared_overseer_builder(args)?
+			.replace_availability_distribution(move |ad| {
+				InterceptedSubsystem::new("withhold-pov", DEFAULT_SPAWN_PREFIX, ad, withhold_pov)
+			})
+			.build_with_connector(connector)
+			.map_err(|e| e.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::channel::oneshot;
+	use polkadot_node_primitives::AvailableData;
+	use polkadot_node_subsystem_test_helpers::TestSubsystemSender;
+	use polkadot_primitives::v2::{Hash, ValidatorIndex};
+
+	fn candidate(n: u8) -> CandidateHash {
+		CandidateHash(Hash::repeat_byte(n))
+	}
+
+	/// Query the available data of the candidate through the interceptor, returning the
+	/// answer if the query was not forwarded to the availability store.
+	fn query_available_data(
+		interceptor: &WithholdPoV,
+		candidate_hash: CandidateHash,
+	) -> Option<Option<AvailableData>> {
+		let (tx, mut rx) = oneshot::channel();
+		let msg = overseer::AvailabilityDistributionOutgoingMessages::AvailabilityStoreMessage(
+			AvailabilityStoreMessage::QueryAvailableData(candidate_hash, tx),
+		);
+
+		match MessageInterceptor::<TestSubsystemSender>::intercept_outgoing(interceptor, msg) {
+			Some(_) => None,
+			None => Some(rx.try_recv().unwrap().expect("withheld queries are answered")),
+		}
+	}
+
+	#[test]
+	fn withholding_decision_respects_percentage() {
+		let always = WithholdPoV::new(Percentage::new(100.0).unwrap());
+		let never = WithholdPoV::new(Percentage::new(0.0).unwrap());
+
+		for n in 0..16 {
+			assert_eq!(query_available_data(&always, candidate(n)), Some(None));
+			assert_eq!(query_available_data(&never, candidate(n)), None);
+		}
+	}
+
+	#[test]
+	fn withholding_decision_is_per_candidate() {
+		let interceptor = WithholdPoV::new(Percentage::new(50.0).unwrap());
+
+		let first: Vec<_> = (0..64).map(|n| interceptor.should_withhold(candidate(n))).collect();
+		let again: Vec<_> = (0..64).map(|n| interceptor.should_withhold(candidate(n))).collect();
+		assert_eq!(first, again);
+	}
+
+	#[test]
+	fn chunks_are_still_served() {
+		let interceptor = WithholdPoV::new(Percentage::new(100.0).unwrap());
+		let (tx, _rx) = oneshot::channel();
+		let msg = overseer::AvailabilityDistributionOutgoingMessages::AvailabilityStoreMessage(
+			AvailabilityStoreMessage::QueryChunk(candidate(1), ValidatorIndex(0), tx),
+		);
+
+		assert!(MessageInterceptor::<TestSubsystemSender>::intercept_outgoing(&interceptor, msg)
+			.is_some());
+	}
+}
@@ -79,6 +79,10 @@ use responder::{respond, ResponderMessage};
 pub(crate) mod metrics;
 use metrics::Metrics;
 
+/// Components of the v2 statement distribution protocol, not yet in use.
+#[allow(unused)]
+mod vstaging;
+
 #[cfg(test)]
 mod tests;
 
@@ -0,0 +1,1994 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Direct distribution of statements within a group, even those concerning
+//! candidates which are not yet backed.
+//!
+//! Members of a validation group assigned to a para at a given relay-parent
+//! always distribute statements directly to each other.
+//!
+//! Each validator in the group is permitted to send up to `seconding_limit`
+//! `Seconded` statements per originator, as well as any number of `Valid`
+//! statements which pertain to candidates that have been seconded by other
+//! validators in the group.
+//!
+//! The limit on `Seconded` statements is set relative to the number of
+//! distinct candidates a validator is permitted to second - but these limits
+//! may differ per-validator, if an attacker is exploiting network partitions.
+//! For that reason, we track the `Seconded` statements received from every
+//! sender on behalf of every originator, and only accept knowledge of up to
+//! `seconding_limit` candidates per originator. Trackers may also be set up
+//! with a distinct limit for each originator.
+//!
+//! This module is a pure bookkeeping utility. It performs no signature
+//! checks and does not interact with the network.
+
+use polkadot_node_network_protocol::UnifiedReputationChange as Rep;
+use polkadot_primitives::v2::{CandidateHash, ValidatorIndex};
+
+use std::{fmt, ops::Range};
+
+const COST_PEER_EXCESS: Rep = Rep::CostMajor("Peer sent excessive `Seconded` statements");
+// peers may relay the statements of an equivocating originator in good faith.
+const COST_ORIGINATOR_EXCESS: Rep =
+	Rep::CostMinor("Peer relayed excessive `Seconded` statements of an originator");
+const COST_NOT_IN_GROUP: Rep =
+	Rep::CostMajorRepeated("Peer sent `Seconded` statement outside of the group");
+
+/// The default maximum number of statement slots a tracker may allocate, which fits
+/// groups of up to 32 validators with a seconding limit of up to 16 each.
+pub const DEFAULT_MAX_SLOTS: usize = 16 * 32 * 32;
+
+/// Utility for keeping track of limits on direct statements within a group.
+///
+/// See module docs for more details.
+pub struct DirectInGroup {
+	validators: Vec<ValidatorIndex>,
+	our_index: usize,
+	// the seconding limit of each originator, by index in the group.
+	limits: Vec<usize>,
+	// the offset of each originator's slots within a row of `accepted` or `incoming`,
+	// i.e. the sum of the limits of all preceding originators.
+	offsets: Vec<usize>,
+	// the sum of all limits, which is the length of a row.
+	row_size: usize,
+	// the maximum number of slots across all matrices.
+	max_slots: usize,
+	// whether our own `Seconded` statements sent to the group are accounted for.
+	track_outgoing: bool,
+
+	// a 3D matrix where the dimensions have the following meaning
+	// X: indicates the sending validator (size: group_size - 1, omitting self)
+	// Y: indicates the originating validator who issued the statement (size: group_size)
+	// Z: the candidate hash of the statement (size: the originator's limit)
+	//
+	// preallocated to (group_size - 1) * row_size.
+	incoming: Vec<Option<CandidateHash>>,
+
+	// a 2D matrix of accepted incoming `Seconded` messages from validators
+	// in the group.
+	// X: indicates the originating validator (size: group_size)
+	// Y: a seconded candidate we've accepted knowledge of locally (size: the originator's limit)
+	accepted: Vec<Option<CandidateHash>>,
+
+	// a 2D matrix of our own `Seconded` statements sent to validators in the group.
+	// X: indicates the recipient validator (size: group_size)
+	// Y: a candidate we've seconded and sent to the recipient (size: our own limit)
+	//
+	// empty if outgoing statements aren't tracked.
+	outgoing: Vec<Option<CandidateHash>>,
+}
+
+impl DirectInGroup {
+	/// A builder for a `DirectInGroup` tracker, to set up a tracker with any of the
+	/// optional features. See `DirectInGroupBuilder` for details.
+	pub fn builder() -> DirectInGroupBuilder {
+		DirectInGroupBuilder::default()
+	}
+
+	/// Instantiate a new `DirectInGroup` tracker. Fails if `group_validators` is empty,
+	/// `our_index` is not in the group, `seconding_limit` is zero or the tracker would
+	/// exceed `DEFAULT_MAX_SLOTS`. The limit must be at least 1, as a tracker with a
+	/// zero limit would reject every `Seconded` statement.
+	pub fn new(
+		group_validators: Vec<ValidatorIndex>,
+		our_index: ValidatorIndex,
+		seconding_limit: usize,
+	) -> Option<Self> {
+		DirectInGroup::builder()
+			.group(group_validators)
+			.our_index(our_index)
+			.seconding_limit(seconding_limit)
+			.build()
+	}
+
+	/// Instantiate a new `DirectInGroup` tracker where `limits[i]` is the seconding
+	/// limit of the `i`-th validator in the group. Fails if `group_validators` is empty,
+	/// `our_index` is not in the group, there isn't exactly one limit per validator,
+	/// any of the limits is zero or the tracker would exceed `DEFAULT_MAX_SLOTS`.
+	pub fn with_limits(
+		group_validators: Vec<ValidatorIndex>,
+		our_index: ValidatorIndex,
+		limits: Vec<usize>,
+	) -> Option<Self> {
+		DirectInGroup::builder()
+			.group(group_validators)
+			.our_index(our_index)
+			.per_validator_limits(limits)
+			.build()
+	}
+
+	/// Like `with_limits`, but fails if the tracker would need more than `max_slots`
+	/// statement slots instead of `DEFAULT_MAX_SLOTS`. The size is checked before
+	/// anything is allocated.
+	pub fn with_max_slots(
+		group_validators: Vec<ValidatorIndex>,
+		our_index: ValidatorIndex,
+		limits: Vec<usize>,
+		max_slots: usize,
+	) -> Option<Self> {
+		DirectInGroup::builder()
+			.group(group_validators)
+			.our_index(our_index)
+			.per_validator_limits(limits)
+			.max_slots(max_slots)
+			.build()
+	}
+
+	/// The validators in the group.
+	pub fn group(&self) -> &[ValidatorIndex] {
+		&self.validators
+	}
+
+	/// The number of validators in the group.
+	pub fn group_size(&self) -> usize {
+		self.validators.len()
+	}
+
+	/// Our own seconding limit. Unless set up `with_limits`, this is the limit
+	/// of every validator in the group.
+	pub fn seconding_limit(&self) -> usize {
+		self.limits[self.our_index]
+	}
+
+	/// The seconding limit of each validator in the group, in group order.
+	pub fn seconding_limits(&self) -> &[usize] {
+		&self.limits
+	}
+
+	/// Our own validator index.
+	pub fn our_validator_index(&self) -> ValidatorIndex {
+		self.validators[self.our_index]
+	}
+
+	/// Whether our own `Seconded` statements sent to the group are accounted for.
+	pub fn tracks_outgoing(&self) -> bool {
+		self.track_outgoing
+	}
+
+	/// Whether the given validator is in the group.
+	pub fn is_in_group(&self, validator: ValidatorIndex) -> bool {
+		self.index_in_group(validator).is_some()
+	}
+
+	/// Whether we are the only validator in the group. A solo group has no other
+	/// validators to exchange `Seconded` statements with, so every incoming statement
+	/// is rejected and direct distribution can be skipped entirely.
+	pub fn is_solo(&self) -> bool {
+		self.validators.len() == 1
+	}
+
+	/// Handle an incoming `Seconded` statement from the given validator.
+	/// If the outcome is `Reject` then no internal state is altered.
+	///
+	/// `YesKnown` means the statement was redundant. Callers should meter it, as
+	/// a high rate of redundant statements hints at gossip amplification.
+	pub fn handle_incoming_seconded(
+		&mut self,
+		sender: ValidatorIndex,
+		originator: ValidatorIndex,
+		candidate_hash: CandidateHash,
+	) -> Result<AcceptIncoming, RejectIncoming> {
+		let (incoming_slot, accepted_slot) =
+			self.check_incoming(sender, originator, candidate_hash)?;
+
+		self.incoming[incoming_slot] = Some(candidate_hash);
+		let accept = match accepted_slot {
+			AcceptedSlot::Known => AcceptIncoming::YesKnown,
+			AcceptedSlot::Free { index, slot } => {
+				self.accepted[index] = Some(candidate_hash);
+				AcceptIncoming::YesUnknown { slot }
+			},
+		};
+
+		#[cfg(debug_assertions)]
+		self.debug_validate();
+
+		Ok(accept)
+	}
+
+	/// Whether an incoming `Seconded` statement from the given validator would be
+	/// accepted, without altering any internal state.
+	///
+	/// This yields the same outcome as `handle_incoming_seconded` would, and is
+	/// cheap enough to be used before doing any signature checks.
+	pub fn can_receive(
+		&self,
+		sender: ValidatorIndex,
+		originator: ValidatorIndex,
+		candidate_hash: CandidateHash,
+	) -> Result<AcceptIncoming, RejectIncoming> {
+		self.check_incoming(sender, originator, candidate_hash)
+			.map(|(_, accepted_slot)| match accepted_slot {
+				AcceptedSlot::Known => AcceptIncoming::YesKnown,
+				AcceptedSlot::Free { slot, .. } => AcceptIncoming::YesUnknown { slot },
+			})
+	}
+
+	/// Whether we are permitted to send a `Seconded` statement by the given
+	/// originator to the rest of the group. This is the case for our own statements
+	/// and for statements we've accepted knowledge of from other group members.
+	pub fn can_send(&self, originator: ValidatorIndex, candidate_hash: CandidateHash) -> bool {
+		match self.index_in_group(originator) {
+			None => false,
+			Some(i) if i == self.our_index => true,
+			Some(i) => self.accepted_range(i).any(|j| self.accepted[j] == Some(candidate_hash)),
+		}
+	}
+
+	/// The number of distinct candidates we've accepted knowledge of from the given
+	/// originator. This never exceeds the originator's seconding limit and is zero
+	/// for validators outside of the group.
+	pub fn accepted_count(&self, originator: ValidatorIndex) -> usize {
+		match self.index_in_group(originator) {
+			None => 0,
+			Some(i) => self.accepted_range(i).filter(|&j| self.accepted[j].is_some()).count(),
+		}
+	}
+
+	/// The number of further distinct candidates we can accept knowledge of from the
+	/// given originator, i.e. its seconding limit minus the candidates accepted so far.
+	/// Zero for validators outside of the group.
+	pub fn remaining_originator_capacity(&self, originator: ValidatorIndex) -> usize {
+		match self.index_in_group(originator) {
+			None => 0,
+			Some(i) => self.accepted_range(i).filter(|&j| self.accepted[j].is_none()).count(),
+		}
+	}
+
+	/// The number of further `Seconded` statements by the given originator we can
+	/// receive from the given sender. Zero if either is outside of the group or the
+	/// sender is us.
+	///
+	/// Statements of candidates unknown for the originator are additionally bounded
+	/// by `remaining_originator_capacity`.
+	pub fn remaining_sender_capacity(
+		&self,
+		sender: ValidatorIndex,
+		originator: ValidatorIndex,
+	) -> usize {
+		match (self.index_in_group(sender), self.index_in_group(originator)) {
+			(Some(s), Some(o)) if s != self.our_index =>
+				self.incoming_range(s, o).filter(|&j| self.incoming[j].is_none()).count(),
+			_ => 0,
+		}
+	}
+
+	/// The number of occupied slots for `Seconded` statements by the given originator
+	/// received from the given sender, out of the originator's seconding limit. Once
+	/// full, further candidates from the sender are rejected with `PeerExcess`.
+	///
+	/// `None` if either is outside of the group or the sender is us, as there is no
+	/// such bucket.
+	pub fn incoming_fill(
+		&self,
+		sender: ValidatorIndex,
+		originator: ValidatorIndex,
+	) -> Option<usize> {
+		match (self.index_in_group(sender), self.index_in_group(originator)) {
+			(Some(s), Some(o)) if s != self.our_index =>
+				Some(self.incoming_range(s, o).filter(|&j| self.incoming[j].is_some()).count()),
+			_ => None,
+		}
+	}
+
+	/// The candidates we have accepted knowledge of on behalf of the given originator,
+	/// in the order they were accepted. Empty if the originator is not in the group.
+	pub fn accepted_candidates(&self, originator: ValidatorIndex) -> Vec<CandidateHash> {
+		match self.index_in_group(originator) {
+			None => Vec::new(),
+			Some(i) => self.accepted[self.accepted_range(i)].iter().flatten().copied().collect(),
+		}
+	}
+
+	/// All candidates we have accepted knowledge of, along with their originator.
+	///
+	/// Originators are in group order, and the candidates of each originator in the
+	/// order they were accepted. As this depends on the group order and on the order
+	/// of imports, use `sorted_accepted` for output that has to be deterministic.
+	pub fn all_accepted(&self) -> Vec<(ValidatorIndex, CandidateHash)> {
+		self.validators
+			.iter()
+			.enumerate()
+			.flat_map(|(i, &originator)| {
+				self.accepted[self.accepted_range(i)]
+					.iter()
+					.flatten()
+					.map(move |&candidate_hash| (originator, candidate_hash))
+			})
+			.collect()
+	}
+
+	/// The candidates we have accepted knowledge of, grouped by originator. Originators
+	/// are sorted by validator index and their candidates by hash, such that the output
+	/// only depends on the set of accepted candidates. Originators without any accepted
+	/// candidates are omitted.
+	pub fn sorted_accepted(&self) -> Vec<(ValidatorIndex, Vec<CandidateHash>)> {
+		let mut sorted: Vec<_> = self
+			.validators
+			.iter()
+			.enumerate()
+			.map(|(i, &originator)| {
+				let mut candidates: Vec<_> =
+					self.accepted[self.accepted_range(i)].iter().flatten().copied().collect();
+				candidates.sort();
+				(originator, candidates)
+			})
+			.filter(|(_, candidates)| !candidates.is_empty())
+			.collect();
+		sorted.sort_by_key(|(originator, _)| *originator);
+		sorted
+	}
+
+	/// The validators we've received a `Seconded` statement for the given candidate
+	/// from on behalf of the given originator, in group order. Empty if the originator
+	/// is not in the group.
+	pub fn senders_of(
+		&self,
+		originator: ValidatorIndex,
+		candidate_hash: CandidateHash,
+	) -> Vec<ValidatorIndex> {
+		let originator_index = match self.index_in_group(originator) {
+			None => return Vec::new(),
+			Some(i) => i,
+		};
+
+		self.validators
+			.iter()
+			.enumerate()
+			.filter(|&(i, _)| i != self.our_index)
+			.filter(|&(i, _)| {
+				self.incoming[self.incoming_range(i, originator_index)]
+					.contains(&Some(candidate_hash))
+			})
+			.map(|(_, &sender)| sender)
+			.collect()
+	}
+
+	/// Note that we have sent one of our own `Seconded` statements to the given
+	/// recipient. If the outcome is `Err` then no internal state is altered.
+	///
+	/// Sending the same candidate to a recipient more than once does not count
+	/// against the limit. If outgoing statements aren't tracked, only the group
+	/// membership of the recipient is checked.
+	pub fn note_sent(
+		&mut self,
+		recipient: ValidatorIndex,
+		candidate_hash: CandidateHash,
+	) -> Result<(), RejectOutgoing> {
+		if let Some(i) = self.outgoing_slot(recipient, candidate_hash)? {
+			self.outgoing[i] = Some(candidate_hash);
+		}
+
+		Ok(())
+	}
+
+	/// Whether we may send one of our own `Seconded` statements to the given
+	/// recipient, without altering any internal state.
+	pub fn can_send_to(
+		&self,
+		recipient: ValidatorIndex,
+		candidate_hash: CandidateHash,
+	) -> Result<(), RejectOutgoing> {
+		self.outgoing_slot(recipient, candidate_hash).map(|_| ())
+	}
+
+	// Find a free slot in the recipient's outgoing range, or `None` if the
+	// candidate has already been sent to the recipient.
+	fn outgoing_slot(
+		&self,
+		recipient: ValidatorIndex,
+		candidate_hash: CandidateHash,
+	) -> Result<Option<usize>, RejectOutgoing> {
+		let recipient_index = match self.index_in_group(recipient) {
+			Some(i) if i != self.our_index => i,
+			_ => return Err(RejectOutgoing::NotInGroup),
+		};
+
+		if !self.track_outgoing {
+			return Ok(None)
+		}
+
+		for i in self.outgoing_range(recipient_index) {
+			match self.outgoing[i] {
+				Some(c) if c == candidate_hash => return Ok(None),
+				Some(_) => continue,
+				None => return Ok(Some(i)),
+			}
+		}
+
+		Err(RejectOutgoing::RecipientExcess)
+	}
+
+	/// Forget all statements we've received and sent, retaining the allocated
+	/// matrices so the tracker can be reused for the same group.
+	///
+	/// Nothing calls this on leaf deactivation yet, as the subsystem doesn't run the
+	/// v2 protocol.
+	pub fn clear(&mut self) {
+		self.incoming.iter_mut().for_each(|c| *c = None);
+		self.accepted.iter_mut().for_each(|c| *c = None);
+		self.outgoing.iter_mut().for_each(|c| *c = None);
+	}
+
+	/// Forget the statements concerning candidates of the given originator: the candidates
+	/// we've accepted knowledge of on its behalf and the statements received from all
+	/// senders on its behalf. The state of other originators and our outgoing statements
+	/// are untouched. Does nothing for validators outside of the group.
+	pub fn clear_originator(&mut self, originator: ValidatorIndex) {
+		let originator_index = match self.index_in_group(originator) {
+			None => return,
+			Some(i) => i,
+		};
+
+		for sender_index in (0..self.validators.len()).filter(|&i| i != self.our_index) {
+			let range = self.incoming_range(sender_index, originator_index);
+			self.incoming[range].iter_mut().for_each(|c| *c = None);
+		}
+
+		let range = self.accepted_range(originator_index);
+		self.accepted[range].iter_mut().for_each(|c| *c = None);
+	}
+
+	/// Move the tracker over to a new group after a group rotation, keeping the
+	/// knowledge of candidates that is still relevant. Accepted candidates of
+	/// originators present in both groups are retained, as are the statements
+	/// received from and sent to validators present in both groups. Everything
+	/// concerning validators which left the group is dropped.
+	///
+	/// Validators staying in the group keep their seconding limit, while those
+	/// joining it get our own. The maximum slot count the tracker was set up with
+	/// and whether it tracks outgoing statements apply to the new group as well.
+	/// If the outcome is `Err` then no internal state is altered.
+	pub fn remap_group(
+		&mut self,
+		new_validators: Vec<ValidatorIndex>,
+		new_our_index: ValidatorIndex,
+	) -> Result<(), RemapError> {
+		if new_validators.is_empty() {
+			return Err(RemapError::EmptyGroup)
+		}
+		if index_in_group(&new_validators, new_our_index).is_none() {
+			return Err(RemapError::NotInGroup)
+		}
+
+		let limits = new_validators
+			.iter()
+			.map(|&v| self.index_in_group(v).map_or(self.seconding_limit(), |i| self.limits[i]))
+			.collect();
+		let mut remapped = DirectInGroup::builder()
+			.group(new_validators)
+			.our_index(new_our_index)
+			.per_validator_limits(limits)
+			.max_slots(self.max_slots)
+			.track_outgoing(self.track_outgoing)
+			.build()
+			.ok_or(RemapError::TooLarge)?;
+
+		// pairs of (old, new) indices of the validators present in both groups.
+		let surviving: Vec<(usize, usize)> = remapped
+			.validators
+			.iter()
+			.enumerate()
+			.filter_map(|(new, &v)| self.index_in_group(v).map(|old| (old, new)))
+			.collect();
+		// those we receive statements from and send statements to in both groups.
+		let peers: Vec<(usize, usize)> = surviving
+			.iter()
+			.copied()
+			.filter(|&(old, new)| old != self.our_index && new != remapped.our_index)
+			.collect();
+
+		for &(old_originator, new_originator) in &surviving {
+			let range = remapped.accepted_range(new_originator);
+			copy_slots(
+				&mut remapped.accepted[range],
+				&self.accepted[self.accepted_range(old_originator)],
+			);
+
+			for &(old_sender, new_sender) in &peers {
+				let range = remapped.incoming_range(new_sender, new_originator);
+				copy_slots(
+					&mut remapped.incoming[range],
+					&self.incoming[self.incoming_range(old_sender, old_originator)],
+				);
+			}
+		}
+
+		if self.track_outgoing {
+			for &(old_recipient, new_recipient) in &peers {
+				let range = remapped.outgoing_range(new_recipient);
+				copy_slots(
+					&mut remapped.outgoing[range],
+					&self.outgoing[self.outgoing_range(old_recipient)],
+				);
+			}
+		}
+
+		*self = remapped;
+		Ok(())
+	}
+
+	/// The number of bytes allocated on the heap by this tracker.
+	pub fn memory_footprint(&self) -> usize {
+		let slot_size = std::mem::size_of::<Option<CandidateHash>>();
+
+		self.validators.capacity() * std::mem::size_of::<ValidatorIndex>() +
+			(self.limits.capacity() + self.offsets.capacity()) * std::mem::size_of::<usize>() +
+			(self.incoming.capacity() + self.accepted.capacity() + self.outgoing.capacity()) *
+				slot_size
+	}
+
+	/// The raw matrix of incoming `Seconded` statements, by sender, originator and slot.
+	#[cfg(test)]
+	pub(crate) fn incoming_snapshot(&self) -> &[Option<CandidateHash>] {
+		&self.incoming
+	}
+
+	/// The raw matrix of accepted `Seconded` statements, by originator and slot.
+	#[cfg(test)]
+	pub(crate) fn accepted_snapshot(&self) -> &[Option<CandidateHash>] {
+		&self.accepted
+	}
+
+	/// Check the layout of the `incoming` matrix against the group, panicking on any
+	/// inconsistency. Every slot has to be owned by exactly one (sender, originator)
+	/// range, and the candidates in a range have to be distinct and accepted for the
+	/// originator.
+	///
+	/// This scans the full matrix, so it's only meant to catch indexing regressions
+	/// in debug builds.
+	#[cfg(debug_assertions)]
+	fn debug_validate(&self) {
+		let group_size = self.validators.len();
+		assert_eq!(self.incoming.len(), (group_size - 1) * self.row_size);
+
+		let mut owners = vec![None; self.incoming.len()];
+		for sender in (0..group_size).filter(|&i| i != self.our_index) {
+			for originator in 0..group_size {
+				let range = self.incoming_range(sender, originator);
+				let mut seen = Vec::with_capacity(range.len());
+				for j in range {
+					assert!(
+						owners[j].replace((sender, originator)).is_none(),
+						"incoming slot {} is owned by more than one range",
+						j,
+					);
+
+					if let Some(candidate_hash) = self.incoming[j] {
+						assert!(
+							!seen.contains(&candidate_hash),
+							"duplicate incoming statement in slot {}",
+							j,
+						);
+						assert!(
+							self.accepted[self.accepted_range(originator)]
+								.contains(&Some(candidate_hash)),
+							"incoming statement in slot {} is not accepted for its originator",
+							j,
+						);
+						seen.push(candidate_hash);
+					}
+				}
+			}
+		}
+
+		if let Some(j) = owners.iter().position(Option::is_none) {
+			panic!("incoming slot {} is not owned by any range", j);
+		}
+	}
+
+	fn check_incoming(
+		&self,
+		sender: ValidatorIndex,
+		originator: ValidatorIndex,
+		candidate_hash: CandidateHash,
+	) -> Result<(usize, AcceptedSlot), RejectIncoming> {
+		let sender_index = self.index_in_group(sender).ok_or(RejectIncoming::NotInGroup)?;
+		let originator_index = self.index_in_group(originator).ok_or(RejectIncoming::NotInGroup)?;
+
+		// we never receive statements from ourselves.
+		if sender_index == self.our_index {
+			return Err(RejectIncoming::NotInGroup)
+		}
+
+		let incoming_slot = self.incoming_slot(sender_index, originator_index, candidate_hash)?;
+		let accepted_slot = self.accepted_slot(originator_index, candidate_hash)?;
+
+		Ok((incoming_slot, accepted_slot))
+	}
+
+	// Find a free slot for the statement within the sender's range for the
+	// given originator. Duplicates are rejected.
+	fn incoming_slot(
+		&self,
+		sender_index: usize,
+		originator_index: usize,
+		candidate_hash: CandidateHash,
+	) -> Result<usize, RejectIncoming> {
+		for i in self.incoming_range(sender_index, originator_index) {
+			match self.incoming[i] {
+				// duplicates get rejected.
+				Some(c) if c == candidate_hash => return Err(RejectIncoming::PeerExcess),
+				Some(_) => continue,
+				None => return Ok(i),
+			}
+		}
+
+		Err(RejectIncoming::PeerExcess)
+	}
+
+	fn accepted_slot(
+		&self,
+		originator_index: usize,
+		candidate_hash: CandidateHash,
+	) -> Result<AcceptedSlot, RejectIncoming> {
+		let range = self.accepted_range(originator_index);
+		for i in range.clone() {
+			match self.accepted[i] {
+				Some(c) if c == candidate_hash => return Ok(AcceptedSlot::Known),
+				Some(_) => continue,
+				None => return Ok(AcceptedSlot::Free { index: i, slot: i - range.start }),
+			}
+		}
+
+		Err(RejectIncoming::OriginatorExcess)
+	}
+
+	fn index_in_group(&self, validator: ValidatorIndex) -> Option<usize> {
+		index_in_group(&self.validators, validator)
+	}
+
+	fn incoming_range(&self, sender: usize, originator: usize) -> Range<usize> {
+		let group_size = self.validators.len();
+		assert!(sender < group_size && sender != self.our_index);
+		assert!(originator < group_size);
+
+		// adjust X dimension to account for the fact that our index is not included.
+		let sender = if sender > self.our_index { sender - 1 } else { sender };
+		let base = sender * self.row_size + self.offsets[originator];
+
+		let range = base..base + self.limits[originator];
+		assert!(range.end <= self.incoming.len());
+		range
+	}
+
+	fn accepted_range(&self, originator: usize) -> Range<usize> {
+		let base = self.offsets[originator];
+		base..base + self.limits[originator]
+	}
+
+	fn outgoing_range(&self, recipient: usize) -> Range<usize> {
+		let our_limit = self.limits[self.our_index];
+		let base = recipient * our_limit;
+		base..base + our_limit
+	}
+}
+
+/// Builder for a `DirectInGroup` tracker.
+///
+/// The group, our index in it and either a common seconding limit or one limit per
+/// validator are required. Outgoing statements are tracked and the tracker is capped
+/// at `DEFAULT_MAX_SLOTS` unless configured otherwise.
+#[derive(Debug, Clone)]
+pub struct DirectInGroupBuilder {
+	group: Vec<ValidatorIndex>,
+	our_index: Option<ValidatorIndex>,
+	seconding_limit: Option<usize>,
+	per_validator_limits: Option<Vec<usize>>,
+	track_outgoing: bool,
+	max_slots: usize,
+}
+
+impl Default for DirectInGroupBuilder {
+	fn default() -> Self {
+		DirectInGroupBuilder {
+			group: Vec::new(),
+			our_index: None,
+			seconding_limit: None,
+			per_validator_limits: None,
+			track_outgoing: true,
+			max_slots: DEFAULT_MAX_SLOTS,
+		}
+	}
+}
+
+impl DirectInGroupBuilder {
+	/// The validators in the group.
+	pub fn group(mut self, group_validators: Vec<ValidatorIndex>) -> Self {
+		self.group = group_validators;
+		self
+	}
+
+	/// Our own validator index, which must be in the group.
+	pub fn our_index(mut self, our_index: ValidatorIndex) -> Self {
+		self.our_index = Some(our_index);
+		self
+	}
+
+	/// The seconding limit of every validator in the group.
+	pub fn seconding_limit(mut self, seconding_limit: usize) -> Self {
+		self.seconding_limit = Some(seconding_limit);
+		self
+	}
+
+	/// The seconding limit of each validator in the group, in group order.
+	pub fn per_validator_limits(mut self, limits: Vec<usize>) -> Self {
+		self.per_validator_limits = Some(limits);
+		self
+	}
+
+	/// Whether to account for our own `Seconded` statements sent to the group. If not,
+	/// no statement slots are allocated for them and sending is never limited.
+	pub fn track_outgoing(mut self, track_outgoing: bool) -> Self {
+		self.track_outgoing = track_outgoing;
+		self
+	}
+
+	/// The maximum number of statement slots the tracker may allocate.
+	pub fn max_slots(mut self, max_slots: usize) -> Self {
+		self.max_slots = max_slots;
+		self
+	}
+
+	/// Build the tracker. Fails if the group is empty, our index is not set or not in
+	/// the group, not exactly one of `seconding_limit` and `per_validator_limits` is
+	/// set, there isn't exactly one limit per validator, any of the limits is zero or
+	/// the tracker would need more than `max_slots` statement slots. The size is
+	/// checked before anything is allocated.
+	pub fn build(self) -> Option<DirectInGroup> {
+		let DirectInGroupBuilder {
+			group: group_validators,
+			our_index,
+			seconding_limit,
+			per_validator_limits,
+			track_outgoing,
+			max_slots,
+		} = self;
+
+		let limits = match (seconding_limit, per_validator_limits) {
+			(Some(limit), None) => vec![limit; group_validators.len()],
+			(None, Some(limits)) => limits,
+			_ => return None,
+		};
+
+		if group_validators.is_empty() ||
+			limits.len() != group_validators.len() ||
+			limits.contains(&0)
+		{
+			return None
+		}
+
+		let our_index = index_in_group(&group_validators, our_index?)?;
+
+		let row_size = limits.iter().try_fold(0usize, |sum, limit| sum.checked_add(*limit))?;
+
+		let incoming_size = (group_validators.len() - 1).checked_mul(row_size)?;
+		let outgoing_size = if track_outgoing {
+			group_validators.len().checked_mul(limits[our_index])?
+		} else {
+			0
+		};
+		let total_size = incoming_size.checked_add(row_size)?.checked_add(outgoing_size)?;
+		if total_size > max_slots {
+			return None
+		}
+
+		let offsets = limits
+			.iter()
+			.scan(0, |offset, limit| {
+				let this = *offset;
+				*offset += limit;
+				Some(this)
+			})
+			.collect();
+
+		let incoming = vec![None; incoming_size];
+		let accepted = vec![None; row_size];
+		let outgoing = vec![None; outgoing_size];
+
+		Some(DirectInGroup {
+			validators: group_validators,
+			our_index,
+			limits,
+			offsets,
+			row_size,
+			max_slots,
+			track_outgoing,
+			incoming,
+			accepted,
+			outgoing,
+		})
+	}
+}
+
+enum AcceptedSlot {
+	Known,
+	// `index` into `accepted`, `slot` within the originator's range.
+	Free { index: usize, slot: usize },
+}
+
+/// Incoming `Seconded` message was accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcceptIncoming {
+	/// The `Seconded` statement was within the peer's limits and unknown
+	/// for the originator. `slot` is the index of the accepted slot it was noted
+	/// in, within the originator's range of `0..seconding_limit` slots.
+	YesUnknown { slot: usize },
+	/// The `Seconded` statement was within the peer's limits and already
+	/// known for the originator. Worth metering, see `handle_incoming_seconded`.
+	YesKnown,
+}
+
+impl fmt::Display for AcceptIncoming {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			AcceptIncoming::YesUnknown { .. } =>
+				write!(f, "accepted candidate unknown for originator"),
+			AcceptIncoming::YesKnown =>
+				write!(f, "accepted candidate already known for originator"),
+		}
+	}
+}
+
+/// Incoming `Seconded` message was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectIncoming {
+	/// Peer sent excessive `Seconded` statements.
+	PeerExcess,
+	/// Originator sent excessive `Seconded` statements.
+	OriginatorExcess,
+	/// Sender or originator is not in the group.
+	NotInGroup,
+}
+
+impl fmt::Display for RejectIncoming {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RejectIncoming::PeerExcess => write!(f, "peer exceeded seconding limit"),
+			RejectIncoming::OriginatorExcess => write!(f, "originator exceeded seconding limit"),
+			RejectIncoming::NotInGroup => write!(f, "sender or originator not in group"),
+		}
+	}
+}
+
+impl std::error::Error for RejectIncoming {}
+
+impl RejectIncoming {
+	/// The reputation change of the peer that sent the rejected statement.
+	pub fn reputation_cost(&self) -> Rep {
+		match self {
+			RejectIncoming::PeerExcess => COST_PEER_EXCESS,
+			RejectIncoming::OriginatorExcess => COST_ORIGINATOR_EXCESS,
+			RejectIncoming::NotInGroup => COST_NOT_IN_GROUP,
+		}
+	}
+}
+
+/// Outgoing `Seconded` message was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectOutgoing {
+	/// We would exceed the recipient's `Seconded` statement limit.
+	RecipientExcess,
+	/// Recipient is not in the group.
+	NotInGroup,
+}
+
+impl fmt::Display for RejectOutgoing {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RejectOutgoing::RecipientExcess => write!(f, "recipient exceeded seconding limit"),
+			RejectOutgoing::NotInGroup => write!(f, "recipient not in group"),
+		}
+	}
+}
+
+impl std::error::Error for RejectOutgoing {}
+
+/// The tracker could not be moved over to a new group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemapError {
+	/// The new group is empty.
+	EmptyGroup,
+	/// We are not in the new group.
+	NotInGroup,
+	/// The new group needs more statement slots than the tracker may allocate.
+	TooLarge,
+}
+
+impl fmt::Display for RemapError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RemapError::EmptyGroup => write!(f, "new group is empty"),
+			RemapError::NotInGroup => write!(f, "own validator not in new group"),
+			RemapError::TooLarge => write!(f, "new group exceeds the maximum slot count"),
+		}
+	}
+}
+
+impl std::error::Error for RemapError {}
+
+fn index_in_group(validators: &[ValidatorIndex], index: ValidatorIndex) -> Option<usize> {
+	validators.iter().position(|v| v == &index)
+}
+
+// Copy the slots of `src` to `dst`, up to the length of the shorter one. Slots are
+// filled from the start of a range, so this keeps as many candidates as fit.
+fn copy_slots(dst: &mut [Option<CandidateHash>], src: &[Option<CandidateHash>]) {
+	let n = dst.len().min(src.len());
+	dst[..n].copy_from_slice(&src[..n]);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use polkadot_primitives::v2::Hash;
+
+	fn candidate(n: u8) -> CandidateHash {
+		CandidateHash(Hash::repeat_byte(n))
+	}
+
+	#[test]
+	fn same_sender_distinct_originators_are_tracked_separately() {
+		let mut tracker = DirectInGroup::new(
+			vec![ValidatorIndex(0), ValidatorIndex(1), ValidatorIndex(2)],
+			ValidatorIndex(0),
+			1,
+		)
+		.unwrap();
+
+		assert!(matches!(
+			tracker.handle_incoming_seconded(ValidatorIndex(1), ValidatorIndex(1), candidate(1)),
+			Ok(AcceptIncoming::YesUnknown { .. })
+		));
+		assert!(matches!(
+			tracker.handle_incoming_seconded(ValidatorIndex(1), ValidatorIndex(2), candidate(2)),
+			Ok(AcceptIncoming::YesUnknown { .. })
+		));
+	}
+
+	#[test]
+	fn originator_limit_enforced_across_senders() {
+		let mut tracker = DirectInGroup::new(
+			vec![ValidatorIndex(0), ValidatorIndex(1), ValidatorIndex(2)],
+			ValidatorIndex(0),
+			1,
+		)
+		.unwrap();
+
+		assert!(matches!(
+			tracker.handle_incoming_seconded(ValidatorIndex(1), ValidatorIndex(2), candidate(1)),
+			Ok(AcceptIncoming::YesUnknown { .. })
+		));
+		assert!(matches!(
+			tracker.handle_incoming_seconded(ValidatorIndex(2), ValidatorIndex(2), candidate(2)),
+			Err(RejectIncoming::OriginatorExcess)
+		));
+	}
+
+	#[test]
+	fn incoming_fill_counts_occupied_slots() {
+		let mut tracker = DirectInGroup::new(
+			vec![ValidatorIndex(0), ValidatorIndex(1), ValidatorIndex(2)],
+			ValidatorIndex(0),
+			2,
+		)
+		.unwrap();
+
+		assert_eq!(tracker.incoming_fill(ValidatorIndex(1), ValidatorIndex(2)), Some(0));
+
+		tracker
+			.handle_incoming_seconded(ValidatorIndex(1), ValidatorIndex(2), candidate(1))
+			.unwrap();
+		assert_eq!(tracker.incoming_fill(ValidatorIndex(1), ValidatorIndex(2)), Some(1));
+		assert_eq!(tracker.incoming_fill(ValidatorIndex(2), ValidatorIndex(2)), Some(0));
+
+		tracker
+			.handle_incoming_seconded(ValidatorIndex(1), ValidatorIndex(2), candidate(2))
+			.unwrap();
+		assert_eq!(
+			tracker.incoming_fill(ValidatorIndex(1), ValidatorIndex(2)),
+			Some(tracker.seconding_limit())
+		);
+		assert!(matches!(
+			tracker.handle_incoming_seconded(ValidatorIndex(1), ValidatorIndex(2), candidate(3)),
+			Err(RejectIncoming::PeerExcess)
+		));
+		assert_eq!(tracker.incoming_fill(ValidatorIndex(1), ValidatorIndex(2)), Some(2));
+
+		assert_eq!(tracker.incoming_fill(ValidatorIndex(3), ValidatorIndex(2)), None);
+		assert_eq!(tracker.incoming_fill(ValidatorIndex(1), ValidatorIndex(3)), None);
+		assert_eq!(tracker.incoming_fill(ValidatorIndex(0), ValidatorIndex(2)), None);
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	fn layout_is_consistent_after_imports() {
+		let validators: Vec<_> = (0..4).map(ValidatorIndex).collect();
+		let mut tracker =
+			DirectInGroup::with_limits(validators.clone(), ValidatorIndex(1), vec![1, 2, 3, 1])
+				.unwrap();
+		tracker.debug_validate();
+
+		let mut n = 0;
+		for &sender in validators.iter().filter(|&&v| v != ValidatorIndex(1)) {
+			for &originator in &validators {
+				for _ in 0..4 {
+					n += 1;
+					let _ = tracker.handle_incoming_seconded(sender, originator, candidate(n % 5));
+					tracker.debug_validate();
+				}
+			}
+		}
+		assert!(tracker.incoming_snapshot().iter().any(Option::is_some));
+
+		tracker.clear_originator(ValidatorIndex(2));
+		tracker.debug_validate();
+	}
+
+	#[test]
+	fn solo_group_rejects_all_incoming() {
+		let mut tracker =
+			DirectInGroup::new(vec![ValidatorIndex(0)], ValidatorIndex(0), 2).unwrap();
+		assert!(tracker.is_solo());
+
+		for sender in [ValidatorIndex(0), ValidatorIndex(1)] {
+			assert!(matches!(
+				tracker.handle_incoming_seconded(sender, ValidatorIndex(0), candidate(1)),
+				Err(RejectIncoming::NotInGroup)
+			));
+		}
+		assert!(tracker.all_accepted().is_empty());
+
+		let tracker =
+			DirectInGroup::new(vec![ValidatorIndex(0), ValidatorIndex(1)], ValidatorIndex(0), 2)
+				.unwrap();
+		assert!(!tracker.is_solo());
+	}
+
+	#[test]
+	fn originator_not_in_group_rejected() {
+		let mut tracker = DirectInGroup::new(
+			vec![ValidatorIndex(0), ValidatorIndex(1), ValidatorIndex(2)],
+			ValidatorIndex(0),
+			1,
+		)
+		.unwrap();
+
+		assert!(matches!(
+			tracker.handle_incoming_seconded(ValidatorIndex(1), ValidatorIndex(7), candidate(1)),
+			Err(RejectIncoming::NotInGroup)
+		));
+	}
+
+	#[test]
+	fn incoming_ranges_do_not_alias() {
+		let group: Vec<_> = (0..5).map(ValidatorIndex).collect();
+		let limit = 3;
+
+		for our_index in 0..group.len() {
+			let tracker =
+				DirectInGroup::new(group.clone(), ValidatorIndex(our_index as u32), limit).unwrap();
+
+			let mut covered = vec![false; tracker.incoming.len()];
+			for sender in (0..group.len()).filter(|s| *s != our_index) {
+				for originator in 0..group.len() {
+					for i in tracker.incoming_range(sender, originator) {
+						assert!(!covered[i], "slot {} aliased", i);
+						covered[i] = true;
+					}
+				}
+			}
+
+			assert!(covered.into_iter().all(|c| c));
+		}
+	}
+
+	#[test]
+	fn every_sender_originator_pair_fills_independently() {
+		let group: Vec<_> = (0..4).map(ValidatorIndex).collect();
+		let limit = 2;
+		let mut tracker = DirectInGroup::new(group.clone(), ValidatorIndex(1), limit).unwrap();
+
+		let relay = |o: u32, k: u32| candidate((o * limit as u32 + k) as u8);
+
+		for (n, &sender) in group.iter().filter(|v| **v != ValidatorIndex(1)).enumerate() {
+			for &originator in &group {
+				for k in 0..limit as u32 {
+					let res = tracker.handle_incoming_seconded(
+						sender,
+						originator,
+						relay(originator.0, k),
+					);
+					if n == 0 {
+						assert!(matches!(res, Ok(AcceptIncoming::YesUnknown { .. })));
+					} else {
+						assert!(matches!(res, Ok(AcceptIncoming::YesKnown)));
+					}
+				}
+
+				// only `limit` slots are available for each pair.
+				assert!(matches!(
+					tracker.handle_incoming_seconded(sender, originator, candidate(255)),
+					Err(RejectIncoming::PeerExcess)
+				));
+			}
+		}
+
+		assert!(tracker.incoming.iter().all(|c| c.is_some()));
+	}
+
+	#[test]
+	fn statements_from_self_rejected() {
+		let mut tracker = DirectInGroup::new(
+			vec![ValidatorIndex(0), ValidatorIndex(1), ValidatorIndex(2)],
+			ValidatorIndex(0),
+			1,
+		)
+		.unwrap();
+
+		assert!(matches!(
+			tracker.handle_incoming_seconded(ValidatorIndex(0), ValidatorIndex(1), candidate(1)),
+			Err(RejectIncoming::NotInGroup)
+		));
+	}
+
+	#[test]
+	fn reject_incoming_reputation_costs() {
+		let cost = |reject: RejectIncoming| reject.reputation_cost();
+
+		assert_eq!(cost(RejectIncoming::PeerExcess), COST_PEER_EXCESS);
+		assert_eq!(cost(RejectIncoming::OriginatorExcess), COST_ORIGINATOR_EXCESS);
+		assert_eq!(cost(RejectIncoming::NotInGroup), COST_NOT_IN_GROUP);
+
+		let value = |reject: RejectIncoming| reject.reputation_cost().into_base_rep().value;
+		assert!(!cost(RejectIncoming::PeerExcess).is_benefit());
+		assert!(!cost(RejectIncoming::OriginatorExcess).is_benefit());
+		assert_ne!(value(RejectIncoming::PeerExcess), value(RejectIncoming::OriginatorExcess));
+		assert!(value(RejectIncoming::NotInGroup) < value(RejectIncoming::PeerExcess));
+		assert!(value(RejectIncoming::NotInGroup) < value(RejectIncoming::OriginatorExcess));
+	}
+
+	#[test]
+	fn can_receive_matches_handle_incoming() {
+		let mut tracker = DirectInGroup::new(
+			vec![ValidatorIndex(0), ValidatorIndex(1), ValidatorIndex(2)],
+			ValidatorIndex(0),
+			1,
+		)
+		.unwrap();
+
+		let sequence = [
+			// unknown
+			(ValidatorIndex(1), ValidatorIndex(2), candidate(1)),
+			// known, via another sender
+			(ValidatorIndex(2), ValidatorIndex(2), candidate(1)),
+			// peer excess
+			(ValidatorIndex(1), ValidatorIndex(2), candidate(2)),
+			// originator excess
+			(ValidatorIndex(2), ValidatorIndex(2), candidate(2)),
+			// not in group
+			(ValidatorIndex(3), ValidatorIndex(2), candidate(3)),
+			(ValidatorIndex(1), ValidatorIndex(3), candidate(3)),
+		];
+
+		for (sender, originator, candidate_hash) in sequence {
+			let expected = tracker.can_receive(sender, originator, candidate_hash);
+			let actual = tracker.handle_incoming_seconded(sender, originator, candidate_hash);
+			assert_eq!(expected, actual);
+		}
+	}
+
+	#[test]
+	fn can_receive_does_not_alter_state() {
+		let tracker = DirectInGroup::new(
+			vec![ValidatorIndex(0), ValidatorIndex(1), ValidatorIndex(2)],
+			ValidatorIndex(0),
+			1,
+		)
+		.unwrap();
+
+		for _ in 0..3 {
+			assert!(matches!(
+				tracker.can_receive(ValidatorIndex(1), ValidatorIndex(2), candidate(1)),
+				Ok(AcceptIncoming::YesUnknown { slot: 0 })
+			));
+		}
+		assert!(tracker.incoming.iter().all(|c| c.is_none()));
+		assert!(tracker.accepted.iter().all(|c| c.is_none()));
+	}
+
+	#[test]
+	fn can_send_accepted_or_own() {
+		let mut tracker = DirectInGroup::new(
+			vec![ValidatorIndex(0), ValidatorIndex(1), ValidatorIndex(2)],
+			ValidatorIndex(0),
+			1,
+		)
+		.unwrap();
+
+		assert!(tracker.can_send(ValidatorIndex(0), candidate(1)));
+		assert!(!tracker.can_send(ValidatorIndex(2), candidate(1)));
+		assert!(!tracker.can_send(ValidatorIndex(3), candidate(1)));
+
+		tracker
+			.handle_incoming_seconded(ValidatorIndex(1), ValidatorIndex(2), candidate(1))
+			.ok()
+			.unwrap();
+
+		assert!(tracker.can_send(ValidatorIndex(2), candidate(1)));
+		assert!(!tracker.can_send(ValidatorIndex(2), candidate(2)));
+	}
+
+	#[test]
+	fn outgoing_accounting_is_per_recipient() {
+		let group: Vec<_> = (0..4).map(ValidatorIndex).collect();
+		let mut tracker = DirectInGroup::new(group.clone(), ValidatorIndex(0), 2).unwrap();
+
+		for &recipient in &group[1..] {
+			assert!(tracker.can_send_to(recipient, candidate(1)).is_ok());
+			assert!(tracker.note_sent(recipient, candidate(1)).is_ok());
+		}
+
+		// re-sending doesn't consume a slot.
+		assert!(tracker.note_sent(ValidatorIndex(1), candidate(1)).is_ok());
+
+		// recipient 1 gets a second candidate and is then saturated.
+		assert!(tracker.note_sent(ValidatorIndex(1), candidate(2)).is_ok());
+		assert!(matches!(
+			tracker.note_sent(ValidatorIndex(1), candidate(3)),
+			Err(RejectOutgoing::RecipientExcess)
+		));
+		assert!(matches!(
+			tracker.can_send_to(ValidatorIndex(1), candidate(3)),
+			Err(RejectOutgoing::RecipientExcess)
+		));
+
+		// others are unaffected.
+		for &recipient in &group[2..] {
+			assert!(tracker.can_send_to(recipient, candidate(3)).is_ok());
+		}
+	}
+
+	#[test]
+	fn outgoing_outside_group_rejected() {
+		let mut tracker = DirectInGroup::new(
+			vec![ValidatorIndex(0), ValidatorIndex(1), ValidatorIndex(2)],
+			ValidatorIndex(0),
+			1,
+		)
+		.unwrap();
+
+		assert!(matches!(
+			tracker.note_sent(ValidatorIndex(5), candidate(1)),
+			Err(RejectOutgoing::NotInGroup)
+		));
+		assert!(matches!(
+			tracker.note_sent(ValidatorIndex(0), candidate(1)),
+			Err(RejectOutgoing::NotInGroup)
+		));
+		assert!(tracker.outgoing.iter().all(|c| c.is_none()));
+	}
+
+	#[test]
+	fn clear_retains_footprint() {
+		let group: Vec<_> = (0..4).map(ValidatorIndex).collect();
+		let mut tracker = DirectInGroup::new(group.clone(), ValidatorIndex(0), 2).unwrap();
+		let footprint = tracker.memory_footprint();
+		assert!(footprint > 0);
+
+		for _ in 0..5 {
+			for &sender in &group[1..] {
+				for &originator in &group {
+					let _ = tracker.handle_incoming_seconded(sender, originator, candidate(1));
+				}
+				let _ = tracker.note_sent(sender, candidate(2));
+			}
+			assert!(tracker.incoming.iter().any(|c| c.is_some()));
+
+			tracker.clear();
+			assert!(tracker.incoming.iter().all(|c| c.is_none()));
+			assert!(tracker.accepted.iter().all(|c| c.is_none()));
+			assert!(tracker.outgoing.iter().all(|c| c.is_none()));
+			assert_eq!(tracker.memory_footprint(), footprint);
+		}
+	}
+
+	#[test]
+	fn clear_originator_leaves_others_untouched() {
+		let group: Vec<_> = (0..4).map(ValidatorIndex).collect();
+		let our_index = ValidatorIndex(1);
+		let mut tracker = DirectInGroup::new(group.clone(), our_index, 2).unwrap();
+
+		for &sender in group.iter().filter(|&&v| v != our_index) {
+			for &originator in &group {
+				let c = candidate(originator.0 as u8 + 1);
+				assert!(tracker.handle_incoming_seconded(sender, originator, c).is_ok());
+			}
+			assert!(tracker.note_sent(sender, candidate(1)).is_ok());
+		}
+
+		let cleared = ValidatorIndex(2);
+		let others = |tracker: &DirectInGroup| -> Vec<_> {
+			group
+				.iter()
+				.filter(|&&v| v != cleared)
+				.map(|&v| {
+					(
+						tracker.accepted_candidates(v),
+						tracker.senders_of(v, candidate(v.0 as u8 + 1)),
+					)
+				})
+				.collect()
+		};
+		let before = others(&tracker);
+		let outgoing = tracker.outgoing.clone();
+
+		tracker.clear_originator(cleared);
+
+		assert!(tracker.accepted_candidates(cleared).is_empty());
+		assert!(tracker.senders_of(cleared, candidate(3)).is_empty());
+		assert_eq!(tracker.remaining_originator_capacity(cleared), 2);
+		assert_eq!(tracker.remaining_sender_capacity(ValidatorIndex(0), cleared), 2);
+
+		let after = others(&tracker);
+		assert_eq!(after, before);
+		assert_eq!(tracker.outgoing, outgoing);
+
+		// clearing a validator outside of the group is a no-op.
+		let incoming = tracker.incoming.clone();
+		tracker.clear_originator(ValidatorIndex(9));
+		assert_eq!(tracker.incoming, incoming);
+	}
+
+	#[test]
+	fn our_index_resolved_by_membership() {
+		let group = vec![ValidatorIndex(5), ValidatorIndex(2), ValidatorIndex(9)];
+
+		assert!(DirectInGroup::new(group.clone(), ValidatorIndex(1), 1).is_none());
+
+		let tracker = DirectInGroup::new(group.clone(), ValidatorIndex(2), 1).unwrap();
+		assert_eq!(tracker.our_index, 1);
+
+		let tracker = DirectInGroup::new(group, ValidatorIndex(9), 1).unwrap();
+		assert_eq!(tracker.our_index, 2);
+	}
+
+	#[test]
+	fn accepted_count_tracks_saturation() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)];
+		let mut direct = DirectInGroup::new(group, ValidatorIndex(1), 2).unwrap();
+		let originator = ValidatorIndex(3);
+
+		assert_eq!(direct.accepted_count(originator), 0);
+
+		assert!(direct
+			.handle_incoming_seconded(ValidatorIndex(2), originator, candidate(1))
+			.is_ok());
+		assert_eq!(direct.accepted_count(originator), 1);
+
+		// knowledge of the same candidate from another sender isn't counted twice.
+		assert!(matches!(
+			direct.handle_incoming_seconded(ValidatorIndex(3), originator, candidate(1)),
+			Ok(AcceptIncoming::YesKnown)
+		));
+		assert_eq!(direct.accepted_count(originator), 1);
+
+		assert!(direct
+			.handle_incoming_seconded(ValidatorIndex(2), originator, candidate(2))
+			.is_ok());
+		assert_eq!(direct.accepted_count(originator), 2);
+
+		// saturated: further candidates are rejected and the count stays at the limit.
+		assert!(direct
+			.handle_incoming_seconded(ValidatorIndex(3), originator, candidate(3))
+			.is_err());
+		assert_eq!(direct.accepted_count(originator), 2);
+
+		// other originators are counted separately.
+		assert_eq!(direct.accepted_count(ValidatorIndex(2)), 0);
+	}
+
+	#[test]
+	fn accepted_count_outside_group_is_zero() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2)];
+		let direct = DirectInGroup::new(group, ValidatorIndex(1), 2).unwrap();
+
+		assert_eq!(direct.accepted_count(ValidatorIndex(7)), 0);
+	}
+
+	#[test]
+	fn remaining_capacity_tracks_saturation() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)];
+		let mut direct = DirectInGroup::new(group, ValidatorIndex(1), 2).unwrap();
+		let originator = ValidatorIndex(3);
+
+		// empty.
+		assert_eq!(direct.remaining_originator_capacity(originator), 2);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(2), originator), 2);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(3), originator), 2);
+
+		// partial.
+		direct
+			.handle_incoming_seconded(ValidatorIndex(2), originator, candidate(1))
+			.unwrap();
+		assert_eq!(direct.remaining_originator_capacity(originator), 1);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(2), originator), 1);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(3), originator), 2);
+
+		// a known candidate from another sender only uses up the sender's capacity.
+		direct
+			.handle_incoming_seconded(ValidatorIndex(3), originator, candidate(1))
+			.unwrap();
+		assert_eq!(direct.remaining_originator_capacity(originator), 1);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(3), originator), 1);
+
+		// full.
+		direct
+			.handle_incoming_seconded(ValidatorIndex(2), originator, candidate(2))
+			.unwrap();
+		assert_eq!(direct.remaining_originator_capacity(originator), 0);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(2), originator), 0);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(3), originator), 1);
+
+		// other originators are unaffected.
+		assert_eq!(direct.remaining_originator_capacity(ValidatorIndex(2)), 2);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(3), ValidatorIndex(2)), 2);
+	}
+
+	#[test]
+	fn remaining_capacity_outside_group_or_from_self_is_zero() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2)];
+		let direct = DirectInGroup::with_limits(group, ValidatorIndex(1), vec![1, 3]).unwrap();
+
+		assert_eq!(direct.remaining_originator_capacity(ValidatorIndex(7)), 0);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(7), ValidatorIndex(2)), 0);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(2), ValidatorIndex(7)), 0);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(1), ValidatorIndex(2)), 0);
+
+		// capacities follow the per-validator limits.
+		assert_eq!(direct.remaining_originator_capacity(ValidatorIndex(2)), 3);
+		assert_eq!(direct.remaining_sender_capacity(ValidatorIndex(2), ValidatorIndex(1)), 1);
+	}
+
+	#[test]
+	fn with_limits_requires_one_limit_per_validator() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2)];
+
+		assert!(DirectInGroup::with_limits(group.clone(), ValidatorIndex(1), vec![1]).is_none());
+		assert!(
+			DirectInGroup::with_limits(group.clone(), ValidatorIndex(1), vec![1, 2, 3]).is_none()
+		);
+		assert!(DirectInGroup::with_limits(group, ValidatorIndex(1), vec![1, 3]).is_some());
+	}
+
+	#[test]
+	fn per_originator_limits_enforced_independently() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)];
+		let mut direct =
+			DirectInGroup::with_limits(group, ValidatorIndex(3), vec![1, 3, 2]).unwrap();
+		let (first, second) = (ValidatorIndex(1), ValidatorIndex(2));
+
+		// the first originator has a limit of 1.
+		assert!(direct.handle_incoming_seconded(first, first, candidate(1)).is_ok());
+		assert!(matches!(
+			direct.handle_incoming_seconded(first, first, candidate(2)),
+			Err(RejectIncoming::PeerExcess)
+		));
+		assert!(matches!(
+			direct.handle_incoming_seconded(second, first, candidate(2)),
+			Err(RejectIncoming::OriginatorExcess)
+		));
+
+		// the second originator has a limit of 3, unaffected by the first one being saturated.
+		for n in 10..13 {
+			assert!(direct.handle_incoming_seconded(second, second, candidate(n)).is_ok());
+		}
+		assert!(matches!(
+			direct.handle_incoming_seconded(second, second, candidate(13)),
+			Err(RejectIncoming::PeerExcess)
+		));
+		assert!(matches!(
+			direct.handle_incoming_seconded(first, second, candidate(13)),
+			Err(RejectIncoming::OriginatorExcess)
+		));
+
+		assert_eq!(direct.accepted_count(first), 1);
+		assert_eq!(direct.accepted_count(second), 3);
+	}
+
+	#[test]
+	fn outgoing_uses_own_limit() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2)];
+		let mut direct = DirectInGroup::with_limits(group, ValidatorIndex(1), vec![2, 1]).unwrap();
+		let recipient = ValidatorIndex(2);
+
+		assert!(direct.note_sent(recipient, candidate(1)).is_ok());
+		assert!(direct.note_sent(recipient, candidate(2)).is_ok());
+		assert!(matches!(
+			direct.note_sent(recipient, candidate(3)),
+			Err(RejectOutgoing::RecipientExcess)
+		));
+	}
+
+	#[test]
+	fn verdicts_display() {
+		assert_eq!(
+			AcceptIncoming::YesUnknown { slot: 0 }.to_string(),
+			"accepted candidate unknown for originator"
+		);
+		assert_eq!(
+			AcceptIncoming::YesKnown.to_string(),
+			"accepted candidate already known for originator"
+		);
+		assert_eq!(RejectIncoming::PeerExcess.to_string(), "peer exceeded seconding limit");
+		assert_eq!(
+			RejectIncoming::OriginatorExcess.to_string(),
+			"originator exceeded seconding limit"
+		);
+		assert_eq!(RejectIncoming::NotInGroup.to_string(), "sender or originator not in group");
+		assert_eq!(
+			RejectOutgoing::RecipientExcess.to_string(),
+			"recipient exceeded seconding limit"
+		);
+		assert_eq!(RejectOutgoing::NotInGroup.to_string(), "recipient not in group");
+	}
+
+	#[test]
+	fn reject_incoming_propagates_as_error() {
+		fn check(
+			tracker: &mut DirectInGroup,
+			sender: ValidatorIndex,
+			originator: ValidatorIndex,
+			candidate_hash: CandidateHash,
+		) -> Result<AcceptIncoming, Box<dyn std::error::Error>> {
+			let accepted = tracker.handle_incoming_seconded(sender, originator, candidate_hash)?;
+			Ok(accepted)
+		}
+
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2)];
+		let mut tracker = DirectInGroup::new(group, ValidatorIndex(1), 1).unwrap();
+
+		assert_eq!(
+			check(&mut tracker, ValidatorIndex(2), ValidatorIndex(2), candidate(1)).unwrap(),
+			AcceptIncoming::YesUnknown { slot: 0 }
+		);
+		let err =
+			check(&mut tracker, ValidatorIndex(2), ValidatorIndex(2), candidate(2)).unwrap_err();
+		assert_eq!(err.to_string(), "peer exceeded seconding limit");
+	}
+
+	#[test]
+	fn accepted_candidates_listed_per_originator() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)];
+		let mut direct = DirectInGroup::new(group, ValidatorIndex(1), 2).unwrap();
+
+		assert!(direct.all_accepted().is_empty());
+
+		for (sender, originator, n) in [(2, 3, 1), (3, 3, 1), (3, 3, 2), (2, 2, 3), (3, 1, 4)] {
+			assert!(direct
+				.handle_incoming_seconded(
+					ValidatorIndex(sender),
+					ValidatorIndex(originator),
+					candidate(n),
+				)
+				.is_ok());
+		}
+
+		assert_eq!(direct.accepted_candidates(ValidatorIndex(1)), vec![candidate(4)]);
+		assert_eq!(direct.accepted_candidates(ValidatorIndex(2)), vec![candidate(3)]);
+		assert_eq!(direct.accepted_candidates(ValidatorIndex(3)), vec![candidate(1), candidate(2)]);
+		assert!(direct.accepted_candidates(ValidatorIndex(4)).is_empty());
+
+		assert_eq!(
+			direct.all_accepted(),
+			vec![
+				(ValidatorIndex(1), candidate(4)),
+				(ValidatorIndex(2), candidate(3)),
+				(ValidatorIndex(3), candidate(1)),
+				(ValidatorIndex(3), candidate(2)),
+			]
+		);
+	}
+
+	#[test]
+	fn sorted_accepted_is_independent_of_group_and_import_order() {
+		let imports = [(2, 3, 9), (3, 3, 1), (2, 2, 5), (3, 1, 4), (3, 3, 9), (3, 2, 3)];
+		let sorted_after = |group: Vec<ValidatorIndex>, reverse: bool| {
+			let mut direct = DirectInGroup::new(group, ValidatorIndex(1), 2).unwrap();
+			let mut imports = imports.to_vec();
+			if reverse {
+				imports.reverse();
+			}
+			for (sender, originator, n) in imports {
+				direct
+					.handle_incoming_seconded(
+						ValidatorIndex(sender),
+						ValidatorIndex(originator),
+						candidate(n),
+					)
+					.unwrap();
+			}
+			direct.sorted_accepted()
+		};
+
+		let expected = vec![
+			(ValidatorIndex(1), vec![candidate(4)]),
+			(ValidatorIndex(2), vec![candidate(3), candidate(5)]),
+			(ValidatorIndex(3), vec![candidate(1), candidate(9)]),
+		];
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)];
+		let shuffled = vec![ValidatorIndex(3), ValidatorIndex(1), ValidatorIndex(2)];
+		for _ in 0..4 {
+			for reverse in [false, true] {
+				assert_eq!(sorted_after(group.clone(), reverse), expected);
+				assert_eq!(sorted_after(shuffled.clone(), reverse), expected);
+			}
+		}
+	}
+
+	#[test]
+	fn sorted_accepted_omits_originators_without_candidates() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)];
+		let mut direct = DirectInGroup::new(group, ValidatorIndex(1), 2).unwrap();
+		assert!(direct.sorted_accepted().is_empty());
+
+		direct
+			.handle_incoming_seconded(ValidatorIndex(2), ValidatorIndex(3), candidate(1))
+			.unwrap();
+		assert_eq!(direct.sorted_accepted(), vec![(ValidatorIndex(3), vec![candidate(1)])]);
+	}
+
+	#[test]
+	fn zero_seconding_limit_rejected() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)];
+
+		assert!(DirectInGroup::new(group.clone(), ValidatorIndex(1), 0).is_none());
+		assert!(
+			DirectInGroup::with_limits(group.clone(), ValidatorIndex(1), vec![1, 0, 1]).is_none()
+		);
+
+		// a limit of 1 accepts exactly one candidate per originator.
+		let mut direct = DirectInGroup::new(group, ValidatorIndex(1), 1).unwrap();
+		for originator in [ValidatorIndex(2), ValidatorIndex(3)] {
+			assert_eq!(
+				direct.handle_incoming_seconded(ValidatorIndex(2), originator, candidate(1)),
+				Ok(AcceptIncoming::YesUnknown { slot: 0 })
+			);
+			assert!(direct
+				.handle_incoming_seconded(ValidatorIndex(3), originator, candidate(2))
+				.is_err());
+			assert_eq!(direct.accepted_candidates(originator), vec![candidate(1)]);
+		}
+	}
+
+	#[test]
+	fn accessors_match_constructor_inputs() {
+		let group = vec![ValidatorIndex(4), ValidatorIndex(7), ValidatorIndex(9)];
+
+		let direct = DirectInGroup::new(group.clone(), ValidatorIndex(7), 3).unwrap();
+		assert_eq!(direct.group(), &group[..]);
+		assert_eq!(direct.group_size(), 3);
+		assert_eq!(direct.seconding_limit(), 3);
+		assert_eq!(direct.seconding_limits(), &[3, 3, 3]);
+
+		let direct =
+			DirectInGroup::with_limits(group.clone(), ValidatorIndex(9), vec![1, 2, 5]).unwrap();
+		assert_eq!(direct.group(), &group[..]);
+		assert_eq!(direct.group_size(), 3);
+		assert_eq!(direct.seconding_limit(), 5);
+		assert_eq!(direct.seconding_limits(), &[1, 2, 5]);
+	}
+
+	#[test]
+	fn snapshots_reflect_accepted_statement() {
+		let mut tracker = DirectInGroup::new(
+			vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)],
+			ValidatorIndex(1),
+			2,
+		)
+		.unwrap();
+
+		assert_eq!(
+			tracker.handle_incoming_seconded(ValidatorIndex(2), ValidatorIndex(3), candidate(1)),
+			Ok(AcceptIncoming::YesUnknown { slot: 0 })
+		);
+
+		// the sender is the first row, as we are omitted, and the originator's slots
+		// follow the two slots of each preceding originator.
+		let mut incoming = [None; 2 * 6];
+		incoming[4] = Some(candidate(1));
+		assert_eq!(tracker.incoming_snapshot(), &incoming[..]);
+
+		let mut accepted = [None; 6];
+		accepted[4] = Some(candidate(1));
+		assert_eq!(tracker.accepted_snapshot(), &accepted[..]);
+	}
+
+	#[test]
+	fn group_membership() {
+		let group = vec![ValidatorIndex(4), ValidatorIndex(7), ValidatorIndex(9)];
+		let direct = DirectInGroup::new(group.clone(), ValidatorIndex(7), 2).unwrap();
+
+		assert_eq!(direct.our_validator_index(), ValidatorIndex(7));
+		for v in group {
+			assert!(direct.is_in_group(v));
+		}
+		for v in [ValidatorIndex(0), ValidatorIndex(5), ValidatorIndex(10)] {
+			assert!(!direct.is_in_group(v));
+		}
+	}
+
+	#[test]
+	fn accepted_slot_is_reported() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)];
+		let mut direct = DirectInGroup::new(group, ValidatorIndex(1), 3).unwrap();
+
+		for (n, slot) in [(1, 0), (2, 1), (3, 2)] {
+			let expected = Ok(AcceptIncoming::YesUnknown { slot });
+			assert_eq!(
+				direct.can_receive(ValidatorIndex(2), ValidatorIndex(3), candidate(n)),
+				expected
+			);
+			assert_eq!(
+				direct.handle_incoming_seconded(ValidatorIndex(2), ValidatorIndex(3), candidate(n)),
+				expected
+			);
+		}
+
+		// slots are relative to the originator's range, which starts at 3 for the
+		// second originator and at 6 for the third one.
+		assert_eq!(
+			direct.handle_incoming_seconded(ValidatorIndex(2), ValidatorIndex(2), candidate(4)),
+			Ok(AcceptIncoming::YesUnknown { slot: 0 })
+		);
+		assert_eq!(direct.accepted_snapshot()[3], Some(candidate(4)));
+		assert_eq!(direct.accepted_snapshot()[7], Some(candidate(2)));
+	}
+
+	#[test]
+	fn remap_group_retains_surviving_originators() {
+		let group = vec![ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)];
+		let mut direct = DirectInGroup::new(group, ValidatorIndex(1), 2).unwrap();
+
+		direct
+			.handle_incoming_seconded(ValidatorIndex(2), ValidatorIndex(3), candidate(1))
+			.unwrap();
+		direct
+			.handle_incoming_seconded(ValidatorIndex(3), ValidatorIndex(2), candidate(2))
+			.unwrap();
+		assert!(direct.note_sent(ValidatorIndex(3), candidate(3)).is_ok());
+
+		// validator 2 leaves the group, 4 joins it and 3 moves to the front.
+		let new_group = vec![ValidatorIndex(3), ValidatorIndex(4), ValidatorIndex(1)];
+		assert_eq!(direct.remap_group(Vec::new(), ValidatorIndex(1)), Err(RemapError::EmptyGroup));
+		assert_eq!(
+			direct.remap_group(new_group.clone(), ValidatorIndex(2)),
+			Err(RemapError::NotInGroup)
+		);
+		assert_eq!(direct.group(), &[ValidatorIndex(1), ValidatorIndex(2), ValidatorIndex(3)]);
+
+		assert_eq!(direct.remap_group(new_group.clone(), ValidatorIndex(1)), Ok(()));
+		assert_eq!(direct.group(), &new_group[..]);
+		assert_eq!(direct.our_validator_index(), ValidatorIndex(1));
+
+		assert_eq!(direct.accepted_candidates(ValidatorIndex(3)), vec![candidate(1)]);
+		assert!(direct.can_send(ValidatorIndex(3), candidate(1)));
+		assert_eq!(direct.all_accepted(), vec![(ValidatorIndex(3), candidate(1))]);
+		assert_eq!(direct.accepted_count(ValidatorIndex(4)), 0);
+
+		// candidate 1 is still known for validator 3, which was already sent candidate 3.
+		assert_eq!(
+			direct.handle_incoming_seconded(ValidatorIndex(3), ValidatorIndex(3), candidate(1)),
+			Ok(AcceptIncoming::YesKnown)
+		);
+		assert!(direct.can_send_to(ValidatorIndex(3), candidate(4)).is_ok());
+		assert!(direct.note_sent(ValidatorIndex(3), candidate(4)).is_ok());
+		assert!(matches!(
+			direct.note_sent(ValidatorIndex(3), candidate(5)),
+			Err(RejectOutgoing::RecipientExcess)
+		));
+
+		// the joining validator gets our limit.
+		assert_eq!(direct.seconding_limits(), &[2, 2, 2]);
+	}
+
+	#[test]
+	fn senders_of_lists_relaying_validators() {
+		let group: Vec<_> = (0..4).map(ValidatorIndex).collect();
+		let mut direct = DirectInGroup::new(group, ValidatorIndex(0), 2).unwrap();
+		let originator = ValidatorIndex(3);
+
+		assert!(direct.senders_of(originator, candidate(1)).is_empty());
+
+		for sender in [ValidatorIndex(3), ValidatorIndex(1)] {
+			direct.handle_incoming_seconded(sender, originator, candidate(1)).unwrap();
+		}
+		direct
+			.handle_incoming_seconded(ValidatorIndex(2), originator, candidate(2))
+			.unwrap();
+
+		assert_eq!(
+			direct.senders_of(originator, candidate(1)),
+			vec![ValidatorIndex(1), ValidatorIndex(3)]
+		);
+		assert_eq!(direct.senders_of(originator, candidate(2)), vec![ValidatorIndex(2)]);
+		assert!(direct.senders_of(ValidatorIndex(2), candidate(1)).is_empty());
+		assert!(direct.senders_of(ValidatorIndex(7), candidate(1)).is_empty());
+	}
+
+	#[test]
+	fn oversized_trackers_are_rejected() {
+		let group: Vec<_> = (0..4).map(ValidatorIndex).collect();
+
+		assert!(DirectInGroup::new(group.clone(), ValidatorIndex(0), usize::MAX / 2).is_none());
+		assert!(DirectInGroup::new(group.clone(), ValidatorIndex(0), usize::MAX).is_none());
+		assert!(DirectInGroup::with_limits(
+			group.clone(),
+			ValidatorIndex(0),
+			vec![1, 1, usize::MAX, 1]
+		)
+		.is_none());
+
+		// 3 senders, 1 row of accepted and 4 recipients of 2 slots each.
+		let slots = 3 * 8 + 8 + 4 * 2;
+		assert!(DirectInGroup::with_max_slots(group.clone(), ValidatorIndex(0), vec![2; 4], slots)
+			.is_some());
+		assert!(DirectInGroup::with_max_slots(
+			group.clone(),
+			ValidatorIndex(0),
+			vec![2; 4],
+			slots - 1
+		)
+		.is_none());
+
+		// the maximum carries over to group rotations.
+		let mut direct =
+			DirectInGroup::with_max_slots(group, ValidatorIndex(0), vec![2; 4], slots).unwrap();
+		let larger: Vec<_> = (0..5).map(ValidatorIndex).collect();
+		assert_eq!(direct.remap_group(larger, ValidatorIndex(0)), Err(RemapError::TooLarge));
+	}
+
+	fn builder(group: &[ValidatorIndex]) -> DirectInGroupBuilder {
+		DirectInGroup::builder().group(group.to_vec()).our_index(group[0])
+	}
+
+	#[test]
+	fn builder_matches_constructors() {
+		let group: Vec<_> = (0..3).map(ValidatorIndex).collect();
+
+		let direct = builder(&group).seconding_limit(2).build().unwrap();
+		assert_eq!(direct.group(), &group[..]);
+		assert_eq!(direct.our_validator_index(), ValidatorIndex(0));
+		assert_eq!(direct.seconding_limits(), &[2, 2, 2]);
+		assert!(direct.tracks_outgoing());
+		assert_eq!(
+			direct.memory_footprint(),
+			DirectInGroup::new(group.clone(), ValidatorIndex(0), 2)
+				.unwrap()
+				.memory_footprint()
+		);
+
+		let direct = builder(&group).per_validator_limits(vec![1, 2, 3]).build().unwrap();
+		assert_eq!(direct.seconding_limit(), 1);
+		assert_eq!(direct.seconding_limits(), &[1, 2, 3]);
+	}
+
+	#[test]
+	fn builder_rejects_invalid_setups() {
+		let group: Vec<_> = (0..3).map(ValidatorIndex).collect();
+
+		// missing or empty group.
+		assert!(DirectInGroup::builder()
+			.our_index(ValidatorIndex(0))
+			.seconding_limit(2)
+			.build()
+			.is_none());
+		// missing our index or our index not in the group.
+		assert!(DirectInGroup::builder()
+			.group(group.clone())
+			.seconding_limit(2)
+			.build()
+			.is_none());
+		assert!(builder(&group)
+			.our_index(ValidatorIndex(7))
+			.seconding_limit(2)
+			.build()
+			.is_none());
+		// neither or both kinds of limits.
+		assert!(builder(&group).build().is_none());
+		assert!(builder(&group)
+			.seconding_limit(2)
+			.per_validator_limits(vec![2; 3])
+			.build()
+			.is_none());
+		// invalid limits.
+		assert!(builder(&group).seconding_limit(0).build().is_none());
+		assert!(builder(&group).per_validator_limits(vec![2; 2]).build().is_none());
+		assert!(builder(&group).per_validator_limits(vec![2, 0, 2]).build().is_none());
+	}
+
+	#[test]
+	fn builder_max_slots_bounds_the_tracker() {
+		let group: Vec<_> = (0..3).map(ValidatorIndex).collect();
+		// 2 senders, 1 row of accepted and 3 recipients of 2 slots each.
+		let slots = 2 * 6 + 6 + 3 * 2;
+
+		assert!(builder(&group).seconding_limit(2).max_slots(slots).build().is_some());
+		assert!(builder(&group).seconding_limit(2).max_slots(slots - 1).build().is_none());
+		// without outgoing slots, fewer slots suffice.
+		assert!(builder(&group)
+			.seconding_limit(2)
+			.track_outgoing(false)
+			.max_slots(slots - 3 * 2)
+			.build()
+			.is_some());
+	}
+
+	#[test]
+	fn untracked_outgoing_is_unlimited() {
+		let group: Vec<_> = (0..3).map(ValidatorIndex).collect();
+		let mut direct = builder(&group).seconding_limit(1).track_outgoing(false).build().unwrap();
+		assert!(!direct.tracks_outgoing());
+		assert!(
+			direct.memory_footprint() <
+				DirectInGroup::new(group.clone(), ValidatorIndex(0), 1)
+					.unwrap()
+					.memory_footprint()
+		);
+
+		for n in 0..4 {
+			assert!(direct.note_sent(ValidatorIndex(1), candidate(n)).is_ok());
+			assert!(direct.can_send_to(ValidatorIndex(1), candidate(n)).is_ok());
+		}
+		assert!(matches!(
+			direct.note_sent(ValidatorIndex(0), candidate(1)),
+			Err(RejectOutgoing::NotInGroup)
+		));
+		assert!(matches!(
+			direct.can_send_to(ValidatorIndex(7), candidate(1)),
+			Err(RejectOutgoing::NotInGroup)
+		));
+
+		// the setting carries over to group rotations.
+		direct
+			.remap_group(vec![ValidatorIndex(0), ValidatorIndex(4)], ValidatorIndex(0))
+			.unwrap();
+		assert!(!direct.tracks_outgoing());
+		assert!(direct.note_sent(ValidatorIndex(4), candidate(1)).is_ok());
+		assert!(direct.note_sent(ValidatorIndex(4), candidate(2)).is_ok());
+	}
+}
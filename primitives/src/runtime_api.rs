@@ -221,5 +221,15 @@ sp_api::decl_runtime_apis! {
 		/// Returns all onchain disputes.
 		#[api_version(3)]
 		fn disputes() -> Vec<(v2::SessionIndex, v2::CandidateHash, v2::DisputeState<v2::BlockNumber>)>;
+
+		/// Returns the host configurations that are scheduled but not yet applied, SCALE-encoded,
+		/// along with the session index at which each of them takes effect, in session order.
+		#[api_version(3)]
+		fn pending_config_changes() -> Vec<(v2::SessionIndex, Vec<u8>)>;
+
+		/// Returns the members of the latest pending host configuration that differ from the active
+		/// one, as their name along with their SCALE-encoded active and pending value.
+		#[api_version(3)]
+		fn config_diff() -> Vec<(Vec<u8>, Vec<u8>, Vec<u8>)>;
 	}
 }
@@ -420,6 +420,80 @@ where
 	}
 }
 
+/// A member of the [`HostConfiguration`] that differs between two configurations: its name,
+/// along with its SCALE-encoded value in either configuration.
+pub type ConfigMemberDiff = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+impl<BlockNumber: Encode + PartialEq> HostConfiguration<BlockNumber> {
+	/// The members whose value in `other` differs from the one in this configuration, in
+	/// declaration order.
+	pub fn diff(&self, other: &Self) -> Vec<ConfigMemberDiff> {
+		// Destructures the configuration, such that a member missing here fails to compile.
+		macro_rules! diff_members {
+			($($member:ident),* $(,)?) => {{
+				let HostConfiguration { $($member),* } = self;
+				let mut diff = Vec::new();
+				$(
+					if *$member != other.$member {
+						diff.push((
+							stringify!($member).as_bytes().to_vec(),
+							$member.encode(),
+							other.$member.encode(),
+						));
+					}
+				)*
+				diff
+			}};
+		}
+
+		diff_members!(
+			max_code_size,
+			max_head_data_size,
+			max_upward_queue_count,
+			max_upward_queue_size,
+			max_upward_message_size,
+			max_upward_message_num_per_candidate,
+			hrmp_max_message_num_per_candidate,
+			validation_upgrade_cooldown,
+			validation_upgrade_delay,
+			max_pov_size,
+			max_downward_message_size,
+			ump_service_total_weight,
+			hrmp_max_parachain_outbound_channels,
+			hrmp_max_parathread_outbound_channels,
+			hrmp_sender_deposit,
+			hrmp_recipient_deposit,
+			hrmp_channel_max_capacity,
+			hrmp_channel_max_total_size,
+			hrmp_max_parachain_inbound_channels,
+			hrmp_max_parathread_inbound_channels,
+			hrmp_channel_max_message_size,
+			code_retention_period,
+			parathread_cores,
+			parathread_retries,
+			group_rotation_frequency,
+			chain_availability_period,
+			thread_availability_period,
+			scheduling_lookahead,
+			max_validators_per_core,
+			max_validators,
+			dispute_period,
+			dispute_post_conclusion_acceptance_period,
+			dispute_max_spam_slots,
+			dispute_conclusion_by_time_out_period,
+			no_show_slots,
+			n_delay_tranches,
+			zeroth_delay_tranche_width,
+			needed_approvals,
+			relay_vrf_modulo_samples,
+			ump_max_individual_weight,
+			pvf_checking_enabled,
+			pvf_voting_ttl,
+			minimum_validation_upgrade_delay,
+		)
+	}
+}
+
 pub trait WeightInfo {
 	fn set_config_with_block_number() -> Weight;
 	fn set_config_with_u32() -> Weight;
@@ -1194,6 +1268,22 @@ impl<T: Config> Pallet<T> {
 		<Self as Store>::ActiveConfig::set(config);
 	}
 
+	/// Returns the configuration changes that are scheduled but not yet applied, along with the
+	/// session index at which each of them takes effect, sorted ascending by session index.
+	pub fn pending_configs() -> Vec<(SessionIndex, HostConfiguration<T::BlockNumber>)> {
+		<PendingConfigs<T>>::get()
+	}
+
+	/// Returns the members of the latest pending configuration that differ from the active
+	/// configuration, i.e. the net effect of all scheduled changes. Empty if no change is
+	/// pending.
+	pub fn config_diff() -> Vec<ConfigMemberDiff> {
+		match <PendingConfigs<T>>::get().last() {
+			Some((_, pending)) => Self::config().diff(pending),
+			None => Vec::new(),
+		}
+	}
+
 	/// This function should be used to update members of the configuration.
 	///
 	/// This function is used to update the configuration in a way that is safe. It will check the
@@ -209,6 +209,66 @@ fn scheduled_session_config_update_while_next_session_pending() {
 	});
 }
 
+#[test]
+fn pending_configs_are_returned_in_session_order() {
+	new_test_ext(Default::default()).execute_with(|| {
+		let initial_config = Configuration::config();
+		let intermediate_config =
+			HostConfiguration { max_code_size: 1_000, ..initial_config.clone() };
+		let final_config =
+			HostConfiguration { max_code_size: 1_000, max_pov_size: 2_000, ..initial_config };
+
+		assert_eq!(Configuration::pending_configs(), vec![]);
+
+		assert_ok!(Configuration::set_max_code_size(RuntimeOrigin::root(), 1_000));
+		on_new_session(1);
+		assert_ok!(Configuration::set_max_pov_size(RuntimeOrigin::root(), 2_000));
+
+		assert_eq!(
+			Configuration::pending_configs(),
+			vec![(2, intermediate_config.clone()), (3, final_config.clone())]
+		);
+		assert_eq!(
+			crate::runtime_api_impl::vstaging::pending_config_changes::<Test>(),
+			vec![(2, intermediate_config.encode()), (3, final_config.encode())],
+		);
+
+		on_new_session(2);
+		assert_eq!(Configuration::pending_configs(), vec![(3, final_config)]);
+
+		on_new_session(3);
+		assert_eq!(Configuration::pending_configs(), vec![]);
+	});
+}
+
+#[test]
+fn config_diff_contains_changed_members_only() {
+	new_test_ext(Default::default()).execute_with(|| {
+		let active = Configuration::config();
+		assert_eq!(Configuration::config_diff(), vec![]);
+
+		assert_ok!(Configuration::set_max_code_size(RuntimeOrigin::root(), 1_000));
+		on_new_session(1);
+		assert_ok!(Configuration::set_max_pov_size(RuntimeOrigin::root(), 2_000));
+		// setting a member to its active value is no change.
+		assert_ok!(Configuration::set_no_show_slots(RuntimeOrigin::root(), active.no_show_slots));
+
+		let diff = Configuration::config_diff();
+		assert_eq!(
+			diff,
+			vec![
+				(b"max_code_size".to_vec(), active.max_code_size.encode(), 1_000u32.encode()),
+				(b"max_pov_size".to_vec(), active.max_pov_size.encode(), 2_000u32.encode()),
+			]
+		);
+		assert_eq!(crate::runtime_api_impl::vstaging::config_diff::<Test>(), diff);
+
+		on_new_session(2);
+		on_new_session(3);
+		assert_eq!(Configuration::config_diff(), vec![]);
+	});
+}
+
 #[test]
 fn invariants() {
 	new_test_ext(Default::default()).execute_with(|| {
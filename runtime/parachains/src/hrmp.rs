@@ -269,6 +269,9 @@ pub mod pallet {
 		OpenChannelAccepted(ParaId, ParaId),
 		/// HRMP channel closed. `[by_parachain, channel_id]`
 		ChannelClosed(ParaId, HrmpChannelId),
+		/// An HRMP channel was opened by Root, on behalf of both parties.
+		/// `[sender, recipient, max_capacity, max_message_size]`
+		OpenChannelForced(ParaId, ParaId, u32, u32),
 	}
 
 	#[pallet::error]
@@ -311,6 +314,8 @@ pub mod pallet {
 		OpenHrmpChannelAlreadyConfirmed,
 		/// The provided witness data is wrong.
 		WrongWitness,
+		/// More channels are to be force opened at once than a parachain may have.
+		ForceOpenHrmpChannelsLimitExceeded,
 	}
 
 	/// The set of pending HRMP open channel requests.
@@ -577,6 +582,52 @@ pub mod pallet {
 			Self::deposit_event(Event::OpenChannelCanceled(origin, channel_id));
 			Ok(())
 		}
+
+		/// Open HRMP channels between the given `(sender, recipient)` pairs, as if the sender had
+		/// requested each channel and the recipient had accepted it. Useful for onboarding paras
+		/// with a set of channels in a single governance motion.
+		///
+		/// The channels use the maximum capacity and message size allowed by the configuration.
+		/// The usual deposits are reserved from both parties and the channels are opened at the
+		/// next session change, like any other accepted request.
+		///
+		/// Each channel is charged like a request to open it followed by its acceptance. To keep
+		/// the weight bounded, no more channels can be passed than a single parachain may have
+		/// inbound and outbound.
+		///
+		/// Origin must be Root.
+		#[pallet::weight(
+			<T as Config>::WeightInfo::hrmp_init_open_channel()
+				.saturating_add(<T as Config>::WeightInfo::hrmp_accept_open_channel())
+				.saturating_mul(channels.len() as u64)
+		)]
+		pub fn force_open_hrmp_channels(
+			origin: OriginFor<T>,
+			channels: Vec<(ParaId, ParaId)>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			let config = configuration::Pallet::<T>::config();
+			ensure!(
+				channels.len() as u32 <=
+					config
+						.hrmp_max_parachain_inbound_channels
+						.saturating_add(config.hrmp_max_parachain_outbound_channels),
+				Error::<T>::ForceOpenHrmpChannelsLimitExceeded
+			);
+			let (max_capacity, max_message_size) =
+				(config.hrmp_channel_max_capacity, config.hrmp_channel_max_message_size);
+			for (sender, recipient) in channels {
+				Self::init_open_channel(sender, recipient, max_capacity, max_message_size)?;
+				Self::accept_open_channel(recipient, sender)?;
+				Self::deposit_event(Event::OpenChannelForced(
+					sender,
+					recipient,
+					max_capacity,
+					max_message_size,
+				));
+			}
+			Ok(())
+		}
 	}
 }
 
@@ -17,7 +17,7 @@
 use super::*;
 use crate::mock::{
 	new_test_ext, Configuration, Hrmp, MockGenesisConfig, Paras, ParasShared,
-	RuntimeEvent as MockEvent, System, Test,
+	RuntimeEvent as MockEvent, RuntimeOrigin, System, Test,
 };
 use frame_support::{assert_noop, assert_ok, traits::Currency as _};
 use primitives::v2::BlockNumber;
@@ -617,3 +617,49 @@ fn cancel_pending_open_channel_request() {
 		Hrmp::assert_storage_consistency_exhaustive();
 	});
 }
+
+#[test]
+fn force_open_channels_works() {
+	let para_a = 1.into();
+	let para_b = 2.into();
+	let para_c = 3.into();
+
+	new_test_ext(GenesisConfigBuilder::default().build()).execute_with(|| {
+		register_parachain(para_a);
+		register_parachain(para_b);
+		register_parachain(para_c);
+		run_to_block(5, Some(vec![4, 5]));
+
+		let channels = vec![(para_a, para_b), (para_b, para_c)];
+		assert_noop!(
+			Hrmp::force_open_hrmp_channels(RuntimeOrigin::signed(1), channels.clone()),
+			sp_runtime::DispatchError::BadOrigin,
+		);
+
+		// A parachain may have at most two inbound and two outbound channels.
+		let too_many = vec![(para_a, para_b); 5];
+		assert_noop!(
+			Hrmp::force_open_hrmp_channels(RuntimeOrigin::root(), too_many),
+			Error::<Test>::ForceOpenHrmpChannelsLimitExceeded,
+		);
+
+		assert_ok!(Hrmp::force_open_hrmp_channels(RuntimeOrigin::root(), channels));
+		Hrmp::assert_storage_consistency_exhaustive();
+		assert!(System::events().iter().any(|record| record.event ==
+			MockEvent::Hrmp(Event::OpenChannelForced(para_b, para_c, 2, 8))));
+
+		// Both parties pay their deposits, like for a regular request.
+		assert_eq!(
+			<Test as Config>::Currency::free_balance(&para_b.into_account_truncating()),
+			800
+		);
+
+		// The channels are opened at the next session change.
+		run_to_block(6, None);
+		assert!(!channel_exists(para_a, para_b));
+		run_to_block(8, Some(vec![8]));
+		assert!(channel_exists(para_a, para_b));
+		assert!(channel_exists(para_b, para_c));
+		Hrmp::assert_storage_consistency_exhaustive();
+	});
+}
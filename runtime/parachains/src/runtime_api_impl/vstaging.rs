@@ -16,7 +16,8 @@
 
 //! Put implementations of functions from staging APIs here.
 
-use crate::disputes;
+use crate::{configuration, disputes};
+use parity_scale_codec::Encode;
 use primitives::v2::{CandidateHash, DisputeState, SessionIndex};
 use sp_std::prelude::*;
 
@@ -25,3 +26,16 @@ pub fn get_session_disputes<T: disputes::Config>(
 ) -> Vec<(SessionIndex, CandidateHash, DisputeState<T::BlockNumber>)> {
 	<disputes::Pallet<T>>::disputes()
 }
+
+/// Implementation for `pending_config_changes` function from the runtime API
+pub fn pending_config_changes<T: configuration::Config>() -> Vec<(SessionIndex, Vec<u8>)> {
+	<configuration::Pallet<T>>::pending_configs()
+		.into_iter()
+		.map(|(session, config)| (session, config.encode()))
+		.collect()
+}
+
+/// Implementation for `config_diff` function from the runtime API
+pub fn config_diff<T: configuration::Config>() -> Vec<configuration::ConfigMemberDiff> {
+	<configuration::Pallet<T>>::config_diff()
+}
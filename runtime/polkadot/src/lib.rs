@@ -2094,6 +2094,27 @@ mod test_fees {
 		assert!(payout_weight * 2f64 < block_weight);
 	}
 
+	#[test]
+	fn spend_funds_without_bounties_is_cheaper() {
+		use pallet_bounties::WeightInfo;
+		type Weights = <Runtime as pallet_bounties::Config>::WeightInfo;
+
+		assert!(Weights::spend_funds(0).ref_time() < Weights::spend_funds(1).ref_time());
+	}
+
+	#[test]
+	fn force_open_hrmp_channels_weight_scales_with_channels() {
+		let weight = |n: u32| {
+			let channels = (0..n).map(|i| (i.into(), (i + 1).into())).collect();
+			parachains_hrmp::Call::<Runtime>::force_open_hrmp_channels { channels }
+				.get_dispatch_info()
+				.weight
+		};
+
+		assert!(weight(1).ref_time() > 0);
+		assert_eq!(weight(3), weight(1).saturating_mul(3));
+	}
+
 	#[test]
 	fn block_cost() {
 		let max_block_weight = BlockWeights::get().max_block;
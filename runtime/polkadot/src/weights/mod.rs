@@ -54,3 +54,6 @@ pub mod runtime_parachains_hrmp;
 pub mod runtime_parachains_initializer;
 pub mod runtime_parachains_paras;
 pub mod runtime_parachains_paras_inherent;
+
+#[cfg(test)]
+mod storage_annotations;
@@ -0,0 +1,149 @@
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that the `// Storage:` annotations of generated weight files agree with the
+//! database reads and writes the weight functions charge for.
+//!
+//! Reads and writes scaling with a component are counted once, as the annotations
+//! list the accesses of a single iteration.
+
+/// Database accesses of a weight function.
+#[derive(Debug, Default, PartialEq)]
+struct Accesses {
+	reads: u64,
+	writes: u64,
+}
+
+/// Annotated and charged accesses of a weight function.
+#[derive(Debug)]
+struct WeightFn {
+	name: String,
+	annotated: Accesses,
+	charged: Accesses,
+}
+
+/// Parses `N` out of `marker` followed by `N` in `s`, e.g. `r:N`.
+fn number_after(s: &str, marker: &str) -> Option<u64> {
+	let rest = &s[s.find(marker)? + marker.len()..];
+	let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+	digits.parse().ok()
+}
+
+/// Parses the accesses annotated by `// Storage: Pallet Item (r:N w:M)`.
+fn annotated(line: &str) -> Option<Accesses> {
+	let line = line.strip_prefix("// Storage:")?;
+	Some(Accesses { reads: number_after(line, "(r:")?, writes: number_after(line, " w:")? })
+}
+
+/// Parses the accesses charged by `T::DbWeight::get().reads(N as u64)` and the like,
+/// including `reads((N as u64).saturating_mul(c as u64))`.
+fn charged(line: &str) -> Accesses {
+	let count = |marker| number_after(&line.replace("((", "("), marker).unwrap_or(0);
+	Accesses {
+		reads: count("T::DbWeight::get().reads("),
+		writes: count("T::DbWeight::get().writes("),
+	}
+}
+
+/// Parses the weight functions of a generated weight file.
+fn weight_fns(source: &str) -> Vec<WeightFn> {
+	let mut fns: Vec<WeightFn> = Vec::new();
+	let mut pending = Accesses::default();
+
+	for line in source.lines().map(str::trim) {
+		if let Some(accesses) = annotated(line) {
+			pending.reads += accesses.reads;
+			pending.writes += accesses.writes;
+		} else if let Some(signature) = line.strip_prefix("fn ") {
+			let name = signature.split('(').next().unwrap_or_default().to_string();
+			let annotated = std::mem::take(&mut pending);
+			fns.push(WeightFn { name, annotated, charged: Accesses::default() });
+		} else if let Some(current) = fns.last_mut() {
+			let accesses = charged(line);
+			current.charged.reads += accesses.reads;
+			current.charged.writes += accesses.writes;
+		}
+	}
+
+	fns
+}
+
+fn assert_annotations_match(file: &str, source: &str) {
+	let fns = weight_fns(source);
+	assert!(!fns.is_empty(), "no weight functions found in {}", file);
+
+	for f in fns {
+		assert_eq!(
+			f.annotated, f.charged,
+			"storage annotations of `{}` in {} disagree with the charged accesses",
+			f.name, file,
+		);
+	}
+}
+
+#[test]
+fn parses_annotated_and_charged_accesses() {
+	let source = "
+		// Storage: Bounties BountyApprovals (r:1 w:1)
+		// Storage: System Account (r:2 w:2)
+		fn spend_funds(b: u32, ) -> Weight {
+			Weight::from_ref_time(13_392_000 as u64)
+				.saturating_add(T::DbWeight::get().reads(1 as u64))
+				.saturating_add(T::DbWeight::get().reads((2 as u64).saturating_mul(b as u64)))
+				.saturating_add(T::DbWeight::get().writes(3 as u64))
+		}
+		fn noop() -> Weight {
+			Weight::from_ref_time(1_000 as u64)
+		}
+	";
+
+	let fns = weight_fns(source);
+	assert_eq!(fns.len(), 2);
+	assert_eq!(fns[0].name, "spend_funds");
+	assert_eq!(fns[0].annotated, Accesses { reads: 3, writes: 3 });
+	assert_eq!(fns[0].charged, Accesses { reads: 3, writes: 3 });
+	assert_eq!(fns[1].name, "noop");
+	assert_eq!(fns[1].annotated, Accesses::default());
+	assert_eq!(fns[1].charged, Accesses::default());
+}
+
+#[test]
+#[should_panic(expected = "storage annotations of `approve_bounty`")]
+fn drift_is_detected() {
+	assert_annotations_match(
+		"test",
+		"
+		// Storage: Bounties Bounties (r:1 w:1)
+		fn approve_bounty() -> Weight {
+			Weight::from_ref_time(11_513_000 as u64)
+				.saturating_add(T::DbWeight::get().reads(2 as u64))
+				.saturating_add(T::DbWeight::get().writes(1 as u64))
+		}
+		",
+	);
+}
+
+#[test]
+fn pallet_bounties_annotations_match() {
+	assert_annotations_match("pallet_bounties.rs", include_str!("pallet_bounties.rs"));
+}
+
+#[test]
+fn runtime_parachains_configuration_annotations_match() {
+	assert_annotations_match(
+		"runtime_parachains_configuration.rs",
+		include_str!("runtime_parachains_configuration.rs"),
+	);
+}
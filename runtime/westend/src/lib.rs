@@ -1432,6 +1432,14 @@ sp_api::impl_runtime_apis! {
 		fn disputes() -> Vec<(SessionIndex, CandidateHash, DisputeState<BlockNumber>)> {
 			runtime_parachains::runtime_api_impl::vstaging::get_session_disputes::<Runtime>()
 		}
+
+		fn pending_config_changes() -> Vec<(SessionIndex, Vec<u8>)> {
+			runtime_parachains::runtime_api_impl::vstaging::pending_config_changes::<Runtime>()
+		}
+
+		fn config_diff() -> Vec<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+			runtime_parachains::runtime_api_impl::vstaging::config_diff::<Runtime>()
+		}
 	}
 
 	impl beefy_primitives::BeefyApi<Block> for Runtime {